@@ -0,0 +1,109 @@
+use std::{panic, thread};
+
+use core_affinity::CoreId;
+use rayon::{ThreadPool, ThreadPoolBuildError, ThreadPoolBuilder};
+
+use crate::Executor;
+
+/// Builds a Rayon pool of `threads` workers with worker `i` pinned to core
+/// `cores[i % cores.len()]`, or to the `i`-th core the OS reports when
+/// `cores` is empty.
+///
+/// Run the sort inside [`ThreadPool::install`] to keep the OS from
+/// migrating its workers between cores (or between CCXs or sockets), which
+/// otherwise dominates the variance of runs at high thread counts. Workers
+/// that cannot be pinned, e.g. on platforms without affinity support, run
+/// unpinned.
+pub fn pinned_thread_pool(threads: usize, cores: &[usize]) -> Result<ThreadPool, ThreadPoolBuildError> {
+    let cores: Vec<usize> = match cores {
+        [] => core_affinity::get_core_ids().unwrap_or_default().into_iter().map(|c| c.id).collect(),
+        cores => cores.to_vec(),
+    };
+    ThreadPoolBuilder::new()
+        .num_threads(threads)
+        .start_handler(move |worker| {
+            if !cores.is_empty() {
+                core_affinity::set_for_current(CoreId { id: cores[worker % cores.len()] });
+            }
+        })
+        .build()
+}
+
+/// An [`Executor`] with one thread per entry of `cores`, pinned to it, where
+/// task `i` of every phase runs on `cores[i % cores.len()]`.
+///
+/// The fixed mapping is what lets
+/// [`PsrsConfig::core_weights`](crate::PsrsConfig::core_weights) give the
+/// fast cores the big chunks; a work-stealing pool would hand them out to
+/// whichever core is free. Threads are spawned per phase, like
+/// [`ScopedThreadExecutor`](crate::ScopedThreadExecutor).
+#[derive(Debug, Clone, Default)]
+pub struct PinnedExecutor {
+    pub cores: Vec<usize>,
+}
+
+impl Executor for PinnedExecutor {
+    fn num_threads(&self) -> usize {
+        self.cores.len().max(1)
+    }
+
+    fn for_each_index(&self, n: usize, task: &(dyn Fn(usize) + Sync)) {
+        if self.cores.is_empty() {
+            (0..n).for_each(task);
+            return;
+        }
+        let workers = self.cores.len().min(n);
+        thread::scope(|s| {
+            let handles: Vec<_> = self.cores[..workers]
+                .iter()
+                .enumerate()
+                .map(|(worker, &core)| {
+                    s.spawn(move || {
+                        core_affinity::set_for_current(CoreId { id: core });
+                        (worker..n).step_by(workers).for_each(task);
+                    })
+                })
+                .collect();
+            // Join every thread before re-raising the first panic's payload.
+            let results: Vec<_> = handles.into_iter().map(|handle| handle.join()).collect();
+            if let Some(Err(payload)) = results.into_iter().find(Result::is_err) {
+                panic::resume_unwind(payload);
+            }
+        });
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::testing::std_sorted;
+
+    #[test]
+    fn pool_workers_are_pinned_to_their_cores() {
+        let pool = pinned_thread_pool(3, &[0]).unwrap();
+        assert_eq!(pool.current_num_threads(), 3);
+        // On Linux the reported cores are the calling thread's affinity mask.
+        #[cfg(target_os = "linux")]
+        for cores in pool.broadcast(|_| core_affinity::get_core_ids().unwrap_or_default()) {
+            assert_eq!(cores, [CoreId { id: 0 }]);
+        }
+
+        let values = crate::testing::random_values(50_000, u64::MAX, 17);
+        let mut data = values.clone();
+        pool.install(|| crate::psrs(&mut data, 3));
+        assert_eq!(data, std_sorted(&values));
+    }
+
+    #[test]
+    fn pinned_executor_keeps_tasks_on_their_worker() {
+        let exec = PinnedExecutor { cores: vec![0, 0, 0] };
+        let threads: Vec<std::sync::Mutex<Option<thread::ThreadId>>> = (0..10).map(|_| Default::default()).collect();
+        exec.for_each_index(10, &|i| *threads[i].lock().unwrap() = Some(thread::current().id()));
+        let threads: Vec<thread::ThreadId> = threads.into_iter().map(|t| t.into_inner().unwrap().unwrap()).collect();
+        for i in 0..10 {
+            for j in 0..10 {
+                assert_eq!(threads[i] == threads[j], i % 3 == j % 3, "tasks {i} and {j}");
+            }
+        }
+    }
+}
@@ -0,0 +1,119 @@
+//! Arrow sort kernels backed by PSRS.
+//!
+//! Both kernels follow Arrow's default `SortOptions`: ascending with nulls
+//! first. Floats are ordered by `f64::total_cmp`.
+
+use arrow_array::types::{Float64Type, UInt32Type, UInt64Type};
+use arrow_array::{Array, ArrowPrimitiveType, PrimitiveArray, UInt32Array};
+use arrow_buffer::{BooleanBuffer, NullBuffer};
+
+use crate::{f64_bits_to_key, psrs_f64, psrs_radix};
+
+/// Arrow primitive types that can be sorted by PSRS.
+pub trait PsrsArrowType: ArrowPrimitiveType {
+    /// Sorts the native values in place.
+    fn sort_values(values: &mut [Self::Native], p: usize);
+
+    /// Maps a native value onto a `u64` with the same ordering.
+    fn sort_key(value: Self::Native) -> u64;
+}
+
+impl PsrsArrowType for UInt32Type {
+    fn sort_values(values: &mut [u32], p: usize) {
+        psrs_radix(values, p);
+    }
+
+    fn sort_key(value: u32) -> u64 {
+        value as u64
+    }
+}
+
+impl PsrsArrowType for UInt64Type {
+    fn sort_values(values: &mut [u64], p: usize) {
+        psrs_radix(values, p);
+    }
+
+    fn sort_key(value: u64) -> u64 {
+        value
+    }
+}
+
+impl PsrsArrowType for Float64Type {
+    fn sort_values(values: &mut [f64], p: usize) {
+        psrs_f64(values, p);
+    }
+
+    fn sort_key(value: f64) -> u64 {
+        f64_bits_to_key(value.to_bits())
+    }
+}
+
+/// Returns a sorted copy of `array`, with its nulls moved to the front.
+pub fn sort<T: PsrsArrowType>(array: &PrimitiveArray<T>, p: usize) -> PrimitiveArray<T> {
+    let nulls = array.null_count();
+    let mut values = vec![T::Native::default(); nulls];
+    values.reserve(array.len() - nulls);
+    values.extend(array.iter().flatten());
+    T::sort_values(&mut values[nulls..], p);
+
+    let validity = (nulls > 0)
+        .then(|| NullBuffer::new(BooleanBuffer::collect_bool(values.len(), |i| i >= nulls)));
+    PrimitiveArray::new(values.into(), validity)
+}
+
+/// Returns the indices that would sort `array`, null slots first.
+///
+/// Equal values keep their original relative order. Panics if `array` is
+/// longer than `u32::MAX`, matching Arrow's own `sort_to_indices`.
+pub fn sort_to_indices<T: PsrsArrowType>(array: &PrimitiveArray<T>, p: usize) -> UInt32Array {
+    let len = u32::try_from(array.len()).expect("array too long for u32 indices");
+    let mut indices = Vec::with_capacity(array.len());
+    let mut pairs = Vec::with_capacity(array.len() - array.null_count());
+    for (i, value) in (0..len).zip(array.iter()) {
+        match value {
+            Some(v) => pairs.push((T::sort_key(v), i)),
+            None => indices.push(i),
+        }
+    }
+
+    // The index breaks ties, which also makes the result stable.
+    psrs_radix(&mut pairs, p);
+    indices.extend(pairs.into_iter().map(|(_, i)| i));
+    UInt32Array::from(indices)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::testing::edge_cases;
+
+    #[test]
+    fn kernels_match_a_stable_sort_with_nulls_first() {
+        for p in [1, 4, 7] {
+            for (name, values) in edge_cases(p) {
+                let options: Vec<Option<u64>> =
+                    values.iter().map(|&v| (v % 7 != 0).then_some(v % 1_000)).collect();
+                let array = PrimitiveArray::<UInt64Type>::from(options.clone());
+
+                let mut expected = options.clone();
+                expected.sort();
+                let sorted: Vec<Option<u64>> = sort(&array, p).iter().collect();
+                assert_eq!(sorted, expected, "{name}, p = {p}");
+
+                let mut order: Vec<u32> = (0..options.len() as u32).collect();
+                order.sort_by_key(|&i| options[i as usize]);
+                assert_eq!(sort_to_indices(&array, p).values().to_vec(), order, "{name}, p = {p}");
+            }
+        }
+    }
+
+    #[test]
+    fn floats_sort_by_total_order() {
+        let values = [Some(1.5), None, Some(f64::NAN), Some(-0.0), Some(0.0), Some(f64::NEG_INFINITY), None];
+        let array = PrimitiveArray::<Float64Type>::from(values.to_vec());
+        let sorted: Vec<Option<u64>> = sort(&array, 2).iter().map(|v| v.map(f64::to_bits)).collect();
+        let expected = [None, None, Some(f64::NEG_INFINITY), Some(-0.0), Some(0.0), Some(1.5), Some(f64::NAN)];
+        assert_eq!(sorted, expected.map(|v| v.map(f64::to_bits)));
+        assert_eq!(sort_to_indices(&array, 2).values().to_vec(), [1, 6, 5, 3, 4, 0, 2]);
+    }
+}
@@ -0,0 +1,104 @@
+//! Async wrappers for calling PSRS from Tokio applications.
+//!
+//! The sort itself is CPU-bound, so it runs on Tokio's blocking thread pool
+//! (which in turn fans out to the sort's [`Executor`](crate::Executor)) while
+//! the calling task yields back to the runtime.
+
+use std::panic;
+
+use tokio::sync::mpsc::UnboundedSender;
+use tokio::task;
+
+use crate::{psrs, psrs_with_progress, Progress};
+
+/// Sorts `data` with [`psrs`] without blocking the async runtime.
+///
+/// Takes ownership of the vector so it can move to the blocking pool, and
+/// hands it back sorted. A panic during the sort is resumed in the caller.
+pub async fn psrs_async<T>(mut data: Vec<T>, p: usize) -> Vec<T>
+where
+    T: Clone + Ord + Send + Sync + 'static,
+{
+    run_blocking(move || {
+        psrs(&mut data, p);
+        data
+    })
+    .await
+}
+
+/// Like [`psrs_async`], but forwards every [`Progress`] event to `progress`,
+/// e.g. to stream status to a websocket. Events sent after the receiver is
+/// dropped are ignored.
+pub async fn psrs_async_with_progress<T>(
+    mut data: Vec<T>,
+    p: usize,
+    progress: UnboundedSender<Progress>,
+) -> Vec<T>
+where
+    T: Clone + Ord + Send + Sync + 'static,
+{
+    run_blocking(move || {
+        psrs_with_progress(&mut data, p, |event| {
+            let _ = progress.send(event);
+        });
+        data
+    })
+    .await
+}
+
+async fn run_blocking<R: Send + 'static>(f: impl FnOnce() -> R + Send + 'static) -> R {
+    match task::spawn_blocking(f).await {
+        Ok(result) => result,
+        Err(err) => match err.try_into_panic() {
+            Ok(payload) => panic::resume_unwind(payload),
+            Err(err) => panic!("sort task failed: {err}"),
+        },
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::testing::std_sorted;
+    use crate::Phase;
+
+    fn block_on<F: std::future::Future>(future: F) -> F::Output {
+        tokio::runtime::Builder::new_current_thread().build().unwrap().block_on(future)
+    }
+
+    #[test]
+    fn async_progress_reaches_the_receiver() {
+        let data = crate::testing::random_values(20_000, u64::MAX, 26);
+        let expected = std_sorted(&data);
+        let (tx, mut rx) = tokio::sync::mpsc::unbounded_channel();
+        assert_eq!(block_on(psrs_async_with_progress(data, 4, tx)), expected);
+        let mut events = Vec::new();
+        while let Ok(event) = rx.try_recv() {
+            events.push(event);
+        }
+        assert_eq!(events.first(), Some(&Progress::PhaseStarted(Phase::LocalSort)));
+        assert_eq!(events.last(), Some(&Progress::Finished));
+    }
+
+    #[test]
+    #[should_panic(expected = "comparison failed")]
+    fn panics_resume_in_the_caller() {
+        /// Orders nothing: every comparison panics.
+        #[derive(Clone, PartialEq, Eq)]
+        struct Faulty(u8);
+
+        impl PartialOrd for Faulty {
+            fn partial_cmp(&self, other: &Self) -> Option<std::cmp::Ordering> {
+                Some(self.cmp(other))
+            }
+        }
+
+        impl Ord for Faulty {
+            fn cmp(&self, _: &Self) -> std::cmp::Ordering {
+                panic!("comparison failed")
+            }
+        }
+
+        block_on(psrs_async(vec![Faulty(1), Faulty(2)], 1));
+    }
+}
@@ -0,0 +1,59 @@
+#[cfg(feature = "serde")]
+use serde::{Deserialize, Serialize};
+
+/// How evenly a PSRS run split its input, as reported by
+/// [`psrs_with_stats`](crate::psrs_with_stats).
+#[derive(Debug, Clone, Default, PartialEq)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+pub struct PartitionStats {
+    /// Elements in each of the `p` partitions after Phase 3, before small
+    /// ones are coalesced for merging.
+    pub sizes: Vec<usize>,
+    /// Largest partition over the ideal `n / p`; 1.0 is perfect balance.
+    pub imbalance: f64,
+    /// Pivots equal to their predecessor, a sign of heavy duplicates.
+    pub duplicate_pivots: usize,
+}
+
+impl PartitionStats {
+    pub(crate) fn new(sizes: Vec<usize>, duplicate_pivots: usize) -> Self {
+        let n: usize = sizes.iter().sum();
+        let max = sizes.iter().copied().max().unwrap_or(0);
+        let imbalance = if n == 0 { 1.0 } else { max as f64 * sizes.len() as f64 / n as f64 };
+        PartitionStats { sizes, imbalance, duplicate_pivots }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::testing::{edge_cases, random_values};
+    use crate::{psrs_with_stats, PsrsConfig};
+
+    #[test]
+    fn stats_cover_every_element() {
+        let config = PsrsConfig::with_threads(5);
+        for (name, mut data) in edge_cases(5) {
+            let stats = psrs_with_stats(&mut data, &config);
+            assert_eq!(stats.sizes.iter().sum::<usize>(), data.len(), "{name}");
+            assert!(stats.imbalance >= 1.0, "{name}: {stats:?}");
+        }
+    }
+
+    #[test]
+    fn duplicates_show_up_as_duplicate_pivots() {
+        let config = PsrsConfig::with_threads(8);
+        let stats = psrs_with_stats(&mut random_values(50_000, 2, 12), &config);
+        assert!(stats.duplicate_pivots > 0, "{stats:?}");
+        let stats = psrs_with_stats(&mut random_values(50_000, u64::MAX, 13), &config);
+        assert_eq!(stats.duplicate_pivots, 0, "{stats:?}");
+        assert_eq!(stats.sizes.len(), 8);
+    }
+
+    #[test]
+    fn imbalance_is_the_largest_share_over_the_ideal() {
+        assert_eq!(PartitionStats::new(vec![10, 10, 10, 10], 0).imbalance, 1.0);
+        assert_eq!(PartitionStats::new(vec![20, 0, 10, 10], 1).imbalance, 2.0);
+        assert_eq!(PartitionStats::new(vec![0, 0], 0).imbalance, 1.0);
+    }
+}
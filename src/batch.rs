@@ -0,0 +1,49 @@
+use std::cmp::Reverse;
+
+use rayon::prelude::*;
+
+use crate::psrs;
+
+/// Chunks smaller than this are not worth a parallel phase of their own.
+const MIN_CHUNK_LEN: usize = 1 << 14;
+
+/// Sorts every array in `batches` on `pool`, keeping the pool busy without
+/// oversubscribing it.
+///
+/// Each array gets a share of the pool's threads proportional to its share
+/// of the elements, and at most one per [`MIN_CHUNK_LEN`] elements: with
+/// many arrays most are sorted serially, one per task, while a few large
+/// ones are split into PSRS chunks. The largest arrays are started first so
+/// a big one does not end up running alone at the end.
+pub fn psrs_batch<T: Clone + Ord + Send + Sync>(batches: &mut [&mut [T]], pool: &rayon::ThreadPool) {
+    let threads = pool.current_num_threads();
+    let total: usize = batches.iter().map(|b| b.len()).sum();
+    let mut jobs: Vec<&mut [T]> = batches.iter_mut().map(|b| &mut **b).collect();
+    jobs.sort_unstable_by_key(|b| Reverse(b.len()));
+    pool.install(|| {
+        jobs.into_par_iter().with_max_len(1).for_each(|batch| {
+            let share = (threads * batch.len()).div_ceil(total.max(1));
+            let p = share.min(batch.len() / MIN_CHUNK_LEN).max(1);
+            psrs(batch, p);
+        })
+    });
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::testing::{edge_cases, std_sorted};
+
+    #[test]
+    fn every_array_matches_std_sort() {
+        let pool = rayon::ThreadPoolBuilder::new().num_threads(4).build().unwrap();
+        let mut arrays: Vec<Vec<u64>> = edge_cases(4).into_iter().map(|(_, values)| values).collect();
+        // One array large enough to be split into PSRS chunks.
+        arrays.push(crate::testing::random_values(100_000, u64::MAX, 21));
+        let expected: Vec<Vec<u64>> = arrays.iter().map(|a| std_sorted(a)).collect();
+        let mut batches: Vec<&mut [u64]> = arrays.iter_mut().map(Vec::as_mut_slice).collect();
+        psrs_batch(&mut batches, &pool);
+        assert_eq!(arrays, expected);
+        psrs_batch::<u64>(&mut [], &pool);
+    }
+}
@@ -0,0 +1,80 @@
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::Arc;
+
+/// Handle that sets how many workers a running sort may use, adjustable
+/// from another thread while it runs.
+///
+/// The sort reads the budget at the start of every phase and runs that
+/// many of its tasks at once. Lowering it mid-phase also takes effect
+/// early: workers above the new budget retire once their current task is
+/// done, while a raise waits for the next phase. The chunk count stays
+/// whatever the config asked for, so the output does not depend on the
+/// budget.
+///
+/// Clones share the same budget, so one can drive the sort while another
+/// stays with e.g. a monitor that yields cores when the host gets busy.
+#[derive(Debug, Clone)]
+pub struct ThreadBudget {
+    threads: Arc<AtomicUsize>,
+}
+
+impl ThreadBudget {
+    pub fn new(threads: usize) -> Self {
+        ThreadBudget { threads: Arc::new(AtomicUsize::new(threads.max(1))) }
+    }
+
+    /// Changes the budget of every sort observing this handle. At least one
+    /// worker always keeps going.
+    pub fn set(&self, threads: usize) {
+        self.threads.store(threads.max(1), Ordering::Relaxed);
+    }
+
+    pub fn get(&self) -> usize {
+        self.threads.load(Ordering::Relaxed)
+    }
+}
+
+/// Clones of one handle are equal; separately created budgets are not,
+/// whatever their values.
+impl PartialEq for ThreadBudget {
+    fn eq(&self, other: &Self) -> bool {
+        Arc::ptr_eq(&self.threads, &other.threads)
+    }
+}
+
+impl Eq for ThreadBudget {}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::testing::{random_values, std_sorted};
+    use crate::{psrs_with_budget, PsrsConfig};
+
+    #[test]
+    fn budget_clamps_and_clones_share_it() {
+        let budget = ThreadBudget::new(0);
+        assert_eq!(budget.get(), 1);
+        let clone = budget.clone();
+        clone.set(6);
+        assert_eq!(budget.get(), 6);
+        assert_eq!(budget, clone);
+        assert_ne!(budget, ThreadBudget::new(6));
+    }
+
+    #[test]
+    fn budget_can_change_while_sorting() {
+        let budget = ThreadBudget::new(8);
+        let values = random_values(500_000, u64::MAX, 10);
+        let mut data = values.clone();
+        std::thread::scope(|s| {
+            s.spawn(|| {
+                for threads in [1, 4, 2, 8, 1] {
+                    std::thread::sleep(std::time::Duration::from_millis(1));
+                    budget.set(threads);
+                }
+            });
+            psrs_with_budget(&mut data, &PsrsConfig::with_threads(8), &budget);
+        });
+        assert_eq!(data, std_sorted(&values));
+    }
+}
@@ -0,0 +1,44 @@
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::Arc;
+
+/// Handle used to abort a running sort from another thread.
+///
+/// Clones share the same flag, so one clone can be handed to the sort while
+/// another stays with e.g. a UI "Cancel" button.
+#[derive(Debug, Clone, Default)]
+pub struct CancellationToken {
+    cancelled: Arc<AtomicBool>,
+}
+
+impl CancellationToken {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Requests cancellation of every sort observing this token.
+    pub fn cancel(&self) {
+        self.cancelled.store(true, Ordering::Relaxed);
+    }
+
+    pub fn is_cancelled(&self) -> bool {
+        self.cancelled.load(Ordering::Relaxed)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::testing::std_sorted;
+    use crate::{try_psrs, PsrsError};
+
+    #[test]
+    fn cancelled_sort_keeps_the_elements() {
+        let token = CancellationToken::new();
+        token.clone().cancel();
+        assert!(token.is_cancelled());
+        let values = crate::testing::random_values(100_000, u64::MAX, 9);
+        let mut data = values.clone();
+        assert_eq!(try_psrs(&mut data, 4, &token), Err(PsrsError::Cancelled));
+        assert_eq!(std_sorted(&data), std_sorted(&values));
+    }
+}
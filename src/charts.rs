@@ -0,0 +1,133 @@
+//! Charts for the benchmark harness, drawn with `plotters`.
+
+use std::collections::BTreeMap;
+use std::error::Error;
+use std::path::Path;
+
+use plotters::coord::Shift;
+use plotters::prelude::*;
+
+use crate::{RunResult, ScalingRow};
+
+const SIZE: (u32, u32) = (1024, 768);
+
+/// Writes `runtime_vs_size` and `speedup_vs_threads` charts into `dir`.
+/// `format` is the file extension, either `svg` or `png`.
+pub fn write_charts(dir: &Path, format: &str, runs: &[RunResult], scaling: &[ScalingRow]) -> Result<(), Box<dyn Error>> {
+    std::fs::create_dir_all(dir)?;
+    let runtime = dir.join(format!("runtime_vs_size.{format}"));
+    let speedup = dir.join(format!("speedup_vs_threads.{format}"));
+    match format {
+        "svg" => {
+            runtime_vs_size(SVGBackend::new(&runtime, SIZE).into_drawing_area(), runs)?;
+            speedup_vs_threads(SVGBackend::new(&speedup, SIZE).into_drawing_area(), scaling)?;
+        }
+        "png" => {
+            runtime_vs_size(BitMapBackend::new(&runtime, SIZE).into_drawing_area(), runs)?;
+            speedup_vs_threads(BitMapBackend::new(&speedup, SIZE).into_drawing_area(), scaling)?;
+        }
+        other => return Err(format!("unsupported chart format {other:?}").into()),
+    }
+    Ok(())
+}
+
+/// One line per `(algorithm, threads, distribution, element type)`,
+/// plotting median measured runtime against input length.
+fn runtime_vs_size<DB: DrawingBackend>(root: DrawingArea<DB, Shift>, runs: &[RunResult]) -> Result<(), Box<dyn Error>>
+where
+    DB::ErrorType: 'static,
+{
+    let mut series: BTreeMap<(String, usize, String), BTreeMap<usize, Vec<f64>>> = BTreeMap::new();
+    for run in runs.iter().filter(|r| !r.warm_up) {
+        series
+            .entry((
+                run.algorithm.clone(),
+                run.config.threads,
+                format!("{:?} {:?}", run.workload.distribution, run.workload.element),
+            ))
+            .or_default()
+            .entry(run.workload.data_len)
+            .or_default()
+            .push(run.runtime_ms);
+    }
+    let lines: Vec<(String, Vec<(f64, f64)>)> = series
+        .into_iter()
+        .map(|((algorithm, threads, distribution), points)| {
+            let points = points
+                .into_iter()
+                .map(|(len, mut times)| {
+                    times.sort_by(f64::total_cmp);
+                    (len as f64, times[times.len() / 2])
+                })
+                .collect();
+            (format!("{algorithm} ({threads} threads, {distribution})"), points)
+        })
+        .collect();
+
+    draw_lines(root, "Runtime vs input size", "elements", "runtime (ms)", &lines, None)
+}
+
+/// One line per parallel algorithm and workload, plotting speedup against
+/// thread count, next to the ideal linear speedup.
+fn speedup_vs_threads<DB: DrawingBackend>(root: DrawingArea<DB, Shift>, scaling: &[ScalingRow]) -> Result<(), Box<dyn Error>>
+where
+    DB::ErrorType: 'static,
+{
+    let mut series: BTreeMap<String, Vec<(f64, f64)>> = BTreeMap::new();
+    for row in scaling.iter().filter(|r| r.speedup.is_finite()) {
+        let name = format!(
+            "{} ({} {:?}, {:?})",
+            row.algorithm, row.workload.data_len, row.workload.element, row.workload.distribution
+        );
+        series.entry(name).or_default().push((row.threads as f64, row.speedup));
+    }
+    let lines: Vec<(String, Vec<(f64, f64)>)> = series.into_iter().collect();
+    let max_threads = scaling.iter().map(|r| r.threads).max().unwrap_or(1) as f64;
+
+    draw_lines(root, "Speedup vs thread count", "threads", "speedup", &lines, Some(max_threads))
+}
+
+/// Draws each named series as a line with point markers. `ideal` adds a
+/// dashed `y = x` reference up to that x value.
+fn draw_lines<DB: DrawingBackend>(
+    root: DrawingArea<DB, Shift>,
+    caption: &str,
+    x_desc: &str,
+    y_desc: &str,
+    lines: &[(String, Vec<(f64, f64)>)],
+    ideal: Option<f64>,
+) -> Result<(), Box<dyn Error>>
+where
+    DB::ErrorType: 'static,
+{
+    root.fill(&WHITE)?;
+    let points = || lines.iter().flat_map(|(_, points)| points.iter());
+    let x_max = points().map(|p| p.0).fold(ideal.unwrap_or(0.0), f64::max).max(1.0);
+    let y_max = points().map(|p| p.1).fold(ideal.unwrap_or(0.0), f64::max).max(1.0);
+
+    let mut chart = ChartBuilder::on(&root)
+        .caption(caption, ("sans-serif", 28))
+        .margin(20)
+        .x_label_area_size(50)
+        .y_label_area_size(70)
+        .build_cartesian_2d(0.0..x_max * 1.05, 0.0..y_max * 1.1)?;
+    chart.configure_mesh().x_desc(x_desc).y_desc(y_desc).draw()?;
+
+    if let Some(max) = ideal {
+        chart
+            .draw_series(DashedLineSeries::new([(0.0, 0.0), (max, max)], 8, 6, BLACK.into()))?
+            .label("ideal")
+            .legend(|(x, y)| PathElement::new([(x, y), (x + 20, y)], BLACK));
+    }
+    for (i, (name, points)) in lines.iter().enumerate() {
+        let color = Palette99::pick(i).to_rgba();
+        chart
+            .draw_series(LineSeries::new(points.iter().copied(), color.stroke_width(2)))?
+            .label(name)
+            .legend(move |(x, y)| PathElement::new([(x, y), (x + 20, y)], color.stroke_width(2)));
+        chart.draw_series(points.iter().map(|&p| Circle::new(p, 4, color.filled())))?;
+    }
+    chart.configure_series_labels().background_style(WHITE.mix(0.8)).border_style(BLACK).draw()?;
+    root.present()?;
+    Ok(())
+}
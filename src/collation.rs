@@ -0,0 +1,101 @@
+//! Locale-aware string order through ICU4X, for the `icu` feature.
+
+use std::cmp::Ordering;
+use std::fmt;
+
+use icu_collator::options::CollatorOptions;
+use icu_collator::CollatorBorrowed;
+use icu_locale_core::Locale;
+
+use crate::psrs_by;
+
+/// A collator for one locale, built from ICU4X's compiled-in data.
+#[derive(Debug)]
+pub struct Collation {
+    collator: CollatorBorrowed<'static>,
+}
+
+/// Reasons [`Collation::new`] can fail.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum CollationError {
+    /// The locale is not a valid BCP 47 language tag.
+    InvalidLocale(String),
+    /// ICU4X has no collation data for the locale.
+    MissingData(String),
+}
+
+impl fmt::Display for CollationError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            CollationError::InvalidLocale(message) => write!(f, "invalid locale: {message}"),
+            CollationError::MissingData(message) => write!(f, "no collation data: {message}"),
+        }
+    }
+}
+
+impl std::error::Error for CollationError {}
+
+impl Collation {
+    /// The collator for `locale`, a BCP 47 tag such as `"de"` or
+    /// `"sv-SE"`, with the locale's default options.
+    pub fn new(locale: &str) -> Result<Self, CollationError> {
+        Collation::with_options(locale, CollatorOptions::default())
+    }
+
+    /// Like [`Collation::new`], with e.g. a different
+    /// [`Strength`](icu_collator::options::Strength).
+    pub fn with_options(locale: &str, options: CollatorOptions) -> Result<Self, CollationError> {
+        let locale = Locale::try_from_str(locale).map_err(|e| CollationError::InvalidLocale(e.to_string()))?;
+        let collator = CollatorBorrowed::try_new((&locale).into(), options)
+            .map_err(|e| CollationError::MissingData(e.to_string()))?;
+        Ok(Collation { collator })
+    }
+
+    pub fn compare(&self, a: &str, b: &str) -> Ordering {
+        self.collator.compare(a, b)
+    }
+}
+
+/// Sorts strings by PSRS in the order `collation` defines rather than by
+/// bytes.
+///
+/// A collated comparison costs far more than a byte compare, so the local
+/// sorts and merges dominate and spread well across threads.
+pub fn psrs_collated<S>(data: &mut [S], p: usize, collation: &Collation)
+where
+    S: AsRef<str> + Clone + Send + Sync,
+{
+    psrs_by(data, p, |a, b| collation.compare(a.as_ref(), b.as_ref()));
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::testing::edge_cases;
+
+    const WORDS: [&str; 8] = ["zebra", "Äpfel", "apple", "Zürich", "öl", "Ol", "ähnlich", "Apfel"];
+
+    #[test]
+    fn collated_sort_matches_a_serial_sort() {
+        let collation = Collation::new("de").unwrap();
+        for p in [1, 4, 7] {
+            for (name, values) in edge_cases(p) {
+                let mut words: Vec<String> =
+                    values.iter().take(5_000).map(|&v| format!("{}{}", WORDS[(v % 8) as usize], v % 5)).collect();
+                let mut expected = words.clone();
+                expected.sort_by(|a, b| collation.compare(a, b));
+                psrs_collated(&mut words, p, &collation);
+                assert_eq!(words, expected, "{name}, p = {p}");
+            }
+        }
+    }
+
+    #[test]
+    fn locales_order_umlauts_their_own_way() {
+        let (german, swedish) = (Collation::new("de").unwrap(), Collation::new("sv").unwrap());
+        // German sorts "ö" with "o"; Swedish puts it after "z".
+        assert_eq!(german.compare("öl", "zebra"), Ordering::Less);
+        assert_eq!(swedish.compare("öl", "zebra"), Ordering::Greater);
+        assert!(matches!(Collation::new("not a locale!"), Err(CollationError::InvalidLocale(_))));
+    }
+}
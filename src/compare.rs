@@ -0,0 +1,125 @@
+//! `compare` subcommand: diffs two saved benchmark reports configuration by
+//! configuration and flags significant regressions.
+
+use std::fs;
+
+use crate::{t_critical_95, BenchReport, RunStats, Workload};
+
+/// How one configuration changed between the two reports.
+struct Delta<'a> {
+    stats: &'a RunStats,
+    old_median: f64,
+    new_median: f64,
+    /// Change of the median, in percent of the old one.
+    change: f64,
+    /// Welch's t statistic of the new runtimes against the old ones.
+    t: f64,
+    /// Whether the means differ at the 95% level.
+    significant: bool,
+}
+
+fn usage() -> ! {
+    eprintln!(
+        "usage: compare old.json new.json [--threshold percent]\n\
+         Flags configurations whose median runtime changed by more than the threshold \
+         (default 5%) with a significant difference in means (Welch's t-test, 95%). \
+         Exits with status 1 if any of them got slower."
+    );
+    std::process::exit(2)
+}
+
+/// Runs `compare` with the arguments that follow the subcommand name.
+pub fn main(mut args: impl Iterator<Item = String>) {
+    let mut paths = Vec::new();
+    let mut threshold = 5.0;
+    while let Some(arg) = args.next() {
+        match arg.as_str() {
+            "--threshold" => {
+                threshold = args.next().and_then(|t| t.parse().ok()).unwrap_or_else(|| usage());
+            }
+            _ => paths.push(arg),
+        }
+    }
+    let [old_path, new_path] = paths.as_slice() else { usage() };
+    let old = load_report(old_path);
+    let new = load_report(new_path);
+
+    println!("algorithm\tthreads\tn\tdistribution\telement\told (ms)\tnew (ms)\tchange\tt\tverdict");
+    let mut regressions = 0;
+    for stats in &new.stats {
+        let Some(old_stats) = old.stats.iter().find(|s| same_config(s, stats)) else {
+            continue;
+        };
+        let delta = delta(&old, old_stats, &new, stats);
+        let verdict = match delta.change {
+            _ if !delta.significant => "",
+            c if c > threshold => {
+                regressions += 1;
+                "REGRESSION"
+            }
+            c if c < -threshold => "improvement",
+            _ => "",
+        };
+        let Workload { data_len, distribution, element } = delta.stats.workload;
+        println!(
+            "{}\t{}\t{data_len}\t{distribution:?}\t{element:?}\t{:.3}\t{:.3}\t{:+.1}%\t{:.2}\t{verdict}",
+            delta.stats.algorithm, delta.stats.threads, delta.old_median, delta.new_median, delta.change, delta.t
+        );
+    }
+    for (from, to, to_path) in [(&old, &new, new_path), (&new, &old, old_path)] {
+        for s in from.stats.iter().filter(|s| !to.stats.iter().any(|t| same_config(t, s))) {
+            let Workload { data_len, distribution, element } = s.workload;
+            println!(
+                "{} with {} threads on {data_len} {element:?} {distribution:?} is missing from {to_path}",
+                s.algorithm, s.threads
+            );
+        }
+    }
+    if regressions > 0 {
+        eprintln!("{regressions} regression(s) beyond {threshold}%");
+        std::process::exit(1);
+    }
+}
+
+fn load_report(path: &str) -> BenchReport {
+    let text = fs::read_to_string(path).unwrap_or_else(|e| panic!("failed to read {path}: {e}"));
+    serde_json::from_str(&text).unwrap_or_else(|e| panic!("invalid report {path}: {e}"))
+}
+
+fn same_config(a: &RunStats, b: &RunStats) -> bool {
+    a.algorithm == b.algorithm && a.workload == b.workload && a.threads == b.threads
+}
+
+/// Measured runtimes of the configuration `stats` summarizes.
+fn runtimes(report: &BenchReport, stats: &RunStats) -> Vec<f64> {
+    report
+        .runs
+        .iter()
+        .filter(|r| !r.warm_up && r.algorithm == stats.algorithm && r.workload == stats.workload)
+        .filter(|r| r.config.threads == stats.threads)
+        .map(|r| r.runtime_ms)
+        .collect()
+}
+
+fn delta<'a>(old: &BenchReport, old_stats: &RunStats, new: &BenchReport, new_stats: &'a RunStats) -> Delta<'a> {
+    let (a, b) = (runtimes(old, old_stats), runtimes(new, new_stats));
+    let mean_var = |x: &[f64]| {
+        let n = x.len() as f64;
+        let mean = x.iter().sum::<f64>() / n;
+        (mean, x.iter().map(|v| (v - mean).powi(2)).sum::<f64>() / (n - 1.0), n)
+    };
+    let ((mean_a, var_a, n_a), (mean_b, var_b, n_b)) = (mean_var(&a), mean_var(&b));
+    let (se_a, se_b) = (var_a / n_a, var_b / n_b);
+    let t = (mean_b - mean_a) / (se_a + se_b).sqrt();
+    // Welch–Satterthwaite degrees of freedom.
+    let df = (se_a + se_b).powi(2) / (se_a.powi(2) / (n_a - 1.0) + se_b.powi(2) / (n_b - 1.0));
+    let significant = a.len() > 1 && b.len() > 1 && t.abs() > t_critical_95(df.round().max(1.0) as usize);
+    Delta {
+        stats: new_stats,
+        old_median: old_stats.median,
+        new_median: new_stats.median,
+        change: (new_stats.median - old_stats.median) / old_stats.median * 100.0,
+        t,
+        significant,
+    }
+}
@@ -0,0 +1,228 @@
+#[cfg(feature = "serde")]
+use serde::{Deserialize, Serialize};
+
+use crate::{core_weights, CacheSizes};
+#[cfg(feature = "serde")]
+use crate::TuningProfile;
+
+/// Upper bound on the oversubscription [`PsrsConfig::tuned_for`] picks.
+const MAX_OVERSUBSCRIPTION: usize = 16;
+
+/// [`PsrsConfig::tuned_for`] keeps at least this many input elements per
+/// sample.
+const MIN_ELEMENTS_PER_SAMPLE: usize = 16;
+
+/// Tuning knobs for a PSRS run.
+///
+/// With the `serde` feature the config can be stored alongside experiment
+/// files; missing fields fall back to their defaults.
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize), serde(default))]
+pub struct PsrsConfig {
+    /// Number of workers the sort is meant for. The input is split into
+    /// `threads * oversubscription` chunks, which is also the number of
+    /// partitions merged in Phase 4.
+    pub threads: usize,
+    /// Chunks (and partitions) per thread. Above 1, a slow chunk or a large
+    /// partition is one of several tasks its worker runs and the scheduler
+    /// balances the rest around it, instead of it alone setting the length
+    /// of the phase. The sample grows with the square of the chunk count.
+    pub oversubscription: usize,
+    /// Adjacent Phase 4 partitions are grouped into one merge task until the
+    /// group holds at least this many elements. With large `threads` and
+    /// narrow key ranges many partitions are nearly empty, and merging them
+    /// one task each costs more in scheduling and heap setup than it saves.
+    pub min_partition_size: usize,
+    /// Scan the input in parallel first and skip the sort if it is already
+    /// in order, or just reverse it if it is in reverse order. Costs one
+    /// read of the input when it is not presorted.
+    pub check_presorted: bool,
+    /// Structure picking the next element in the Phase 4 merges.
+    pub merge: MergeStructure,
+    /// Caps on the workers of individual phases.
+    pub phase_threads: PhaseThreads,
+    /// Samples drawn per chunk, in multiples of the chunk count. More
+    /// samples place the pivots closer to the true quantiles, at the cost
+    /// of a larger serial sample sort.
+    pub oversampling: usize,
+    /// Inputs with fewer elements are sorted serially, as are inputs with
+    /// fewer elements than chunks.
+    pub serial_cutoff: usize,
+    /// Relative speed of the core each chunk is meant for, cycled over the
+    /// chunks; empty for equal cores. Chunk `i` and partition `i` then get
+    /// shares of the input in proportion to their weight, so on hybrid CPUs
+    /// the efficiency cores finish Phases 1 and 4 with the performance
+    /// cores. This only helps if task `i` of every phase runs on that core,
+    /// as with `PinnedExecutor` (with the `affinity` feature); see
+    /// [`PsrsConfig::for_cores`]. Uneven shares need a few times the
+    /// samples to hit, so raise `oversampling` with them.
+    pub core_weights: Vec<u32>,
+}
+
+/// How many workers may run the tasks of a phase at once; `None` uses every
+/// worker of the executor.
+///
+/// The chunk and partition counts still follow
+/// [`PsrsConfig::chunks`]; a cap only limits how many of them are in
+/// flight. The memory-bound merge often saturates bandwidth with fewer
+/// threads than the compute-bound local sort wants.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize), serde(default))]
+pub struct PhaseThreads {
+    /// Phase 1, the chunk sorts.
+    pub local_sort: Option<usize>,
+    /// Phase 3, splitting the chunks at the pivots.
+    pub boundaries: Option<usize>,
+    /// Phase 4, the partition merges.
+    pub merge: Option<usize>,
+}
+
+/// How a k-way merge finds the smallest head among its `k` slices. Which
+/// is fastest depends on `k` and on how expensive comparisons are, so the
+/// harness can compare them.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize), serde(rename_all = "snake_case"))]
+pub enum MergeStructure {
+    /// `std`'s `BinaryHeap`: a pop and a push per element.
+    #[default]
+    BinaryHeap,
+    /// A 4-ary heap whose top is replaced in place: half the depth of a
+    /// binary heap, at up to four comparisons per level.
+    QuaternaryHeap,
+    /// A tournament (loser) tree: exactly `log2 k` comparisons per element,
+    /// which pays off when comparisons are expensive.
+    LoserTree,
+}
+
+impl Default for PsrsConfig {
+    fn default() -> Self {
+        PsrsConfig {
+            threads: crate::default_executor().num_threads(),
+            oversubscription: 1,
+            min_partition_size: 4096,
+            check_presorted: false,
+            merge: MergeStructure::BinaryHeap,
+            phase_threads: PhaseThreads::default(),
+            oversampling: 1,
+            serial_cutoff: 0,
+            core_weights: Vec::new(),
+        }
+    }
+}
+
+impl PsrsConfig {
+    pub fn with_threads(threads: usize) -> Self {
+        PsrsConfig { threads, ..Self::default() }
+    }
+
+    /// A config with a chunk for each of `cores`, weighted by the
+    /// [`core_weights`] detected for them (equal if detection fails or they
+    /// are all alike, else with 8x oversampling), for sorting on a
+    /// `PinnedExecutor` over the same cores.
+    pub fn for_cores(cores: &[usize]) -> Self {
+        let detected = core_weights();
+        let weights: Option<Vec<u32>> = cores.iter().map(|&core| detected.get(core).copied()).collect();
+        let core_weights = weights.filter(|w| w.iter().any(|&x| x != w[0])).unwrap_or_default();
+        let oversampling = if core_weights.is_empty() { 1 } else { 8 };
+        PsrsConfig { core_weights, oversampling, ..PsrsConfig::with_threads(cores.len()) }
+    }
+
+    /// Number of chunks the input is split into.
+    pub fn chunks(&self) -> usize {
+        self.threads.max(1) * self.oversubscription.max(1)
+    }
+
+    /// A config for sorting `data` on `threads` workers: the closest entry
+    /// of the installed [`TuningProfile`] (with the `serde` feature), or
+    /// else one sized from the [`CacheSizes`] of the machine.
+    ///
+    /// Without a profile, inputs that fit in L2 are sorted serially.
+    /// Otherwise the chunk count is raised through
+    /// [`oversubscription`](Self::oversubscription) until each chunk fits in
+    /// half of L2, so the Phase 1 sorts run in cache, as long as the `p²`
+    /// samples stay small next to the input.
+    pub fn tuned_for<T>(data: &[T], threads: usize) -> Self {
+        let n = data.len();
+        let bytes = std::mem::size_of_val(data);
+        #[cfg(feature = "serde")]
+        if let Some(config) = TuningProfile::installed().and_then(|profile| profile.config_for(bytes, threads)) {
+            return config;
+        }
+        let caches = CacheSizes::detect();
+        if threads <= 1 || bytes <= caches.l2 {
+            return PsrsConfig::with_threads(1);
+        }
+        let wanted_chunks = bytes.div_ceil((caches.l2 / 2).max(1));
+        let mut oversubscription = wanted_chunks.div_ceil(threads).clamp(1, MAX_OVERSUBSCRIPTION);
+        while oversubscription > 1 && (threads * oversubscription).pow(2) > n / MIN_ELEMENTS_PER_SAMPLE {
+            oversubscription -= 1;
+        }
+        PsrsConfig { oversubscription, ..PsrsConfig::with_threads(threads) }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::testing::std_sorted;
+    use crate::psrs_with_config;
+
+    #[test]
+    fn oversubscription_multiplies_the_chunks() {
+        // Splits evenly into every chunk count below, so no remainder chunk is added.
+        let data = crate::testing::random_values(72_000, u64::MAX, 16);
+        for oversubscription in [1, 3, 8] {
+            let config = PsrsConfig { oversubscription, ..PsrsConfig::with_threads(3) };
+            assert_eq!(config.chunks(), 3 * oversubscription);
+            let timeline = crate::Timeline::new();
+            crate::psrs_with_timeline(&mut data.clone(), &config, &timeline);
+            let events = timeline.events();
+            let sorts = events.iter().filter(|e| e.phase == crate::Phase::LocalSort && e.task.is_some()).count();
+            assert_eq!(sorts, config.chunks(), "oversubscription = {oversubscription}");
+        }
+    }
+
+    #[test]
+    fn tuned_configs_keep_chunks_in_cache_and_samples_small() {
+        // An installed profile would take precedence over the cache sizes.
+        #[cfg(feature = "serde")]
+        if TuningProfile::installed().is_some() {
+            return;
+        }
+        let l2 = CacheSizes::detect().l2;
+        assert_eq!(PsrsConfig::tuned_for(&vec![0u8; l2], 8).threads, 1, "inputs in L2 are sorted serially");
+        assert_eq!(PsrsConfig::tuned_for(&vec![0u64; l2], 1).threads, 1);
+
+        let mut data = crate::testing::random_values(l2 / 2, u64::MAX, 24);
+        let config = PsrsConfig::tuned_for(&data, 4);
+        assert_eq!(config.threads, 4);
+        assert!((1..=MAX_OVERSUBSCRIPTION).contains(&config.oversubscription), "{config:?}");
+        assert!(config.oversubscription == 1 || config.chunks().pow(2) <= data.len() / MIN_ELEMENTS_PER_SAMPLE);
+        let expected = std_sorted(&data);
+        psrs_with_config(&mut data, &config);
+        assert_eq!(data, expected);
+    }
+
+    #[cfg(feature = "serde")]
+    #[test]
+    fn configs_round_trip_and_default_missing_fields() {
+        let config = PsrsConfig {
+            merge: MergeStructure::LoserTree,
+            phase_threads: PhaseThreads { merge: Some(2), ..PhaseThreads::default() },
+            core_weights: vec![2, 1],
+            ..PsrsConfig::with_threads(6)
+        };
+        let json = serde_json::to_string(&config).unwrap();
+        assert!(json.contains(r#""merge":"loser_tree""#), "{json}");
+        assert_eq!(serde_json::from_str::<PsrsConfig>(&json).unwrap(), config);
+
+        let partial: PsrsConfig =
+            serde_json::from_str(r#"{"threads": 3, "phase_threads": {"local_sort": 1}}"#).unwrap();
+        let expected = PsrsConfig {
+            phase_threads: PhaseThreads { local_sort: Some(1), ..PhaseThreads::default() },
+            ..PsrsConfig::with_threads(3)
+        };
+        assert_eq!(partial, expected);
+    }
+}
+
@@ -0,0 +1,280 @@
+//! Parallel counting sort for integers drawn from a narrow range.
+
+use std::ops::RangeInclusive;
+
+use crate::executor::{self, default_executor, Executor};
+use crate::{psrs_radix, radix_sort, RadixKey};
+
+/// Largest number of distinct values [`psrs_integers`] will count; wider
+/// ranges go through [`psrs_radix`].
+pub const COUNTING_MAX_RANGE: usize = 1 << 16;
+
+/// Integers that can be counted by their offset from the smallest value.
+pub trait CountingKey: RadixKey {
+    /// `self - min` as an unsigned distance. Requires `min <= self`.
+    fn offset_from(self, min: Self) -> u128;
+
+    /// The value `offset` above `min`.
+    fn add_offset(min: Self, offset: usize) -> Self;
+}
+
+macro_rules! impl_counting_key {
+    ($($t:ty => $unsigned:ty;)*) => {$(
+        impl CountingKey for $t {
+            #[inline]
+            fn offset_from(self, min: Self) -> u128 {
+                (self as $unsigned).wrapping_sub(min as $unsigned) as u128
+            }
+
+            #[inline]
+            fn add_offset(min: Self, offset: usize) -> Self {
+                (min as $unsigned).wrapping_add(offset as $unsigned) as $t
+            }
+        }
+    )*};
+}
+
+impl_counting_key! {
+    u8 => u8;
+    u16 => u16;
+    u32 => u32;
+    u64 => u64;
+    u128 => u128;
+    i8 => u8;
+    i16 => u16;
+    i32 => u32;
+    i64 => u64;
+    i128 => u128;
+}
+
+/// Sorts integers known to lie in `range` with a parallel counting sort
+/// over `p` chunks. If some value turns out to be outside `range`, `data` is
+/// left untouched by the counting pass and sorted by [`psrs_radix`] instead.
+pub fn psrs_counting<T: CountingKey>(data: &mut [T], p: usize, range: RangeInclusive<T>) {
+    let (min, max) = range.into_inner();
+    let span = usize::try_from(max.offset_from(min)).ok().and_then(|s| s.checked_add(1));
+    match span {
+        Some(span) if min <= max && counting_sort(default_executor(), data, p, min, span) => {}
+        _ => psrs_radix(data, p),
+    }
+}
+
+/// Sorts integers by PSRS, first scanning for the value range in parallel:
+/// if it spans at most [`COUNTING_MAX_RANGE`] values (and no more than the
+/// input length) a counting sort replaces the whole PSRS pipeline.
+pub fn psrs_integers<T: CountingKey>(data: &mut [T], p: usize) {
+    let exec = default_executor();
+    let Some((min, max)) = value_range(exec, data) else { return };
+    let span = max.offset_from(min).saturating_add(1);
+    if span <= COUNTING_MAX_RANGE.min(data.len()) as u128 {
+        psrs_counting(data, p, min..=max);
+    } else {
+        psrs_radix(data, p);
+    }
+}
+
+/// Whether `T` is `u8` or `u16`, whose whole range is cheap to count, so
+/// that [`sort_small_integers`] sorts it. `castaway` checks the type at
+/// compile time without requiring `T: 'static` for a `TypeId`, so the
+/// generic entry points keep their bounds; any other type, even one with
+/// the same layout, is left alone.
+pub(crate) fn is_small_integer<T>() -> bool {
+    let empty: &[T] = &[];
+    castaway::cast!(empty, &[u8]).is_ok() || castaway::cast!(empty, &[u16]).is_ok()
+}
+
+/// Sorts `data` on `exec` if `T` is `u8` or `u16`, and returns whether it
+/// did: by counting, or by a serial [`radix_sort`] if it is shorter than
+/// its value range. It never goes through the PSRS pipeline, so the entry
+/// points can call it from there.
+pub(crate) fn sort_small_integers<T>(exec: &dyn Executor, data: &mut [T], p: usize) -> bool {
+    let data = match castaway::cast!(data, &mut [u8]) {
+        Ok(bytes) => {
+            count_or_radix_sort(exec, bytes, p);
+            return true;
+        }
+        Err(data) => data,
+    };
+    match castaway::cast!(data, &mut [u16]) {
+        Ok(words) => count_or_radix_sort(exec, words, p),
+        Err(_) => return false,
+    }
+    true
+}
+
+fn count_or_radix_sort<T: CountingKey>(exec: &dyn Executor, data: &mut [T], p: usize) {
+    let Some((min, max)) = value_range(exec, data) else { return };
+    let span = max.offset_from(min) as usize + 1;
+    if span > data.len() || !counting_sort(exec, data, p, min, span) {
+        radix_sort(data);
+    }
+}
+
+/// The smallest and largest value in `data`, scanned in parallel; `None`
+/// if it is empty.
+fn value_range<T: CountingKey>(exec: &dyn Executor, data: &[T]) -> Option<(T, T)> {
+    let chunk_size = executor::even_chunk_size(exec, data.len());
+    let bounds = executor::par_chunks_map(exec, data, chunk_size, |_, chunk| {
+        chunk.iter().fold(None, |acc: Option<(T, T)>, &v| match acc {
+            None => Some((v, v)),
+            Some((lo, hi)) => Some((lo.min(v), hi.max(v))),
+        })
+    });
+    bounds.into_iter().flatten().reduce(|(a, b), (c, d)| (a.min(c), b.max(d)))
+}
+
+/// Counts every chunk's values in `min..min + span` in parallel, then
+/// rewrites `data` chunk by chunk from the prefix sums. Returns `false`
+/// without writing anything if a value falls outside the range.
+fn counting_sort<T: CountingKey>(exec: &dyn Executor, data: &mut [T], p: usize, min: T, span: usize) -> bool {
+    let n = data.len();
+    if n == 0 {
+        return true;
+    }
+    let chunk_size = n.div_ceil(p.max(1));
+
+    // Per-chunk histograms.
+    let histograms = executor::par_chunks_map(exec, data, chunk_size, |_, chunk| {
+        let mut counts = vec![0usize; span];
+        for &v in chunk {
+            let offset = v.offset_from(min);
+            if offset >= span as u128 {
+                return None;
+            }
+            counts[offset as usize] += 1;
+        }
+        Some(counts)
+    });
+    let Some(histograms) = histograms.into_iter().collect::<Option<Vec<_>>>() else {
+        return false;
+    };
+
+    // starts[v] is the first output index holding offset v.
+    let mut starts = Vec::with_capacity(span + 1);
+    let mut total = 0;
+    starts.push(0);
+    for v in 0..span {
+        total += histograms.iter().map(|h| h[v]).sum::<usize>();
+        starts.push(total);
+    }
+
+    // Every chunk fills its own index range with the runs overlapping it.
+    executor::par_chunks_mut_map(exec, data, chunk_size, |chunk_idx, chunk| {
+        let start = chunk_idx * chunk_size;
+        let end = start + chunk.len();
+        let mut pos = start;
+        let mut v = starts.partition_point(|&s| s <= pos) - 1;
+        while pos < end {
+            let run_end = starts[v + 1].min(end);
+            chunk[pos - start..run_end - start].fill(T::add_offset(min, v));
+            pos = run_end;
+            v += 1;
+        }
+    });
+    true
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::testing::{edge_cases, random_values, std_sorted};
+
+    #[test]
+    fn integers_match_std_sort() {
+        for p in [1, 4, 7] {
+            for (name, values) in edge_cases(p) {
+                let mut narrow: Vec<u32> = values.iter().map(|&v| (v % 1000) as u32).collect();
+                let expected = std_sorted(&narrow);
+                psrs_integers(&mut narrow, p);
+                assert_eq!(narrow, expected, "{name}, p = {p}");
+
+                let mut wide: Vec<i64> = values.iter().map(|&v| v as i64).collect();
+                let expected = std_sorted(&wide);
+                psrs_integers(&mut wide, p);
+                assert_eq!(wide, expected, "{name} (wide), p = {p}");
+            }
+        }
+    }
+
+    #[test]
+    fn counting_falls_back_outside_range() {
+        let mut data: Vec<u16> = random_values(10_000, 5_000, 5).iter().map(|&v| v as u16).collect();
+        let expected = std_sorted(&data);
+        psrs_counting(&mut data, 4, 0..=100);
+        assert_eq!(data, expected);
+    }
+
+    #[test]
+    fn small_integers_dispatch_through_psrs() {
+        for p in [1, 3, 8] {
+            for (name, values) in edge_cases(p) {
+                let mut bytes: Vec<u8> = values.iter().map(|&v| v as u8).collect();
+                let expected = std_sorted(&bytes);
+                crate::psrs(&mut bytes, p);
+                assert_eq!(bytes, expected, "{name}, p = {p}");
+
+                let mut words: Vec<u16> = values.iter().map(|&v| v as u16).collect();
+                let expected = std_sorted(&words);
+                crate::psrs(&mut words, p);
+                assert_eq!(words, expected, "{name}, p = {p}");
+            }
+        }
+    }
+
+    #[test]
+    fn ord_entry_points_count_small_integers() {
+        use crate::{Phase, Progress, PsrsConfig};
+        let values: Vec<u16> = random_values(20_000, 1_000, 30).iter().map(|&v| v as u16).collect();
+        let expected = std_sorted(&values);
+        let config = PsrsConfig::with_threads(4);
+
+        // A counted run reports one partition and no pipeline phases.
+        let mut data = values.clone();
+        assert_eq!(crate::psrs_with_stats(&mut data, &config).sizes, [values.len()]);
+        assert_eq!(data, expected);
+        let events = std::sync::Mutex::new(Vec::new());
+        let mut data = values.clone();
+        crate::psrs_with_progress(&mut data, 4, |event| events.lock().unwrap().push(event));
+        let serial = [
+            Progress::PhaseStarted(Phase::LocalSort),
+            Progress::Completed { phase: Phase::LocalSort, done: 1, total: 1 },
+            Progress::Finished,
+        ];
+        assert_eq!(events.into_inner().unwrap(), serial);
+        assert_eq!(data, expected);
+        let mut data = values.clone();
+        assert!(crate::psrs_indexed(&mut data, 4).pivots().is_empty());
+        assert_eq!(data, expected);
+
+        let mut dst = vec![0; values.len()];
+        crate::psrs_to(&values, &mut dst, 4);
+        assert_eq!(dst, expected);
+    }
+
+    /// Same layout as `u8`, but ordered the other way round.
+    #[derive(Debug, Clone, Copy, PartialEq, Eq)]
+    #[repr(transparent)]
+    struct Descending(u8);
+
+    impl PartialOrd for Descending {
+        fn partial_cmp(&self, other: &Self) -> Option<std::cmp::Ordering> {
+            Some(self.cmp(other))
+        }
+    }
+
+    impl Ord for Descending {
+        fn cmp(&self, other: &Self) -> std::cmp::Ordering {
+            other.0.cmp(&self.0)
+        }
+    }
+
+    #[test]
+    fn same_layout_newtype_is_not_counting_sorted() {
+        let mut data: Vec<Descending> = random_values(10_000, 256, 6).iter().map(|&v| Descending(v as u8)).collect();
+        assert!(!is_small_integer::<Descending>());
+        assert!(!sort_small_integers(default_executor(), &mut data.clone(), 4));
+        let expected = std_sorted(&data);
+        crate::psrs(&mut data, 4);
+        assert_eq!(data, expected);
+    }
+}
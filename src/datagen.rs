@@ -0,0 +1,305 @@
+//! Generates the harness inputs, and shuffles them in parallel.
+//!
+//! Randomness comes from independent [`StdRng`] streams keyed by a seed and
+//! a stream number, so a dataset or a parallel shuffle is the same for a
+//! given seed whatever the thread count.
+
+use std::mem;
+use std::time::Instant;
+
+use rand::rngs::StdRng;
+use rand::seq::SliceRandom;
+use rand::{Rng, SeedableRng};
+use rayon::prelude::*;
+use serde::{Deserialize, Serialize};
+
+use crate::LOG_RUN_INFO;
+
+/// Shape of the generated input.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Distribution {
+    /// Independent uniform values.
+    Uniform,
+    /// Uniform values, already ascending.
+    Sorted,
+    /// Uniform values, descending.
+    Reverse,
+    /// Ascending, with 1% of the elements swapped to random positions.
+    NearlySorted,
+    /// Ascending, then the given percentage of positions shuffled among
+    /// themselves with [`par_shuffle_fraction`]; `shuffled = 100` is a
+    /// uniform random permutation of sorted values.
+    Shuffled(u32),
+    /// Zipf-distributed values with exponent 1: the smallest value is the
+    /// most common, the next one half as common, and so on.
+    Zipf,
+}
+
+impl Distribution {
+    pub fn name(self) -> String {
+        match self {
+            Distribution::Uniform => "uniform".to_string(),
+            Distribution::Sorted => "sorted".to_string(),
+            Distribution::Reverse => "reverse".to_string(),
+            Distribution::NearlySorted => "nearly_sorted".to_string(),
+            Distribution::Shuffled(percent) => format!("shuffled_{percent}"),
+            Distribution::Zipf => "zipf".to_string(),
+        }
+    }
+}
+
+/// `n` values from `start..end` shaped by `distribution`, the same for the
+/// same `seed` whatever the thread count.
+///
+/// The values are drawn in fixed-size chunks, each from its own random
+/// stream, in parallel on the current Rayon pool.
+pub fn generate_data(n: usize, start: u32, end: u32, distribution: Distribution, seed: u64) -> Vec<u32> {
+    let time_start = Instant::now();
+    let mut data = vec![0; n];
+    let zipf = (distribution == Distribution::Zipf).then(|| Zipf::new(u64::from(end - start), ZIPF_EXPONENT));
+    data.par_chunks_mut(GENERATE_CHUNK_LEN).enumerate().for_each(|(i, chunk)| {
+        let mut rng = stream_rng(seed, GENERATE_STREAM + i as u64);
+        chunk.iter_mut().for_each(|value| {
+            *value = match &zipf {
+                Some(zipf) => start + (zipf.sample(&mut rng) - 1) as u32,
+                None => rng.random_range(start..end),
+            }
+        });
+    });
+    match distribution {
+        Distribution::Uniform | Distribution::Zipf => {}
+        Distribution::Sorted => data.par_sort_unstable(),
+        Distribution::Reverse => data.par_sort_unstable_by(|a, b| b.cmp(a)),
+        Distribution::NearlySorted => {
+            data.par_sort_unstable();
+            let mut rng = stream_rng(seed, SWAP_STREAM);
+            for _ in 0..n / 100 {
+                let (i, j) = (rng.random_range(0..n), rng.random_range(0..n));
+                data.swap(i, j);
+            }
+        }
+        Distribution::Shuffled(percent) => {
+            data.par_sort_unstable();
+            par_shuffle_fraction(&mut data, f64::from(percent.min(100)) / 100.0, seed);
+        }
+    }
+
+    let duration = time_start.elapsed();
+    if LOG_RUN_INFO {
+        println!("Time elapsed for generation: {:?}", duration);
+    }
+    data
+}
+
+/// Block `index` of a dataset streamed to disk: `len` values from `0..max`,
+/// drawn from the block's own random stream so blocks can be generated in
+/// any order. Only the distributions that need no global sort, uniform and
+/// Zipf, can be generated a block at a time.
+pub fn generate_block(index: usize, len: usize, max: u64, distribution: Distribution, seed: u64) -> Vec<u64> {
+    let mut rng = stream_rng(seed, GENERATE_STREAM + index as u64);
+    match distribution {
+        Distribution::Uniform => (0..len).map(|_| rng.random_range(0..max)).collect(),
+        Distribution::Zipf => {
+            let zipf = Zipf::new(max, ZIPF_EXPONENT);
+            (0..len).map(|_| zipf.sample(&mut rng) - 1).collect()
+        }
+        _ => panic!("{distribution:?} data cannot be generated a block at a time"),
+    }
+}
+
+/// Values [`generate_data`] draws from one random stream, and the block
+/// length a streamed dataset is generated in.
+pub const GENERATE_CHUNK_LEN: usize = 1 << 20;
+const ZIPF_EXPONENT: f64 = 1.0;
+/// Inputs shorter than this are shuffled serially.
+const PARALLEL_MIN_LEN: usize = 1 << 16;
+/// Target length of the buckets [`par_shuffle`] scatters into, small enough
+/// to be shuffled in cache.
+const BUCKET_LEN: usize = 1 << 16;
+
+/// Stream numbers, so the steps drawing from one seed never share random
+/// numbers.
+const SCATTER_STREAM: u64 = 0;
+const BUCKET_STREAM: u64 = 1 << 32;
+const SELECT_STREAM: u64 = 2 << 32;
+const GENERATE_STREAM: u64 = 3 << 32;
+const SWAP_STREAM: u64 = 4 << 32;
+
+/// The random stream `stream` of `seed`.
+fn stream_rng(seed: u64, stream: u64) -> StdRng {
+    let mut key = [0; 32];
+    key[..8].copy_from_slice(&seed.to_le_bytes());
+    key[8..16].copy_from_slice(&stream.to_le_bytes());
+    StdRng::from_seed(key)
+}
+
+/// Puts `data` into a uniformly random order determined by `seed`, in
+/// parallel on the current Rayon pool.
+///
+/// Every element is sent to a random bucket, then each bucket is shuffled
+/// with Fisher–Yates (Sandelius' algorithm). Both steps run per chunk or
+/// per bucket, each with its own random stream, and the scatter goes
+/// through a second buffer, so every element is cloned twice.
+pub fn par_shuffle<T: Clone + Send + Sync>(data: &mut [T], seed: u64) {
+    let n = data.len();
+    if n < PARALLEL_MIN_LEN {
+        data.shuffle(&mut stream_rng(seed, SCATTER_STREAM));
+        return;
+    }
+    let buckets = n.div_ceil(BUCKET_LEN);
+    // The chunk count is fixed rather than taken from the pool, so the
+    // permutation does not depend on the thread count.
+    let chunk_size = n.div_ceil(256);
+    let bucket_of = |chunk: usize| {
+        let mut rng = stream_rng(seed, SCATTER_STREAM + chunk as u64);
+        move || rng.random_range(0..buckets)
+    };
+
+    // Count the elements every chunk sends to every bucket, then replay the
+    // same random streams to scatter them.
+    let counts: Vec<Vec<usize>> = data
+        .par_chunks(chunk_size)
+        .enumerate()
+        .map(|(i, chunk)| {
+            let mut next = bucket_of(i);
+            let mut counts = vec![0; buckets];
+            chunk.iter().for_each(|_| counts[next()] += 1);
+            counts
+        })
+        .collect();
+    let bucket_lens: Vec<usize> = (0..buckets).map(|b| counts.iter().map(|c| c[b]).sum()).collect();
+
+    let mut scattered = data.to_vec();
+    let mut targets: Vec<Vec<&mut [T]>> = counts.iter().map(|_| Vec::with_capacity(buckets)).collect();
+    let mut rest = &mut scattered[..];
+    for (b, &len) in bucket_lens.iter().enumerate() {
+        let (mut bucket, tail) = rest.split_at_mut(len);
+        rest = tail;
+        for (chunk, counts) in counts.iter().enumerate() {
+            let (part, tail) = mem::take(&mut bucket).split_at_mut(counts[b]);
+            targets[chunk].push(part);
+            bucket = tail;
+        }
+    }
+    data.par_chunks(chunk_size).zip(targets).enumerate().for_each(|(i, (chunk, mut targets))| {
+        let mut next = bucket_of(i);
+        for value in chunk {
+            let target = &mut targets[next()];
+            let (slot, tail) = mem::take(target).split_first_mut().expect("bucket counted too few elements");
+            slot.clone_from(value);
+            *target = tail;
+        }
+    });
+
+    let mut bucket_slices = Vec::with_capacity(buckets);
+    let mut rest = &mut scattered[..];
+    for &len in &bucket_lens {
+        let (bucket, tail) = rest.split_at_mut(len);
+        bucket_slices.push(bucket);
+        rest = tail;
+    }
+    bucket_slices
+        .into_par_iter()
+        .enumerate()
+        .for_each(|(b, bucket)| bucket.shuffle(&mut stream_rng(seed, BUCKET_STREAM + b as u64)));
+    data.par_chunks_mut(chunk_size)
+        .zip(scattered.par_chunks(chunk_size))
+        .for_each(|(dst, src)| dst.clone_from_slice(src));
+}
+
+/// Shuffles a random `fraction` of the positions of `data` among
+/// themselves, leaving the rest in place: sorted input with a fraction of
+/// 0.05 is sorted but for about 5% of its elements. Each position takes
+/// part with probability `fraction`, and the picked elements are permuted
+/// with [`par_shuffle`], so some may land where they started.
+pub fn par_shuffle_fraction<T: Clone + Send + Sync>(data: &mut [T], fraction: f64, seed: u64) {
+    let chunk_size = data.len().div_ceil(256).max(1);
+    let fraction = fraction.clamp(0.0, 1.0);
+    let picked: Vec<Vec<usize>> = data
+        .par_chunks(chunk_size)
+        .enumerate()
+        .map(|(i, chunk)| {
+            let mut rng = stream_rng(seed, SELECT_STREAM + i as u64);
+            (0..chunk.len()).filter(|_| rng.random_bool(fraction)).collect()
+        })
+        .collect();
+    let mut values: Vec<T> = data
+        .par_chunks(chunk_size)
+        .zip(&picked)
+        .flat_map_iter(|(chunk, picked)| picked.iter().map(|&j| chunk[j].clone()))
+        .collect();
+    par_shuffle(&mut values, seed);
+
+    let mut rest = &values[..];
+    let mut sources = Vec::with_capacity(picked.len());
+    for picked in &picked {
+        let (source, tail) = rest.split_at(picked.len());
+        sources.push(source);
+        rest = tail;
+    }
+    data.par_chunks_mut(chunk_size).zip(picked).zip(sources).for_each(|((chunk, picked), source)| {
+        for (&j, value) in picked.iter().zip(source) {
+            chunk[j].clone_from(value);
+        }
+    });
+}
+
+/// Zipf distribution over the ranks `1..=n`, sampled by Hörmann and
+/// Derflinger's rejection-inversion method, so it needs neither a table
+/// of `n` entries nor more than a few tries per sample.
+pub struct Zipf {
+    n: f64,
+    exponent: f64,
+    h_integral_x1: f64,
+    h_integral_n: f64,
+    s: f64,
+}
+
+impl Zipf {
+    pub fn new(n: u64, exponent: f64) -> Self {
+        let mut zipf = Zipf { n: n.max(1) as f64, exponent, h_integral_x1: 0.0, h_integral_n: 0.0, s: 0.0 };
+        zipf.h_integral_x1 = zipf.h_integral(1.5) - 1.0;
+        zipf.h_integral_n = zipf.h_integral(zipf.n + 0.5);
+        zipf.s = 2.0 - zipf.h_integral_inverse(zipf.h_integral(2.5) - zipf.h(2.0));
+        zipf
+    }
+
+    /// A rank in `1..=n`, rank `k` with probability proportional to
+    /// `k^-exponent`.
+    pub fn sample(&self, rng: &mut impl Rng) -> u64 {
+        loop {
+            let u = self.h_integral_n + rng.random::<f64>() * (self.h_integral_x1 - self.h_integral_n);
+            let x = self.h_integral_inverse(u);
+            let k = (x + 0.5).floor().clamp(1.0, self.n);
+            if k - x <= self.s || u >= self.h_integral(k + 0.5) - self.h(k) {
+                return k as u64;
+            }
+        }
+    }
+
+    fn h(&self, x: f64) -> f64 {
+        (-self.exponent * x.ln()).exp()
+    }
+
+    /// Antiderivative of [`Zipf::h`].
+    fn h_integral(&self, x: f64) -> f64 {
+        let log_x = x.ln();
+        expm1_over_x((1.0 - self.exponent) * log_x) * log_x
+    }
+
+    fn h_integral_inverse(&self, x: f64) -> f64 {
+        let t = (x * (1.0 - self.exponent)).max(-1.0);
+        (ln1p_over_x(t) * x).exp()
+    }
+}
+
+/// `ln(1 + x) / x`, continued to 1 at 0.
+fn ln1p_over_x(x: f64) -> f64 {
+    if x.abs() > 1e-8 { x.ln_1p() / x } else { 1.0 - x * (0.5 - x * (1.0 / 3.0 - 0.25 * x)) }
+}
+
+/// `(e^x - 1) / x`, continued to 1 at 0.
+fn expm1_over_x(x: f64) -> f64 {
+    if x.abs() > 1e-8 { x.exp_m1() / x } else { 1.0 + x * 0.5 * (1.0 + x / 3.0 * (1.0 + 0.25 * x)) }
+}
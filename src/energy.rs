@@ -0,0 +1,74 @@
+//! Package energy readings from RAPL, through the Linux powercap interface.
+//! AMD processors expose theirs under the same `intel-rapl` names.
+
+use std::fs;
+use std::io;
+use std::path::PathBuf;
+
+const POWERCAP: &str = "/sys/class/powercap";
+
+/// One package domain (`intel-rapl:N`; its `intel-rapl:N:M` subdomains are
+/// already included in it).
+struct Domain {
+    energy_uj: PathBuf,
+    /// The counter wraps to zero after this value.
+    max_energy_range_uj: u64,
+}
+
+/// Reads the energy counters of every package.
+pub struct EnergyMeter {
+    domains: Vec<Domain>,
+}
+
+impl EnergyMeter {
+    /// Finds the package domains. Fails without RAPL support, and usually
+    /// without root, as `energy_uj` is only readable by root on current
+    /// kernels.
+    pub fn open() -> io::Result<EnergyMeter> {
+        let mut domains = Vec::new();
+        for entry in fs::read_dir(POWERCAP)? {
+            let path = entry?.path();
+            let name = path.file_name().and_then(|n| n.to_str()).unwrap_or_default();
+            if name.strip_prefix("intel-rapl:").is_none_or(|index| index.contains(':')) {
+                continue;
+            }
+            let max = read_u64(&path.join("max_energy_range_uj"))?;
+            let energy_uj = path.join("energy_uj");
+            // Fail now rather than on the first run if it is not readable.
+            read_u64(&energy_uj)?;
+            domains.push(Domain { energy_uj, max_energy_range_uj: max });
+        }
+        if domains.is_empty() {
+            return Err(io::Error::new(io::ErrorKind::NotFound, "no RAPL package domains"));
+        }
+        Ok(EnergyMeter { domains })
+    }
+
+    /// Current counter of every package, in microjoules.
+    pub fn read(&self) -> io::Result<Vec<u64>> {
+        self.domains.iter().map(|d| read_u64(&d.energy_uj)).collect()
+    }
+
+    /// Joules used by all packages since `start` was [`read`](Self::read).
+    /// Correct as long as no counter wrapped more than once.
+    pub fn joules_since(&self, start: &[u64]) -> io::Result<f64> {
+        let end = self.read()?;
+        let microjoules: u64 = self
+            .domains
+            .iter()
+            .zip(start.iter().zip(end))
+            .map(|(domain, (&start, end))| match end >= start {
+                true => end - start,
+                false => domain.max_energy_range_uj - start + end,
+            })
+            .sum();
+        Ok(microjoules as f64 / 1e6)
+    }
+}
+
+fn read_u64(path: &PathBuf) -> io::Result<u64> {
+    fs::read_to_string(path)?
+        .trim()
+        .parse()
+        .map_err(|_| io::Error::new(io::ErrorKind::InvalidData, format!("bad value in {}", path.display())))
+}
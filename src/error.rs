@@ -0,0 +1,29 @@
+use std::fmt;
+
+use crate::Phase;
+
+/// Reasons a fallible PSRS run can stop before the data is sorted.
+///
+/// In every case the data still holds exactly the original elements, in
+/// unspecified order: only Phase 1 permutes the input in place, and the
+/// merged output is copied back only once all merges have succeeded.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum PsrsError {
+    /// The run observed a cancelled [`CancellationToken`](crate::CancellationToken).
+    Cancelled,
+    /// The comparator (or the element type's `Ord`) panicked in `phase`.
+    Panicked { phase: Phase, message: String },
+}
+
+impl fmt::Display for PsrsError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            PsrsError::Cancelled => write!(f, "sort was cancelled"),
+            PsrsError::Panicked { phase, message } => {
+                write!(f, "sort panicked during {phase:?} and left the data unsorted: {message}")
+            }
+        }
+    }
+}
+
+impl std::error::Error for PsrsError {}
@@ -0,0 +1,284 @@
+use std::panic;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::Mutex;
+use std::thread;
+
+use crate::ThreadBudget;
+
+/// Runs the independent tasks of each PSRS phase.
+///
+/// Implement this to run the sort on a thread pool of your own. The crate
+/// ships [`RayonExecutor`] (with the `rayon` feature, the default) and
+/// [`ScopedThreadExecutor`], which only needs `std`.
+pub trait Executor: Sync {
+    /// Number of workers the tasks are spread over.
+    fn num_threads(&self) -> usize;
+
+    /// Calls `task(i)` once for every `i` in `0..n`, possibly in parallel, and
+    /// returns once all calls have finished. A panicking task must be
+    /// propagated to the caller after the other tasks have finished.
+    fn for_each_index(&self, n: usize, task: &(dyn Fn(usize) + Sync));
+}
+
+/// Runs tasks on the current Rayon thread pool (the global one unless called
+/// inside `ThreadPool::install`).
+#[cfg(feature = "rayon")]
+#[derive(Debug, Clone, Copy, Default)]
+pub struct RayonExecutor;
+
+#[cfg(feature = "rayon")]
+impl Executor for RayonExecutor {
+    fn num_threads(&self) -> usize {
+        rayon::current_num_threads()
+    }
+
+    fn for_each_index(&self, n: usize, task: &(dyn Fn(usize) + Sync)) {
+        use rayon::prelude::*;
+        (0..n).into_par_iter().for_each(task);
+    }
+}
+
+/// Spawns `threads` plain `std` threads per phase, which pull task indices
+/// from a shared counter. Needs no dependencies beyond `std`.
+#[derive(Debug, Clone, Copy)]
+pub struct ScopedThreadExecutor {
+    pub threads: usize,
+}
+
+impl Default for ScopedThreadExecutor {
+    fn default() -> Self {
+        ScopedThreadExecutor {
+            threads: thread::available_parallelism().map_or(1, |n| n.get()),
+        }
+    }
+}
+
+impl Executor for ScopedThreadExecutor {
+    fn num_threads(&self) -> usize {
+        self.threads
+    }
+
+    fn for_each_index(&self, n: usize, task: &(dyn Fn(usize) + Sync)) {
+        let workers = self.threads.clamp(1, n.max(1));
+        if workers == 1 {
+            (0..n).for_each(task);
+            return;
+        }
+        let next = AtomicUsize::new(0);
+        let work = || loop {
+            let i = next.fetch_add(1, Ordering::Relaxed);
+            if i >= n {
+                break;
+            }
+            task(i);
+        };
+        thread::scope(|s| {
+            let handles: Vec<_> = (1..workers).map(|_| s.spawn(work)).collect();
+            // The calling thread works too instead of just waiting.
+            let local = panic::catch_unwind(panic::AssertUnwindSafe(work));
+            // Re-raise the original payload rather than the scope's generic one.
+            for handle in handles {
+                if let Err(payload) = handle.join() {
+                    panic::resume_unwind(payload);
+                }
+            }
+            if let Err(payload) = local {
+                panic::resume_unwind(payload);
+            }
+        });
+    }
+}
+
+/// The executor used when none is given: Rayon with the `rayon` feature,
+/// [`ScopedThreadExecutor`] otherwise.
+pub fn default_executor() -> &'static dyn Executor {
+    #[cfg(feature = "rayon")]
+    {
+        &RayonExecutor
+    }
+    #[cfg(not(feature = "rayon"))]
+    {
+        static DEFAULT: std::sync::OnceLock<ScopedThreadExecutor> = std::sync::OnceLock::new();
+        DEFAULT.get_or_init(ScopedThreadExecutor::default)
+    }
+}
+
+/// An executor running at most `threads` tasks of another at once, and no
+/// more than `budget` allows.
+pub(crate) struct Limited<'a> {
+    inner: &'a dyn Executor,
+    threads: Option<usize>,
+    budget: Option<&'a ThreadBudget>,
+}
+
+/// `exec`, limited to `threads` concurrent tasks when that is `Some` and to
+/// the current `budget` when there is one.
+pub(crate) fn limited<'a>(exec: &'a dyn Executor, threads: Option<usize>, budget: Option<&'a ThreadBudget>) -> Limited<'a> {
+    Limited { inner: exec, threads, budget }
+}
+
+impl Executor for Limited<'_> {
+    fn num_threads(&self) -> usize {
+        let inner = self.inner.num_threads().max(1);
+        let cap = self.threads.unwrap_or(inner).min(self.budget.map_or(inner, ThreadBudget::get));
+        cap.clamp(1, inner)
+    }
+
+    fn for_each_index(&self, n: usize, task: &(dyn Fn(usize) + Sync)) {
+        let workers = self.num_threads().min(n);
+        if self.budget.is_none() && (self.threads.is_none() || workers >= n) {
+            self.inner.for_each_index(n, task);
+            return;
+        }
+        // `workers` tasks of the inner executor pull the indices from a
+        // shared counter, so they stay balanced like the tasks would be.
+        // Workers above a lowered budget retire between tasks; worker 0
+        // always stays to finish the phase.
+        let next = AtomicUsize::new(0);
+        self.inner.for_each_index(workers, &|worker| loop {
+            if worker > 0 && self.budget.is_some_and(|b| worker >= b.get()) {
+                break;
+            }
+            let i = next.fetch_add(1, Ordering::Relaxed);
+            if i >= n {
+                break;
+            }
+            task(i);
+        });
+    }
+}
+
+/// Runs `f(i)` for every `i` in `0..n` on `exec` and collects the results in
+/// index order.
+pub(crate) fn par_map<R, F>(exec: &dyn Executor, n: usize, f: F) -> Vec<R>
+where
+    R: Send,
+    F: Fn(usize) -> R + Sync,
+{
+    let slots: Vec<Mutex<Option<R>>> = (0..n).map(|_| Mutex::new(None)).collect();
+    exec.for_each_index(n, &|i| {
+        let result = f(i);
+        *slots[i].lock().unwrap() = Some(result);
+    });
+    slots
+        .into_iter()
+        .map(|slot| slot.into_inner().unwrap().expect("executor skipped a task"))
+        .collect()
+}
+
+/// Runs `f(chunk_index, chunk)` for every `chunk_size` chunk of `data` on
+/// `exec` and collects the results in chunk order.
+pub(crate) fn par_chunks_mut_map<T, R, F>(exec: &dyn Executor, data: &mut [T], chunk_size: usize, f: F) -> Vec<R>
+where
+    T: Send,
+    R: Send,
+    F: Fn(usize, &mut [T]) -> R + Sync,
+{
+    let chunks: Vec<Mutex<&mut [T]>> = data.chunks_mut(chunk_size).map(Mutex::new).collect();
+    par_map(exec, chunks.len(), |i| f(i, &mut chunks[i].lock().unwrap()))
+}
+
+/// Runs `f(chunk_index, chunk)` for the consecutive chunks of `data` with
+/// lengths `lens`, which add up to its length, on `exec` and collects the
+/// results in chunk order.
+pub(crate) fn par_split_mut_map<T, R, F>(exec: &dyn Executor, data: &mut [T], lens: &[usize], f: F) -> Vec<R>
+where
+    T: Send,
+    R: Send,
+    F: Fn(usize, &mut [T]) -> R + Sync,
+{
+    let mut chunks: Vec<Mutex<&mut [T]>> = Vec::with_capacity(lens.len());
+    let mut rest = data;
+    for &len in lens {
+        let (chunk, tail) = rest.split_at_mut(len);
+        chunks.push(Mutex::new(chunk));
+        rest = tail;
+    }
+    par_map(exec, chunks.len(), |i| f(i, &mut chunks[i].lock().unwrap()))
+}
+
+/// Runs `f(chunk_index, chunk)` for every `chunk_size` chunk of `data` on
+/// `exec` and collects the results in chunk order.
+pub(crate) fn par_chunks_map<T, R, F>(exec: &dyn Executor, data: &[T], chunk_size: usize, f: F) -> Vec<R>
+where
+    T: Sync,
+    R: Send,
+    F: Fn(usize, &[T]) -> R + Sync,
+{
+    let chunks: Vec<&[T]> = data.chunks(chunk_size).collect();
+    par_map(exec, chunks.len(), |i| f(i, chunks[i]))
+}
+
+/// Chunk length that spreads `len` elements evenly over the workers of `exec`.
+pub(crate) fn even_chunk_size(exec: &dyn Executor, len: usize) -> usize {
+    len.div_ceil(exec.num_threads().max(1)).max(1)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn scoped_threads_run_every_task_once() {
+        for threads in [1, 3, 16] {
+            let exec = ScopedThreadExecutor { threads };
+            let runs: Vec<AtomicUsize> = (0..100).map(|_| AtomicUsize::new(0)).collect();
+            exec.for_each_index(runs.len(), &|i| {
+                runs[i].fetch_add(1, Ordering::Relaxed);
+            });
+            assert!(runs.iter().all(|r| r.load(Ordering::Relaxed) == 1), "threads = {threads}");
+            assert_eq!(par_map(&exec, 5, |i| i * i), [0, 1, 4, 9, 16]);
+        }
+    }
+
+    #[test]
+    fn scoped_threads_propagate_task_panics() {
+        let exec = ScopedThreadExecutor { threads: 4 };
+        let result = panic::catch_unwind(|| exec.for_each_index(8, &|i| assert_ne!(i, 5, "task five")));
+        let payload = result.expect_err("the panic must reach the caller");
+        let message = payload.downcast_ref::<String>().map_or("", String::as_str);
+        assert!(message.contains("task five"), "{message}");
+    }
+
+    /// The most tasks `exec` ran at once while running `n` short ones.
+    fn peak_concurrency(exec: &dyn Executor, n: usize) -> usize {
+        let (running, peak) = (AtomicUsize::new(0), AtomicUsize::new(0));
+        exec.for_each_index(n, &|_| {
+            let now = running.fetch_add(1, Ordering::SeqCst) + 1;
+            peak.fetch_max(now, Ordering::SeqCst);
+            thread::sleep(std::time::Duration::from_millis(2));
+            running.fetch_sub(1, Ordering::SeqCst);
+        });
+        peak.into_inner()
+    }
+
+    #[test]
+    fn limited_executor_caps_concurrent_tasks() {
+        let inner = ScopedThreadExecutor { threads: 8 };
+        let capped = limited(&inner, Some(2), None);
+        assert_eq!(capped.num_threads(), 2);
+        assert!(peak_concurrency(&capped, 40) <= 2);
+        assert_eq!(limited(&inner, Some(100), None).num_threads(), 8);
+    }
+
+    #[test]
+    fn phase_caps_bound_the_tasks_running_at_once() {
+        let phase_threads = crate::PhaseThreads { local_sort: Some(1), boundaries: None, merge: Some(2) };
+        let config = crate::PsrsConfig { phase_threads, min_partition_size: 0, ..crate::PsrsConfig::with_threads(6) };
+        let timeline = crate::Timeline::new();
+        // Scoped threads overlap even on a single core, unlike a one-thread Rayon pool.
+        let executor = ScopedThreadExecutor { threads: 6 };
+        let hooks = crate::Hooks { executor: &executor, timeline: Some(&timeline), ..crate::Hooks::new() };
+        let mut data = crate::testing::random_values(300_000, u64::MAX, 18);
+        crate::psrs_ord(&mut data, &config, hooks, allocator_api2::alloc::Global).unwrap();
+        let events = timeline.events();
+        for (phase, cap) in [(crate::Phase::LocalSort, 1), (crate::Phase::Merge, 2)] {
+            let tasks: Vec<_> = events.iter().filter(|e| e.phase == phase && e.task.is_some()).collect();
+            assert_eq!(tasks.len(), 6, "{phase:?}");
+            for task in &tasks {
+                let running = tasks.iter().filter(|t| t.start <= task.start && task.start < t.end).count();
+                assert!(running <= cap, "{phase:?}: {running} tasks at once");
+            }
+        }
+    }
+}
@@ -0,0 +1,1024 @@
+//! Out-of-core sorting of binary files that do not fit in memory.
+//!
+//! The input is cut into runs of [`ExternalSortConfig::run_len`] elements;
+//! each run is sorted in memory with [`psrs`] and spilled to the work
+//! directory, and the runs are then k-way merged into the output.
+//!
+//! Progress is recorded in a manifest in the work directory: which runs are
+//! complete and, during the merge, how much of the output has been written
+//! and how far into each run it has read. If the process dies, calling
+//! [`external_sort`] again with the same input, output and work directory
+//! picks up from the last checkpoint instead of starting over.
+//!
+//! Disk I/O overlaps with the computation: runs are read and written by
+//! their own threads while the previous one is being sorted, and the merge
+//! reads every run a block ahead on a few shared I/O threads and writes the
+//! output from a separate one. Over [`ExternalSortConfig::max_fan_in`] runs
+//! are first merged in groups, so the final merge keeps a bounded number of
+//! files open.
+//!
+//! With the `lz4` or `zstd` feature the runs can be compressed
+//! ([`RunCompression`]), trading CPU for temporary disk bandwidth; they are
+//! decompressed as the merge streams through them.
+
+use std::cmp::Reverse;
+use std::collections::BinaryHeap;
+use std::fs::{self, File, OpenOptions};
+use std::io::{self, BufRead, BufReader, BufWriter, Read, Seek, SeekFrom, Write};
+use std::mem;
+use std::ops::Range;
+use std::path::{Path, PathBuf};
+use std::sync::{mpsc, Arc, Mutex};
+use std::thread;
+
+use crate::{psrs, psrs_with_budget, PsrsConfig, ThreadBudget};
+
+/// Fixed-size elements, stored in files as their little-endian bytes.
+pub trait FixedBytes: Sized {
+    /// Size of one element on disk.
+    const SIZE: usize;
+
+    fn write_bytes(&self, out: &mut [u8]);
+
+    fn read_bytes(bytes: &[u8]) -> Self;
+}
+
+macro_rules! impl_fixed_bytes {
+    ($($t:ty),*) => {$(
+        impl FixedBytes for $t {
+            const SIZE: usize = std::mem::size_of::<$t>();
+
+            #[inline]
+            fn write_bytes(&self, out: &mut [u8]) {
+                out.copy_from_slice(&self.to_le_bytes());
+            }
+
+            #[inline]
+            fn read_bytes(bytes: &[u8]) -> Self {
+                <$t>::from_le_bytes(bytes.try_into().unwrap())
+            }
+        }
+    )*};
+}
+
+impl_fixed_bytes!(u8, u16, u32, u64, u128, i8, i16, i32, i64, i128);
+
+/// Settings of an [`external_sort`].
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ExternalSortConfig {
+    /// Chunks each run is split into by PSRS.
+    pub threads: usize,
+    /// Elements sorted in memory at a time. PSRS needs about twice that
+    /// much memory while it merges, and one more run each is held by the
+    /// threads reading the next run and writing the previous one.
+    pub run_len: usize,
+    /// Holds the spilled runs and the manifest until the sort completes.
+    pub work_dir: PathBuf,
+    /// Output elements merged between two checkpoints.
+    pub checkpoint_interval: usize,
+    /// How the spilled runs are stored.
+    pub compression: RunCompression,
+    /// Limits the workers sorting each run, so a long sort can give cores
+    /// back while it runs; see [`ThreadBudget`].
+    pub budget: Option<ThreadBudget>,
+    /// Runs merged at once. With more runs than this, the oldest are merged
+    /// into a new run, a group at a time, until few enough are left; each
+    /// group merged is a checkpoint.
+    pub max_fan_in: usize,
+}
+
+/// Encoding of the runs spilled to the work directory.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum RunCompression {
+    /// Raw little-endian elements.
+    #[default]
+    None,
+    /// LZ4 frames: cheap enough to keep up with most disks.
+    #[cfg(feature = "lz4")]
+    Lz4,
+    /// Zstd at the given level; smaller runs than LZ4 for more CPU.
+    #[cfg(feature = "zstd")]
+    Zstd { level: i32 },
+}
+
+impl RunCompression {
+    /// Identifies the encoding in the manifest, so a sort is not resumed
+    /// with runs written in another one.
+    fn code(self) -> usize {
+        match self {
+            RunCompression::None => 0,
+            #[cfg(feature = "lz4")]
+            RunCompression::Lz4 => 1,
+            #[cfg(feature = "zstd")]
+            RunCompression::Zstd { .. } => 2,
+        }
+    }
+}
+
+impl ExternalSortConfig {
+    pub fn new(work_dir: impl Into<PathBuf>) -> Self {
+        ExternalSortConfig {
+            threads: crate::default_executor().num_threads(),
+            run_len: 1 << 24,
+            work_dir: work_dir.into(),
+            checkpoint_interval: 1 << 24,
+            compression: RunCompression::None,
+            budget: None,
+            max_fan_in: MAX_FAN_IN,
+        }
+    }
+
+    /// A config whose runs are as long as `budget` bytes of `T` allow: a
+    /// run is held about four times over, by the thread reading it, by PSRS
+    /// and its merge buffers, and by the thread writing it.
+    pub fn with_memory_budget<T>(work_dir: impl Into<PathBuf>, budget: usize) -> Self {
+        ExternalSortConfig { run_len: (budget / (4 * mem::size_of::<T>().max(1))).max(1), ..Self::new(work_dir) }
+    }
+}
+
+/// Name of the progress file inside the work directory.
+const MANIFEST: &str = "manifest";
+
+/// Progress of one external sort, persisted in the work directory.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+struct Manifest {
+    /// Input length in elements.
+    input_len: usize,
+    element_size: usize,
+    run_len: usize,
+    /// [`RunCompression::code`] of the runs.
+    compression: usize,
+    /// Lengths of the runs spilled so far, in input order, followed by
+    /// those of the runs merged from groups of them.
+    runs: Vec<usize>,
+    /// Runs before this one have been merged into later ones and removed.
+    first_run: usize,
+    /// Elements written to the output so far.
+    merged: usize,
+    /// For each run from `first_run` on, how many of its elements are in
+    /// the output. Empty until the final merge starts.
+    consumed: Vec<usize>,
+}
+
+impl Manifest {
+    fn load(dir: &Path) -> io::Result<Option<Manifest>> {
+        let file = match File::open(dir.join(MANIFEST)) {
+            Ok(file) => file,
+            Err(e) if e.kind() == io::ErrorKind::NotFound => return Ok(None),
+            Err(e) => return Err(e),
+        };
+        let mut manifest = Manifest::default();
+        for line in BufReader::new(file).lines() {
+            let line = line?;
+            let mut fields = line.split_whitespace();
+            let key = fields.next().unwrap_or_default();
+            let values: Vec<usize> = fields
+                .map(|v| v.parse().map_err(|_| invalid_data(format!("bad manifest line {line:?}"))))
+                .collect::<io::Result<_>>()?;
+            let single = || values.first().copied().ok_or_else(|| invalid_data(format!("bad manifest line {line:?}")));
+            match key {
+                "input_len" => manifest.input_len = single()?,
+                "element_size" => manifest.element_size = single()?,
+                "run_len" => manifest.run_len = single()?,
+                "compression" => manifest.compression = single()?,
+                "first_run" => manifest.first_run = single()?,
+                "merged" => manifest.merged = single()?,
+                "runs" => manifest.runs = values,
+                "consumed" => manifest.consumed = values,
+                _ => return Err(invalid_data(format!("bad manifest line {line:?}"))),
+            }
+        }
+        Ok(Some(manifest))
+    }
+
+    /// Replaces the manifest atomically, so a crash leaves either the old
+    /// or the new checkpoint.
+    fn save(&self, dir: &Path) -> io::Result<()> {
+        let join = |values: &[usize]| values.iter().map(|v| v.to_string()).collect::<Vec<_>>().join(" ");
+        let text = format!(
+            "input_len {}\nelement_size {}\nrun_len {}\ncompression {}\nruns {}\nfirst_run {}\nmerged {}\n\
+             consumed {}\n",
+            self.input_len,
+            self.element_size,
+            self.run_len,
+            self.compression,
+            join(&self.runs),
+            self.first_run,
+            self.merged,
+            join(&self.consumed),
+        );
+        let tmp = dir.join(format!("{MANIFEST}.tmp"));
+        let mut file = File::create(&tmp)?;
+        file.write_all(text.as_bytes())?;
+        file.sync_all()?;
+        fs::rename(tmp, dir.join(MANIFEST))
+    }
+}
+
+fn invalid_data(message: String) -> io::Error {
+    io::Error::new(io::ErrorKind::InvalidData, message)
+}
+
+fn run_path(dir: &Path, run: usize) -> PathBuf {
+    dir.join(format!("run-{run}.bin"))
+}
+
+/// Elements read from or written to disk per I/O call.
+const IO_BLOCK: usize = 1 << 16;
+
+/// Default of [`ExternalSortConfig::max_fan_in`], and the most runs
+/// [`psrs_with_memory_budget`] merges at once.
+const MAX_FAN_IN: usize = 128;
+
+/// Threads reading ahead the inputs of one merge, however many there are.
+const IO_THREADS: usize = 4;
+
+/// Reads `len` elements from `reader`.
+fn read_elements<T: FixedBytes>(reader: &mut impl Read, len: usize) -> io::Result<Vec<T>> {
+    let mut elements = Vec::with_capacity(len);
+    let mut bytes = vec![0; IO_BLOCK.min(len) * T::SIZE];
+    while elements.len() < len {
+        let block = &mut bytes[..(len - elements.len()).min(IO_BLOCK) * T::SIZE];
+        reader.read_exact(block)?;
+        elements.extend(block.chunks_exact(T::SIZE).map(T::read_bytes));
+    }
+    Ok(elements)
+}
+
+/// Reads one element from `reader`, using `bytes` (`T::SIZE` long) as the
+/// buffer.
+fn read_one<T: FixedBytes>(reader: &mut impl Read, bytes: &mut [u8]) -> io::Result<T> {
+    reader.read_exact(bytes)?;
+    Ok(T::read_bytes(bytes))
+}
+
+/// Writes `elements` to `writer`.
+fn write_elements<T: FixedBytes>(writer: &mut impl Write, elements: &[T]) -> io::Result<()> {
+    let mut bytes = vec![0; IO_BLOCK.min(elements.len()) * T::SIZE];
+    for block in elements.chunks(IO_BLOCK) {
+        let out = &mut bytes[..block.len() * T::SIZE];
+        for (element, slot) in block.iter().zip(out.chunks_exact_mut(T::SIZE)) {
+            element.write_bytes(slot);
+        }
+        writer.write_all(out)?;
+    }
+    Ok(())
+}
+
+/// Writes `elements` to a new run file at `path` and syncs it.
+fn write_run<T: FixedBytes>(path: &Path, elements: &[T], compression: RunCompression) -> io::Result<()> {
+    write_run_with(path, compression, |mut writer| write_elements(&mut writer, elements))
+}
+
+/// Creates a run file at `path`, has `write` fill it with the elements'
+/// bytes and syncs it.
+fn write_run_with(
+    path: &Path,
+    compression: RunCompression,
+    write: impl FnOnce(&mut dyn Write) -> io::Result<()>,
+) -> io::Result<()> {
+    let writer = BufWriter::new(File::create(path)?);
+    let writer = match compression {
+        RunCompression::None => {
+            let mut writer = writer;
+            write(&mut writer)?;
+            writer
+        }
+        #[cfg(feature = "lz4")]
+        RunCompression::Lz4 => {
+            let mut encoder = lz4_flex::frame::FrameEncoder::new(writer);
+            write(&mut encoder)?;
+            encoder.finish().map_err(io::Error::from)?
+        }
+        #[cfg(feature = "zstd")]
+        RunCompression::Zstd { level } => {
+            let mut encoder = zstd::Encoder::new(writer, level)?;
+            write(&mut encoder)?;
+            encoder.finish()?
+        }
+    };
+    writer.into_inner().map_err(|e| e.into_error())?.sync_all()
+}
+
+/// Opens the run file at `path`, positioned after its first `skip` elements.
+fn open_run<T: FixedBytes>(path: &Path, skip: usize, compression: RunCompression) -> io::Result<Box<dyn Read + Send>> {
+    let file = File::open(path)?;
+    let skip_bytes = (skip * T::SIZE) as u64;
+    let (mut reader, skip_bytes): (Box<dyn Read + Send>, u64) = match compression {
+        RunCompression::None => {
+            let mut reader = BufReader::new(file);
+            reader.seek(SeekFrom::Start(skip_bytes))?;
+            (Box::new(reader), 0)
+        }
+        #[cfg(feature = "lz4")]
+        RunCompression::Lz4 => {
+            (Box::new(BufReader::new(lz4_flex::frame::FrameDecoder::new(BufReader::new(file)))), skip_bytes)
+        }
+        #[cfg(feature = "zstd")]
+        RunCompression::Zstd { .. } => (Box::new(BufReader::new(zstd::Decoder::new(file)?)), skip_bytes),
+    };
+    // Compressed runs cannot be seeked into, so they are decompressed up to
+    // the resume point and that part is dropped.
+    let skipped = io::copy(&mut reader.by_ref().take(skip_bytes), &mut io::sink())?;
+    if skipped < skip_bytes {
+        return Err(invalid_data(format!("{} is shorter than the manifest says", path.display())));
+    }
+    Ok(reader)
+}
+
+/// Sorts the elements of the binary file `input` into `output`, holding at
+/// most [`ExternalSortConfig::run_len`] of them in memory at a time.
+///
+/// If `config.work_dir` holds the manifest of an interrupted sort of the
+/// same input, the sort resumes from its last checkpoint. The work files
+/// are removed once the output is complete.
+pub fn external_sort<T>(input: &Path, output: &Path, config: &ExternalSortConfig) -> io::Result<()>
+where
+    T: FixedBytes + Clone + Ord + Send + Sync,
+{
+    let dir = &config.work_dir;
+    fs::create_dir_all(dir)?;
+    let input_bytes = fs::metadata(input)?.len() as usize;
+    if !input_bytes.is_multiple_of(T::SIZE) {
+        return Err(invalid_data(format!("{} is not a whole number of elements", input.display())));
+    }
+    let fresh = Manifest {
+        input_len: input_bytes / T::SIZE,
+        element_size: T::SIZE,
+        run_len: config.run_len.max(1),
+        compression: config.compression.code(),
+        ..Manifest::default()
+    };
+    let key = |m: &Manifest| (m.input_len, m.element_size, m.run_len, m.compression);
+    let mut manifest = match Manifest::load(dir)? {
+        Some(m) if key(&m) == key(&fresh) => m,
+        _ => fresh,
+    };
+
+    spill_runs::<T>(input, &mut manifest, config)?;
+    reduce_runs::<T>(&mut manifest, config)?;
+    merge_runs::<T>(output, &mut manifest, config)?;
+
+    // Merged groups may be left over from a crash right after their merge.
+    for path in (0..manifest.runs.len()).map(|run| run_path(dir, run)).chain([dir.join(MANIFEST)]) {
+        match fs::remove_file(path) {
+            Err(e) if e.kind() != io::ErrorKind::NotFound => return Err(e),
+            _ => {}
+        }
+    }
+    Ok(())
+}
+
+/// K-way merges binary files that are each already sorted into `output`,
+/// e.g. shards sorted by separate jobs.
+///
+/// The inputs are read ahead a block at a time on a few shared threads and
+/// the output is buffered, so only two blocks per input are in memory at a
+/// time. Every input is open at once. An input that
+/// turns out not to be sorted is reported as [`io::ErrorKind::InvalidData`],
+/// leaving `output` incomplete.
+pub fn merge_sorted_files<T>(inputs: &[impl AsRef<Path>], output: &Path) -> io::Result<()>
+where
+    T: FixedBytes + Ord,
+{
+    let mut readers = Vec::with_capacity(inputs.len());
+    let mut lens = Vec::with_capacity(inputs.len());
+    for path in inputs {
+        let path = path.as_ref();
+        let file = File::open(path)?;
+        let len = file.metadata()?.len() as usize;
+        if !len.is_multiple_of(T::SIZE) {
+            return Err(invalid_data(format!("{} is not a whole number of elements", path.display())));
+        }
+        readers.push((Box::new(file) as Box<dyn Read + Send>, len));
+        lens.push(len);
+    }
+    let mut out = BufWriter::with_capacity(IO_BLOCK * T::SIZE, File::create(output)?);
+
+    thread::scope(|s| {
+        let mut inputs_ahead = read_ahead(s, readers, IO_BLOCK * T::SIZE);
+        let mut remaining: Vec<usize> = lens.iter().map(|len| len / T::SIZE).collect();
+        let mut bytes = vec![0; T::SIZE];
+        let mut heap = BinaryHeap::with_capacity(inputs_ahead.len());
+        for (input, reader) in inputs_ahead.iter_mut().enumerate() {
+            if remaining[input] > 0 {
+                remaining[input] -= 1;
+                heap.push(Reverse((read_one::<T>(reader, &mut bytes)?, input)));
+            }
+        }
+        while let Some(Reverse((element, input))) = heap.pop() {
+            element.write_bytes(&mut bytes);
+            out.write_all(&bytes)?;
+            if remaining[input] > 0 {
+                remaining[input] -= 1;
+                let next = read_one::<T>(&mut inputs_ahead[input], &mut bytes)?;
+                if next < element {
+                    let path = inputs[input].as_ref().display();
+                    return Err(invalid_data(format!("{path} is not sorted")));
+                }
+                heap.push(Reverse((next, input)));
+            }
+        }
+        out.flush()
+    })
+}
+
+/// Sorts and spills every run not yet recorded in `manifest`.
+///
+/// Reading, sorting and writing run as a pipeline on three threads: while
+/// run `k` is sorted, run `k + 1` is read and run `k - 1` written.
+fn spill_runs<T>(input: &Path, manifest: &mut Manifest, config: &ExternalSortConfig) -> io::Result<()>
+where
+    T: FixedBytes + Clone + Ord + Send + Sync,
+{
+    let run_len = manifest.run_len;
+    let input_len = manifest.input_len;
+    let total_runs = input_len.div_ceil(run_len);
+    let done = manifest.runs.len();
+    if done >= total_runs {
+        return Ok(());
+    }
+    let mut reader = BufReader::new(File::open(input)?);
+    reader.seek(SeekFrom::Start((done * run_len * T::SIZE) as u64))?;
+
+    thread::scope(|s| {
+        let (read_tx, read_rx) = mpsc::sync_channel::<io::Result<Vec<T>>>(0);
+        let (sorted_tx, sorted_rx) = mpsc::sync_channel::<Vec<T>>(0);
+        s.spawn(move || {
+            for run in done..total_runs {
+                let elements = read_elements(&mut reader, run_len.min(input_len - run * run_len));
+                let failed = elements.is_err();
+                if read_tx.send(elements).is_err() || failed {
+                    break;
+                }
+            }
+        });
+        let writer = s.spawn(move || -> io::Result<()> {
+            for (run, elements) in (done..).zip(sorted_rx) {
+                write_run(&run_path(&config.work_dir, run), &elements, config.compression)?;
+                manifest.runs.push(elements.len());
+                manifest.save(&config.work_dir)?;
+            }
+            Ok(())
+        });
+
+        for elements in read_rx {
+            let mut elements = elements?;
+            match &config.budget {
+                Some(budget) => psrs_with_budget(&mut elements, &PsrsConfig::with_threads(config.threads), budget),
+                None => psrs(&mut elements, config.threads),
+            }
+            // Only fails if the writer gave up, and joining it reports why.
+            if sorted_tx.send(elements).is_err() {
+                break;
+            }
+        }
+        drop(sorted_tx);
+        writer.join().expect("run writer panicked")
+    })
+}
+
+/// Bytes [`psrs`] allocates besides the input when sorting `n` elements of
+/// `T` in `p` chunks: the merged copy of the input, plus about `p²` samples
+/// and partition boundaries.
+pub fn psrs_aux_bytes<T>(n: usize, p: usize) -> usize {
+    let p = p.max(1);
+    n * mem::size_of::<T>() + p * p * (mem::size_of::<T>() + 2 * mem::size_of::<Range<usize>>())
+}
+
+/// Sorts `data` with [`psrs`] if the input and [`psrs_aux_bytes`] fit in
+/// `budget` bytes. Otherwise it sorts runs of `data` short enough for their
+/// buffers to fit beside the input, spills them to `work_dir` and merges
+/// them back into `data`, so beyond a few I/O buffers the sort stays
+/// within the budget instead of running the machine out of memory.
+///
+/// Fails with [`io::ErrorKind::OutOfMemory`], leaving `data` untouched, if
+/// the input alone leaves too little of the budget for even that. After
+/// any other error, e.g. a full disk or a run that cannot be read back,
+/// the contents of `data` are unspecified: the merge writes straight over
+/// it, so elements may be missing or repeated. The spilled runs are
+/// removed whether the sort succeeds or not.
+pub fn psrs_with_memory_budget<T>(data: &mut [T], p: usize, budget: usize, work_dir: &Path) -> io::Result<()>
+where
+    T: FixedBytes + Clone + Ord + Send + Sync,
+{
+    let n = data.len();
+    let spare = budget.saturating_sub(mem::size_of_val(data));
+    if n == 0 || psrs_aux_bytes::<T>(n, p) <= spare {
+        psrs(data, p);
+        return Ok(());
+    }
+
+    // The runs are sorted one at a time and then merged, so each step may
+    // use all of the spare memory: a run's PSRS buffers first, then a few
+    // read-ahead blocks per run.
+    let size = mem::size_of::<T>().max(1);
+    let run_len = spare.saturating_sub(psrs_aux_bytes::<T>(0, p)) / size;
+    let runs = n.div_ceil(run_len.max(1));
+    let block_len = (spare / (SPILL_BLOCKS_PER_RUN * runs.min(MAX_FAN_IN) * size)).min(IO_BLOCK);
+    if run_len < p.max(1) || block_len == 0 {
+        let message = format!("{n} elements leave {spare} bytes of a {budget} byte budget, too few to sort them");
+        return Err(io::Error::new(io::ErrorKind::OutOfMemory, message));
+    }
+
+    fs::create_dir_all(work_dir)?;
+    let mut spilled = SpillFiles(Vec::with_capacity(runs));
+    for (run, elements) in data.chunks_mut(run_len).enumerate() {
+        psrs(elements, p);
+        spilled.0.push(work_dir.join(format!("spill-{run}.bin")));
+        write_run(&spilled.0[run], elements, RunCompression::None)?;
+    }
+
+    // Too many runs to merge at once are merged a group at a time into
+    // new runs; those before `first` are gone.
+    let mut lens: Vec<usize> = data.chunks(run_len).map(<[T]>::len).collect();
+    let mut first = 0;
+    while lens.len() - first > MAX_FAN_IN {
+        let group = first..first + MAX_FAN_IN;
+        spilled.0.push(work_dir.join(format!("spill-{}.bin", lens.len())));
+        let (inputs, merged) = (&spilled.0[group.clone()], &spilled.0[lens.len()]);
+        merge_into_run::<T>(inputs, &lens[group.clone()], merged, RunCompression::None, block_len * T::SIZE)?;
+        for path in inputs {
+            fs::remove_file(path)?;
+        }
+        lens.push(lens[group.clone()].iter().sum());
+        first = group.end;
+    }
+
+    let mut readers = Vec::with_capacity(lens.len() - first);
+    for (path, &len) in spilled.0[first..].iter().zip(&lens[first..]) {
+        readers.push((open_run::<T>(path, 0, RunCompression::None)?, len * T::SIZE));
+    }
+    thread::scope(|s| {
+        let mut inputs = read_ahead(s, readers, block_len * T::SIZE);
+        let mut remaining = lens[first..].to_vec();
+        let mut bytes = vec![0; T::SIZE];
+        let mut heap = BinaryHeap::with_capacity(inputs.len());
+        for (run, input) in inputs.iter_mut().enumerate() {
+            remaining[run] -= 1;
+            heap.push(Reverse((read_one::<T>(input, &mut bytes)?, run)));
+        }
+        // Every run is on disk, so `data` is free to be overwritten.
+        for slot in data.iter_mut() {
+            let Reverse((element, run)) = heap.pop().expect("one element per slot");
+            *slot = element;
+            if remaining[run] > 0 {
+                remaining[run] -= 1;
+                heap.push(Reverse((read_one::<T>(&mut inputs[run], &mut bytes)?, run)));
+            }
+        }
+        Ok(())
+    })
+}
+
+/// The runs [`psrs_with_memory_budget`] spilled, removed when dropped so
+/// that a failed sort does not leave them behind either.
+struct SpillFiles(Vec<PathBuf>);
+
+impl Drop for SpillFiles {
+    fn drop(&mut self) {
+        for path in &self.0 {
+            // The last one may not have been created if writing it failed.
+            let _ = fs::remove_file(path);
+        }
+    }
+}
+
+/// Blocks each run of [`psrs_with_memory_budget`] holds while merging: the
+/// one being consumed and the one read ahead.
+const SPILL_BLOCKS_PER_RUN: usize = 2;
+
+/// What the merge hands to the thread writing the output.
+enum Output {
+    Bytes(Vec<u8>),
+    /// Everything sent so far is in the output: sync it, then save this
+    /// manifest.
+    Checkpoint(Manifest),
+}
+
+/// Merges the oldest `config.max_fan_in` runs into a new one until no more
+/// than that many are left for [`merge_runs`]. Each group is recorded in
+/// the manifest once its run is complete, so an interrupted sort redoes at
+/// most the group it was merging.
+fn reduce_runs<T>(manifest: &mut Manifest, config: &ExternalSortConfig) -> io::Result<()>
+where
+    T: FixedBytes + Ord,
+{
+    let dir = &config.work_dir;
+    let fan_in = config.max_fan_in.max(2);
+    while manifest.runs.len() - manifest.first_run > fan_in {
+        let group = manifest.first_run..manifest.first_run + fan_in;
+        let inputs: Vec<PathBuf> = group.clone().map(|run| run_path(dir, run)).collect();
+        let merged = run_path(dir, manifest.runs.len());
+        merge_into_run::<T>(&inputs, &manifest.runs[group.clone()], &merged, config.compression, IO_BLOCK * T::SIZE)?;
+        manifest.runs.push(manifest.runs[group.clone()].iter().sum());
+        manifest.first_run = group.end;
+        manifest.save(dir)?;
+        for path in inputs {
+            fs::remove_file(path)?;
+        }
+    }
+    Ok(())
+}
+
+/// K-way merges the sorted runs at `inputs`, of `lens` elements each, into
+/// a new run at `output`, reading them ahead in `block_bytes` blocks.
+fn merge_into_run<T: FixedBytes + Ord>(
+    inputs: &[PathBuf],
+    lens: &[usize],
+    output: &Path,
+    compression: RunCompression,
+    block_bytes: usize,
+) -> io::Result<()> {
+    let mut readers = Vec::with_capacity(inputs.len());
+    for (path, &len) in inputs.iter().zip(lens) {
+        readers.push((open_run::<T>(path, 0, compression)?, len * T::SIZE));
+    }
+    thread::scope(|s| {
+        let mut inputs = read_ahead(s, readers, block_bytes);
+        let mut remaining = lens.to_vec();
+        write_run_with(output, compression, |out| {
+            let mut bytes = vec![0; T::SIZE];
+            let mut heap = BinaryHeap::with_capacity(inputs.len());
+            for (run, input) in inputs.iter_mut().enumerate() {
+                if remaining[run] > 0 {
+                    remaining[run] -= 1;
+                    heap.push(Reverse((read_one::<T>(input, &mut bytes)?, run)));
+                }
+            }
+            while let Some(Reverse((element, run))) = heap.pop() {
+                element.write_bytes(&mut bytes);
+                out.write_all(&bytes)?;
+                if remaining[run] > 0 {
+                    remaining[run] -= 1;
+                    heap.push(Reverse((read_one::<T>(&mut inputs[run], &mut bytes)?, run)));
+                }
+            }
+            Ok(())
+        })
+    })
+}
+
+/// K-way merges the runs left by [`reduce_runs`] into `output`,
+/// checkpointing every `config.checkpoint_interval` elements.
+///
+/// The runs are read ahead a block at a time on a few shared threads and
+/// the output is written by another, so the merge itself rarely waits on
+/// disk.
+fn merge_runs<T>(output: &Path, manifest: &mut Manifest, config: &ExternalSortConfig) -> io::Result<()>
+where
+    T: FixedBytes + Clone + Ord + Send + Sync,
+{
+    let first = manifest.first_run;
+    if manifest.consumed.len() != manifest.runs.len() - first {
+        manifest.consumed = vec![0; manifest.runs.len() - first];
+        manifest.merged = 0;
+    }
+    // Anything past the last checkpoint is rewritten.
+    let mut file = OpenOptions::new().write(true).create(true).truncate(false).open(output)?;
+    file.set_len((manifest.merged * T::SIZE) as u64)?;
+    file.seek(SeekFrom::End(0))?;
+
+    let mut readers = Vec::with_capacity(manifest.consumed.len());
+    for (run, &consumed) in manifest.consumed.iter().enumerate() {
+        let reader = open_run::<T>(&run_path(&config.work_dir, first + run), consumed, config.compression)?;
+        readers.push((reader, (manifest.runs[first + run] - consumed) * T::SIZE));
+    }
+    let block_bytes = IO_BLOCK * T::SIZE;
+
+    thread::scope(|s| {
+        let mut inputs = read_ahead(s, readers, block_bytes);
+        let (out_tx, out_rx) = mpsc::sync_channel::<Output>(1);
+        let work_dir = &config.work_dir;
+        let writer = s.spawn(move || -> io::Result<()> {
+            for message in out_rx {
+                match message {
+                    Output::Bytes(bytes) => file.write_all(&bytes)?,
+                    Output::Checkpoint(manifest) => {
+                        file.sync_data()?;
+                        manifest.save(work_dir)?;
+                    }
+                }
+            }
+            file.sync_all()
+        });
+
+        let mut bytes = vec![0; T::SIZE];
+        let mut heap = BinaryHeap::with_capacity(inputs.len());
+        for (run, input) in inputs.iter_mut().enumerate() {
+            if manifest.consumed[run] < manifest.runs[first + run] {
+                heap.push(Reverse((read_one::<T>(input, &mut bytes)?, run)));
+            }
+        }
+
+        let interval = config.checkpoint_interval.max(1);
+        let mut block = Vec::with_capacity(block_bytes);
+        // A failed send means the writer gave up, and joining it reports why.
+        let send = |message| out_tx.send(message).is_ok();
+        while let Some(Reverse((element, run))) = heap.pop() {
+            element.write_bytes(&mut bytes);
+            block.extend_from_slice(&bytes);
+            manifest.consumed[run] += 1;
+            manifest.merged += 1;
+            if manifest.consumed[run] < manifest.runs[first + run] {
+                heap.push(Reverse((read_one::<T>(&mut inputs[run], &mut bytes)?, run)));
+            }
+            let checkpoint = manifest.merged.is_multiple_of(interval);
+            if (block.len() == block_bytes || checkpoint)
+                && !send(Output::Bytes(mem::replace(&mut block, Vec::with_capacity(block_bytes))))
+            {
+                break;
+            }
+            if checkpoint && !send(Output::Checkpoint(manifest.clone())) {
+                break;
+            }
+        }
+        if !block.is_empty() {
+            send(Output::Bytes(block));
+        }
+        drop(out_tx);
+        writer.join().expect("output writer panicked")
+    })
+}
+
+/// An input of a merge, read ahead by [`read_ahead`]'s threads.
+struct Source {
+    reader: Box<dyn Read + Send>,
+    /// Bytes left to read.
+    len: usize,
+    /// Where its blocks go; dropped after the last one.
+    blocks: Option<mpsc::SyncSender<io::Result<Vec<u8>>>>,
+}
+
+/// Starts reading ahead the next `len` bytes of each of `inputs` in
+/// `block_bytes` blocks, on at most [`IO_THREADS`] threads shared by all of
+/// them.
+///
+/// Each input has one block read ahead of the one being consumed: taking a
+/// block from its [`Prefetch`] queues the read of the next. The threads
+/// exit once every `Prefetch` is dropped.
+fn read_ahead<'scope>(
+    scope: &'scope thread::Scope<'scope, '_>,
+    inputs: Vec<(Box<dyn Read + Send>, usize)>,
+    block_bytes: usize,
+) -> Vec<Prefetch> {
+    let (requests, queue) = mpsc::channel();
+    let mut sources = Vec::with_capacity(inputs.len());
+    let mut prefetches = Vec::with_capacity(inputs.len());
+    for (input, (reader, len)) in inputs.into_iter().enumerate() {
+        let (tx, rx) = mpsc::sync_channel(1);
+        sources.push(Mutex::new(Source { reader, len, blocks: (len > 0).then_some(tx) }));
+        if len > 0 {
+            requests.send(input).expect("the queue outlives the requests");
+        }
+        prefetches.push(Prefetch { input, requests: requests.clone(), blocks: rx, block: Vec::new(), pos: 0 });
+    }
+    drop(requests);
+
+    let sources = Arc::new(sources);
+    let queue = Arc::new(Mutex::new(queue));
+    for _ in 0..IO_THREADS.min(prefetches.len()) {
+        let (sources, queue) = (Arc::clone(&sources), Arc::clone(&queue));
+        scope.spawn(move || loop {
+            // Not in the loop condition, which would hold the lock while
+            // reading.
+            let request = queue.lock().unwrap().recv();
+            let Ok(input) = request else { break };
+            let mut source = sources[input].lock().unwrap();
+            let Source { reader, len, blocks } = &mut *source;
+            // A request for a block past the end.
+            let Some(tx) = blocks else { continue };
+            let mut block = vec![0; (*len).min(block_bytes)];
+            *len -= block.len();
+            let result = reader.read_exact(&mut block).map(|()| block);
+            let last = *len == 0 || result.is_err();
+            // The channel has room: an input only asks for a block once it
+            // has taken the one before. It fails if the merge gave up.
+            let _ = tx.send(result);
+            if last {
+                *blocks = None;
+            }
+        });
+    }
+    prefetches
+}
+
+/// An input of a merge read ahead by [`read_ahead`], one block at a time.
+struct Prefetch {
+    input: usize,
+    requests: mpsc::Sender<usize>,
+    blocks: mpsc::Receiver<io::Result<Vec<u8>>>,
+    block: Vec<u8>,
+    pos: usize,
+}
+
+impl Read for Prefetch {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        if self.pos == self.block.len() {
+            match self.blocks.recv() {
+                Ok(block) => {
+                    self.block = block?;
+                    self.pos = 0;
+                    // Only fails once the I/O threads are gone, and then
+                    // the next `recv` reports the end.
+                    let _ = self.requests.send(self.input);
+                }
+                Err(_) => return Ok(0),
+            }
+        }
+        let n = buf.len().min(self.block.len() - self.pos);
+        buf[..n].copy_from_slice(&self.block[self.pos..self.pos + n]);
+        self.pos += n;
+        Ok(n)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::testing::{edge_cases, scratch_dir, std_sorted};
+
+    fn write_file(path: &Path, values: &[u64]) {
+        let mut writer = BufWriter::new(File::create(path).unwrap());
+        write_elements(&mut writer, values).unwrap();
+        writer.flush().unwrap();
+    }
+
+    fn read_file(path: &Path) -> Vec<u64> {
+        let len = fs::metadata(path).unwrap().len() as usize / u64::SIZE;
+        read_elements(&mut BufReader::new(File::open(path).unwrap()), len).unwrap()
+    }
+
+    /// A config with short runs and frequent checkpoints, so small inputs
+    /// still spill several runs.
+    fn small_runs(work_dir: PathBuf) -> ExternalSortConfig {
+        ExternalSortConfig { threads: 3, run_len: 1_000, checkpoint_interval: 700, ..ExternalSortConfig::new(work_dir) }
+    }
+
+    #[test]
+    fn external_sort_matches_std_sort() {
+        let dir = scratch_dir("external-sort");
+        let (input, output) = (dir.join("input.bin"), dir.join("output.bin"));
+        for (name, values) in edge_cases(3) {
+            write_file(&input, &values);
+            external_sort::<u64>(&input, &output, &small_runs(dir.join("work"))).unwrap();
+            assert_eq!(read_file(&output), std_sorted(&values), "{name}");
+            assert!(!dir.join("work").join(MANIFEST).exists(), "{name}: the work files must be removed");
+        }
+        fs::remove_dir_all(dir).unwrap();
+    }
+
+    #[test]
+    fn external_sort_resumes_from_spilled_runs() {
+        let dir = scratch_dir("external-resume");
+        let (input, output) = (dir.join("input.bin"), dir.join("output.bin"));
+        let values = crate::testing::random_values(5_500, u64::MAX, 15);
+        write_file(&input, &values);
+        let config = small_runs(dir.join("work"));
+
+        // Spill every run as an interrupted sort would have, and poison the
+        // input so a sort starting over would notice.
+        fs::create_dir_all(&config.work_dir).unwrap();
+        let mut manifest = Manifest { input_len: values.len(), element_size: 8, run_len: 1_000, ..Manifest::default() };
+        spill_runs::<u64>(&input, &mut manifest, &config).unwrap();
+        assert_eq!(Manifest::load(&config.work_dir).unwrap(), Some(manifest));
+        write_file(&input, &vec![0; values.len()]);
+
+        external_sort::<u64>(&input, &output, &config).unwrap();
+        assert_eq!(read_file(&output), std_sorted(&values));
+        fs::remove_dir_all(dir).unwrap();
+    }
+
+    #[test]
+    fn many_runs_are_merged_in_groups() {
+        let dir = scratch_dir("external-fan-in");
+        let (input, output) = (dir.join("input.bin"), dir.join("output.bin"));
+        let values = crate::testing::random_values(1_000, 500, 28);
+        write_file(&input, &values);
+        let config = ExternalSortConfig { run_len: 10, max_fan_in: 3, ..small_runs(dir.join("work")) };
+
+        // Reduce the 100 runs as an interrupted sort would have, then check
+        // that the sort resumes from the merged groups alone.
+        fs::create_dir_all(&config.work_dir).unwrap();
+        let mut manifest = Manifest { input_len: values.len(), element_size: 8, run_len: 10, ..Manifest::default() };
+        spill_runs::<u64>(&input, &mut manifest, &config).unwrap();
+        reduce_runs::<u64>(&mut manifest, &config).unwrap();
+        assert!(manifest.runs.len() - manifest.first_run <= 3, "{manifest:?}");
+        assert_eq!(manifest.runs[manifest.first_run..].iter().sum::<usize>(), values.len());
+        assert!(!run_path(&config.work_dir, 0).exists(), "merged runs must be removed");
+        assert_eq!(Manifest::load(&config.work_dir).unwrap(), Some(manifest));
+        write_file(&input, &vec![0; values.len()]);
+
+        external_sort::<u64>(&input, &output, &config).unwrap();
+        assert_eq!(read_file(&output), std_sorted(&values));
+        assert_eq!(fs::read_dir(&config.work_dir).unwrap().count(), 0, "the work files must be removed");
+        fs::remove_dir_all(dir).unwrap();
+    }
+
+    #[cfg(any(feature = "lz4", feature = "zstd"))]
+    #[test]
+    fn compressed_runs_match_std_sort() {
+        let dir = scratch_dir("external-compressed");
+        let (input, output) = (dir.join("input.bin"), dir.join("output.bin"));
+        let values = crate::testing::random_values(4_200, 1_000, 16);
+        write_file(&input, &values);
+        let compressions = [
+            #[cfg(feature = "lz4")]
+            RunCompression::Lz4,
+            #[cfg(feature = "zstd")]
+            RunCompression::Zstd { level: 3 },
+        ];
+        for compression in compressions {
+            let config = ExternalSortConfig { compression, ..small_runs(dir.join("work")) };
+            external_sort::<u64>(&input, &output, &config).unwrap();
+            assert_eq!(read_file(&output), std_sorted(&values), "{compression:?}");
+        }
+        fs::remove_dir_all(dir).unwrap();
+    }
+
+    #[test]
+    fn merge_sorted_files_matches_std_sort() {
+        let dir = scratch_dir("merge-files");
+        let output = dir.join("output.bin");
+        for (name, values) in edge_cases(4) {
+            let shards: Vec<PathBuf> = (0..4).map(|i| dir.join(format!("shard-{i}.bin"))).collect();
+            for (i, shard) in shards.iter().enumerate() {
+                let part: Vec<u64> = values.iter().copied().skip(i).step_by(4).collect();
+                write_file(shard, &std_sorted(&part));
+            }
+            merge_sorted_files::<u64>(&shards, &output).unwrap();
+            assert_eq!(read_file(&output), std_sorted(&values), "{name}");
+        }
+
+        let unsorted = dir.join("unsorted.bin");
+        write_file(&unsorted, &[1, 3, 2]);
+        let err = merge_sorted_files::<u64>(&[unsorted], &output).unwrap_err();
+        assert_eq!(err.kind(), io::ErrorKind::InvalidData);
+        fs::remove_dir_all(dir).unwrap();
+    }
+
+    #[test]
+    fn memory_budget_spills_when_psrs_does_not_fit() {
+        let dir = scratch_dir("memory-budget");
+        for (name, values) in edge_cases(4) {
+            let input_bytes = mem::size_of_val(values.as_slice());
+            // Room for a quarter of the merge buffers: several runs spill.
+            let budget = input_bytes + psrs_aux_bytes::<u64>(values.len(), 4) / 4 + 4096;
+            let work_dir = dir.join(name.replace(' ', "-"));
+            let mut data = values.clone();
+            psrs_with_memory_budget(&mut data, 4, budget, &work_dir).unwrap();
+            assert_eq!(data, std_sorted(&values), "{name}");
+            let spilled = psrs_aux_bytes::<u64>(values.len(), 4) > budget - input_bytes;
+            assert_eq!(work_dir.exists(), spilled, "{name}: spilled");
+            if spilled {
+                assert_eq!(fs::read_dir(&work_dir).unwrap().count(), 0, "{name}: the spilled runs must be removed");
+            }
+        }
+        fs::remove_dir_all(dir).unwrap();
+    }
+
+    #[test]
+    fn memory_budget_fits_or_fails_cleanly() {
+        let dir = scratch_dir("memory-budget-limits");
+        let values = crate::testing::random_values(10_000, u64::MAX, 17);
+        let input_bytes = mem::size_of_val(values.as_slice());
+
+        let mut data = values.clone();
+        let budget = input_bytes + psrs_aux_bytes::<u64>(10_000, 4);
+        psrs_with_memory_budget(&mut data, 4, budget, &dir.join("roomy")).unwrap();
+        assert_eq!(data, std_sorted(&values));
+        assert!(!dir.join("roomy").exists(), "an input that fits must not spill");
+
+        let mut data = values.clone();
+        let err = psrs_with_memory_budget(&mut data, 4, input_bytes + 64, &dir.join("tight")).unwrap_err();
+        assert_eq!(err.kind(), io::ErrorKind::OutOfMemory);
+        assert_eq!(data, values, "a failed sort must leave the data untouched");
+        fs::remove_dir_all(dir).unwrap();
+    }
+
+    #[test]
+    fn memory_budget_merges_many_runs_in_groups() {
+        let dir = scratch_dir("memory-budget-fan-in");
+        let values = crate::testing::random_values(40_000, u64::MAX, 29);
+        // Runs of about 250 elements: 160 of them, over the fan-in.
+        let budget = mem::size_of_val(values.as_slice()) + 2048;
+        let mut data = values.clone();
+        psrs_with_memory_budget(&mut data, 1, budget, &dir).unwrap();
+        assert_eq!(data, std_sorted(&values));
+        assert_eq!(fs::read_dir(&dir).unwrap().count(), 0, "the spilled runs must be removed");
+        fs::remove_dir_all(dir).unwrap();
+    }
+
+    #[test]
+    fn failed_spills_are_removed() {
+        let dir = scratch_dir("memory-budget-failure");
+        let values = crate::testing::random_values(10_000, u64::MAX, 27);
+        let budget = mem::size_of_val(values.as_slice()) + psrs_aux_bytes::<u64>(10_000, 4) / 4;
+        // A directory in the way of the second run makes spilling it fail.
+        fs::create_dir_all(dir.join("spill-1.bin")).unwrap();
+        let mut data = values.clone();
+        assert!(psrs_with_memory_budget(&mut data, 4, budget, &dir).is_err());
+        assert!(!dir.join("spill-0.bin").exists(), "the first run must be removed");
+        fs::remove_dir_all(dir).unwrap();
+    }
+}
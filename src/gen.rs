@@ -0,0 +1,151 @@
+//! `gen` subcommand: writes a generated dataset straight to a binary file,
+//! so inputs for `external_sort` can be larger than memory.
+//!
+//! The file is generated in blocks of [`GENERATE_CHUNK_LEN`] values, a batch
+//! of blocks at a time in parallel, while a writer thread streams the
+//! previous batch to disk. Each block has its own random stream, so the
+//! file only depends on the arguments and the seed.
+
+use std::fs::File;
+use std::io::{self, BufWriter, Write};
+use std::sync::mpsc;
+use std::thread;
+use std::time::Instant;
+
+use rayon::prelude::*;
+
+use crate::datagen::{generate_block, Distribution, GENERATE_CHUNK_LEN};
+
+/// Options of `gen`, see [`usage`].
+struct GenArgs {
+    bytes: u64,
+    distribution: Distribution,
+    out: String,
+    /// Bytes per value: 4 for `u32`, 8 for `u64`.
+    width: usize,
+    max: Option<u64>,
+    seed: u64,
+}
+
+fn usage() -> ! {
+    eprintln!(
+        "usage: gen --size SIZE --out data.bin [--distribution uniform|zipf] [--type u32|u64] \
+         [--max N] [--seed N]\n\
+         Writes SIZE bytes (with an optional K, M, G or T suffix, powers of 1024) of \
+         little-endian values from 0..N, by default the whole range of the type. Zipf \
+         values are most often 0, half as often 1, and so on."
+    );
+    std::process::exit(2)
+}
+
+/// Parses a byte count such as `512M` or `200G`.
+fn parse_size(size: &str) -> Option<u64> {
+    let (digits, shift) = match size.as_bytes().last()? {
+        b'K' | b'k' => (&size[..size.len() - 1], 10),
+        b'M' | b'm' => (&size[..size.len() - 1], 20),
+        b'G' | b'g' => (&size[..size.len() - 1], 30),
+        b'T' | b't' => (&size[..size.len() - 1], 40),
+        _ => (size, 0),
+    };
+    digits.parse::<u64>().ok()?.checked_mul(1 << shift)
+}
+
+fn parse_args(mut args: impl Iterator<Item = String>) -> GenArgs {
+    let (mut bytes, mut out) = (None, None);
+    let mut parsed = GenArgs {
+        bytes: 0,
+        distribution: Distribution::Uniform,
+        out: String::new(),
+        width: 4,
+        max: None,
+        seed: rand::random(),
+    };
+    while let Some(arg) = args.next() {
+        let mut value = || args.next().unwrap_or_else(|| usage());
+        match arg.as_str() {
+            "--size" => bytes = Some(parse_size(&value()).unwrap_or_else(|| usage())),
+            "--out" => out = Some(value()),
+            "--distribution" => {
+                parsed.distribution = match value().as_str() {
+                    "uniform" => Distribution::Uniform,
+                    "zipf" => Distribution::Zipf,
+                    _ => usage(),
+                }
+            }
+            "--type" => {
+                parsed.width = match value().as_str() {
+                    "u32" => 4,
+                    "u64" => 8,
+                    _ => usage(),
+                }
+            }
+            "--max" => parsed.max = Some(value().parse().unwrap_or_else(|_| usage())),
+            "--seed" => parsed.seed = value().parse().unwrap_or_else(|_| usage()),
+            _ => usage(),
+        }
+    }
+    let (Some(bytes), Some(out)) = (bytes, out) else { usage() };
+    parsed.bytes = bytes;
+    parsed.out = out;
+    parsed
+}
+
+/// Runs `gen` with the arguments that follow the subcommand name.
+pub fn main(args: impl Iterator<Item = String>) {
+    let args = parse_args(args);
+    let start = Instant::now();
+    match generate_file(&args) {
+        Ok(len) => println!(
+            "wrote {len} values ({} bytes) to {} in {:.1?} with seed {}",
+            len * args.width as u64,
+            args.out,
+            start.elapsed(),
+            args.seed
+        ),
+        Err(e) => {
+            eprintln!("gen: {e}");
+            std::process::exit(1);
+        }
+    }
+}
+
+/// Generates the file described by `args` and returns its number of values.
+fn generate_file(args: &GenArgs) -> io::Result<u64> {
+    let len = args.bytes / args.width as u64;
+    let type_max = if args.width == 4 { 1 << 32 } else { u64::MAX };
+    let max = args.max.unwrap_or(type_max).min(type_max).max(1);
+    let blocks = len.div_ceil(GENERATE_CHUNK_LEN as u64) as usize;
+    let batch = 2 * rayon::current_num_threads();
+    let mut out = BufWriter::new(File::create(&args.out)?);
+
+    let (sender, receiver) = mpsc::sync_channel::<Vec<Vec<u8>>>(1);
+    thread::scope(|s| {
+        let writer = s.spawn(move || -> io::Result<()> {
+            for batch in receiver {
+                batch.iter().try_for_each(|block| out.write_all(block))?;
+            }
+            out.flush()
+        });
+        for first in (0..blocks).step_by(batch) {
+            let encoded: Vec<Vec<u8>> = (first..blocks.min(first + batch))
+                .into_par_iter()
+                .map(|index| {
+                    let start = index as u64 * GENERATE_CHUNK_LEN as u64;
+                    let block_len = (len - start).min(GENERATE_CHUNK_LEN as u64) as usize;
+                    let values = generate_block(index, block_len, max, args.distribution, args.seed);
+                    match args.width {
+                        4 => values.iter().flat_map(|&v| (v as u32).to_le_bytes()).collect(),
+                        _ => values.iter().flat_map(|&v| v.to_le_bytes()).collect(),
+                    }
+                })
+                .collect();
+            // A failed writer drops the receiver; its error is reported below.
+            if sender.send(encoded).is_err() {
+                break;
+            }
+        }
+        drop(sender);
+        writer.join().expect("writer thread panicked")
+    })?;
+    Ok(len)
+}
@@ -0,0 +1,1013 @@
+//! Parallel Sorting by Regular Sampling (PSRS).
+//!
+//! The sort runs in four phases: every chunk is sorted locally, regular
+//! samples are drawn from the sorted chunks to pick `p - 1` pivots, each
+//! chunk is split at the pivots, and finally the matching pieces of every
+//! chunk are k‑way merged into the output. The [`phases`] module exposes
+//! each of them on its own.
+//!
+//! The phases run on an [`Executor`]: Rayon by default, or plain scoped
+//! `std` threads when the `rayon` feature is disabled.
+
+use std::cmp::Ordering;
+use std::ops::Range;
+use std::panic::{self, AssertUnwindSafe};
+use std::sync::atomic::{self, AtomicUsize};
+use std::sync::{Mutex, OnceLock};
+use allocator_api2::alloc::{Allocator, Global};
+use allocator_api2::vec::Vec as AVec;
+use local_sort::{unstable_sort, unstable_sort_by};
+
+#[cfg(feature = "affinity")]
+pub use affinity::{pinned_thread_pool, PinnedExecutor};
+pub use allocator_api2;
+#[cfg(feature = "icu")]
+pub use icu_collator;
+pub use balance::PartitionStats;
+#[cfg(feature = "tokio")]
+pub use async_sort::{psrs_async, psrs_async_with_progress};
+#[cfg(feature = "rayon")]
+pub use batch::psrs_batch;
+
+pub use budget::ThreadBudget;
+pub use cancel::CancellationToken;
+#[cfg(feature = "icu")]
+pub use collation::{psrs_collated, Collation, CollationError};
+pub use config::{MergeStructure, PhaseThreads, PsrsConfig};
+pub use counting::{psrs_counting, psrs_integers, CountingKey, COUNTING_MAX_RANGE};
+pub use error::PsrsError;
+#[cfg(feature = "rayon")]
+pub use executor::RayonExecutor;
+pub use executor::{default_executor, Executor, ScopedThreadExecutor};
+#[cfg(all(feature = "hugepages", target_os = "linux"))]
+pub use hugepages::{HugePageAlloc, HUGE_PAGE_SIZE};
+pub use merge::{k_way_merge, k_way_merge_by, KWayMergeIter};
+pub use nulls::{psrs_nullable, psrs_nullable_by, NullOrder};
+pub use out_of_place::psrs_to;
+pub use pairs::psrs_pairs;
+#[cfg(feature = "serde")]
+pub use profile::{TunedEntry, TuningProfile, PROFILE_ENV};
+pub use progress::{Phase, Progress};
+pub use quantiles::{psrs_quantiles, Quantile, QUANTILE_SAMPLES_PER_CHUNK};
+pub use radix::{msb_radix_sort, radix_sort, RadixKey};
+pub use rank_index::RankIndex;
+pub use records::{
+    psrs_binary_records, psrs_indirect, psrs_indirect_by_key, psrs_records, psrs_rows, RecordKey, RecordLayout, SortKey,
+};
+use presorted::Presorted;
+pub use sortedness::{inversions, inversions_by, sortedness};
+pub use strings::{multikey_quicksort, natural_cmp};
+pub use table::{psrs_table_order, SortColumn};
+pub use timeline::{Timeline, TimelineEvent};
+pub use topology::{core_weights, CacheSizes};
+
+/// Enters a `tracing` span for the rest of the enclosing block when the
+/// `tracing` feature is enabled; expands to nothing otherwise, so the field
+/// expressions are not evaluated either.
+macro_rules! span {
+    ($name:literal $(, $($fields:tt)*)?) => {
+        #[cfg(feature = "tracing")]
+        let _span = tracing::info_span!($name $(, $($fields)*)?).entered();
+    };
+}
+
+mod balance;
+mod budget;
+mod cancel;
+mod config;
+mod counting;
+mod error;
+mod executor;
+#[cfg(all(feature = "hugepages", target_os = "linux"))]
+mod hugepages;
+mod local_sort;
+mod merge;
+mod nulls;
+mod out_of_place;
+mod pairs;
+pub mod phases;
+mod presorted;
+mod progress;
+mod quantiles;
+mod radix;
+mod rank_index;
+mod records;
+mod sortedness;
+mod strings;
+mod table;
+mod timeline;
+#[cfg(test)]
+mod testing;
+mod topology;
+#[cfg(feature = "affinity")]
+mod affinity;
+#[cfg(feature = "arrow")]
+pub mod arrow;
+#[cfg(feature = "tokio")]
+mod async_sort;
+#[cfg(feature = "rayon")]
+mod batch;
+#[cfg(feature = "icu")]
+mod collation;
+#[cfg(not(target_arch = "wasm32"))]
+pub mod external;
+#[cfg(feature = "polars")]
+pub mod polars;
+#[cfg(feature = "serde")]
+mod profile;
+#[cfg(feature = "python")]
+mod python;
+#[cfg(all(feature = "wasm", target_arch = "wasm32"))]
+pub mod wasm;
+
+/// Observers and policies threaded through a single PSRS run of `T`s.
+struct Hooks<'a, T> {
+    executor: &'a dyn Executor,
+    progress: &'a (dyn Fn(Progress) + Sync),
+    cancel: Option<&'a CancellationToken>,
+    /// Turn panics inside a phase into [`PsrsError::Panicked`] instead of
+    /// unwinding into the caller.
+    catch_panics: bool,
+    /// Receives the partition sizes and pivot duplicates of the run.
+    stats: Option<&'a OnceLock<PartitionStats>>,
+    /// Limits the workers of every phase, read as each phase starts.
+    budget: Option<&'a ThreadBudget>,
+    /// Receives the merged partitions in order, each as soon as it and all
+    /// before it are final.
+    partitions: Option<&'a PartitionSink<'a, T>>,
+    /// Receives the pivots and partition sizes of the run.
+    index: Option<&'a OnceLock<RankIndex<T>>>,
+    /// Records when each phase, chunk sort and merge task ran, and where.
+    timeline: Option<&'a Timeline>,
+    /// The run sorts by `T`'s own `Ord`, so `u8` and `u16` inputs can be
+    /// counted instead; set by [`psrs_ord`].
+    natural_order: bool,
+}
+
+/// Callback of [`psrs_streaming`], called with one merged partition.
+type PartitionSink<'a, T> = dyn Fn(&[T]) + Sync + 'a;
+
+impl<T> Hooks<'_, T> {
+    fn new() -> Self {
+        Hooks {
+            executor: default_executor(),
+            progress: &|_| {},
+            cancel: None,
+            catch_panics: false,
+            stats: None,
+            budget: None,
+            partitions: None,
+            index: None,
+            timeline: None,
+            natural_order: false,
+        }
+    }
+
+    /// Hands the merged partitions in `slots` that directly follow the
+    /// `delivered` ones so far to the `partitions` sink. Whichever merge
+    /// finishes the next partition due delivers it, along with any later
+    /// ones already waiting.
+    fn deliver_partitions<A: Allocator>(&self, slots: &[OnceLock<AVec<T, A>>], delivered: &Mutex<usize>) {
+        let Some(sink) = self.partitions else { return };
+        let mut next = delivered.lock().unwrap();
+        while let Some(partition) = slots.get(*next).and_then(OnceLock::get) {
+            sink(partition);
+            *next += 1;
+        }
+    }
+
+    fn record_stats(&self, stats: impl FnOnce() -> PartitionStats) {
+        if let Some(sink) = self.stats {
+            let _ = sink.set(stats());
+        }
+    }
+
+    fn record_index(&self, index: impl FnOnce() -> RankIndex<T>) {
+        if let Some(sink) = self.index {
+            let _ = sink.set(index());
+        }
+    }
+
+    /// Runs `f` as `task` of `phase`, or as the whole phase if `None`,
+    /// recording it on the `timeline` if there is one.
+    fn timed<R>(&self, phase: Phase, task: Option<usize>, len: usize, f: impl FnOnce() -> R) -> R {
+        match self.timeline {
+            Some(timeline) => timeline.record(phase, task, len, f),
+            None => f(),
+        }
+    }
+
+    fn check_cancelled(&self) -> Result<(), PsrsError> {
+        match self.cancel {
+            Some(token) if token.is_cancelled() => Err(PsrsError::Cancelled),
+            _ => Ok(()),
+        }
+    }
+
+    /// Runs one phase, catching panics from it (and from the Rayon workers
+    /// it spawned) when `catch_panics` is set.
+    fn phase<R>(&self, phase: Phase, f: impl FnOnce() -> Result<R, PsrsError>) -> Result<R, PsrsError> {
+        self.check_cancelled()?;
+        (self.progress)(Progress::PhaseStarted(phase));
+        let f = || self.timed(phase, None, 0, f);
+        if !self.catch_panics {
+            return f();
+        }
+        panic::catch_unwind(AssertUnwindSafe(f)).unwrap_or_else(|payload| {
+            let message = payload
+                .downcast_ref::<&str>()
+                .map(|s| s.to_string())
+                .or_else(|| payload.downcast_ref::<String>().cloned())
+                .unwrap_or_else(|| "unknown panic payload".to_string());
+            Err(PsrsError::Panicked { phase, message })
+        })
+    }
+}
+
+/// The PSRS implementation, run on the [`default_executor`].
+///
+/// `p` is the number of chunks the input is split into. Inputs shorter than
+/// `p` (and `p <= 1`) are sorted serially. Phase 4 merges into fresh buffers,
+/// so every element is cloned once; for `Copy` types that is a plain copy.
+///
+/// Inputs with few distinct values, even fewer than `p`, are handled too:
+/// duplicate pivots are collapsed, and runs of elements equal to a pivot
+/// are split across adjacent partitions rather than all merged by a single
+/// task. Partitions left empty are folded into a neighbour, so fewer than
+/// `p` merge tasks may run.
+///
+/// Chunks that are already ascending are not sorted again, and descending
+/// ones are just reversed, so nearly sorted input such as log-structured
+/// data skips most of the local sorting.
+///
+/// `u8` and `u16` slices skip the pipeline altogether, here and in the
+/// other entry points sorting by `Ord`: a parallel histogram, prefix sum
+/// and scatter ([`psrs_integers`]) sorts them far faster. Progress, stats
+/// and the other observers see such a run like a serial one.
+pub fn psrs<T: Clone + Ord + Send + Sync>(data: &mut [T], p: usize) {
+    psrs_ord(data, &PsrsConfig::with_threads(p), Hooks::new(), Global).expect("sort without a token cannot fail");
+}
+
+/// Like [`psrs`], but orders elements with `compare` instead of `Ord`.
+///
+/// A panicking comparator unwinds into the caller just like `slice::sort_by`;
+/// use [`try_psrs_by`] to get an error instead.
+pub fn psrs_by<T, F>(data: &mut [T], p: usize, compare: F)
+where
+    T: Clone + Send + Sync,
+    F: Fn(&T, &T) -> Ordering + Sync,
+{
+    let local_sort = |chunk: &mut [T]| unstable_sort_by(chunk, &compare);
+    psrs_default(data, p, &compare, &local_sort, &Hooks::new())
+        .expect("sort without a token cannot fail");
+}
+
+/// Like [`psrs_by`], but a panic in the comparator is caught and returned as
+/// [`PsrsError::Panicked`] naming the phase it happened in.
+pub fn try_psrs_by<T, F>(data: &mut [T], p: usize, compare: F) -> Result<(), PsrsError>
+where
+    T: Clone + Send + Sync,
+    F: Fn(&T, &T) -> Ordering + Sync,
+{
+    let hooks = Hooks { catch_panics: true, ..Hooks::new() };
+    let local_sort = |chunk: &mut [T]| unstable_sort_by(chunk, &compare);
+    psrs_default(data, p, &compare, &local_sort, &hooks)
+}
+
+/// Like [`psrs`], but reports phase transitions and per-chunk/per-partition
+/// completion to `progress`, e.g. to drive a progress bar.
+///
+/// The callback runs on the Rayon worker that finished the work item, so it
+/// should be cheap; forwarding into an `mpsc::Sender` works well.
+pub fn psrs_with_progress<T, F>(data: &mut [T], p: usize, progress: F)
+where
+    T: Clone + Ord + Send + Sync,
+    F: Fn(Progress) + Sync,
+{
+    let hooks = Hooks { progress: &progress, ..Hooks::new() };
+    psrs_ord(data, &PsrsConfig::with_threads(p), hooks, Global).expect("sort without a token cannot fail");
+}
+
+/// Like [`psrs`], but gives up with [`PsrsError::Cancelled`] soon after
+/// `cancel` is triggered.
+///
+/// The token is checked between phases, before each chunk sort and
+/// periodically inside the merges. Panics are reported as
+/// [`PsrsError::Panicked`].
+pub fn try_psrs<T: Clone + Ord + Send + Sync>(
+    data: &mut [T],
+    p: usize,
+    cancel: &CancellationToken,
+) -> Result<(), PsrsError> {
+    let hooks = Hooks { cancel: Some(cancel), catch_panics: true, ..Hooks::new() };
+    psrs_ord(data, &PsrsConfig::with_threads(p), hooks, Global)
+}
+
+/// Combines [`try_psrs`] and [`psrs_with_progress`].
+pub fn try_psrs_with_progress<T, F>(
+    data: &mut [T],
+    p: usize,
+    cancel: &CancellationToken,
+    progress: F,
+) -> Result<(), PsrsError>
+where
+    T: Clone + Ord + Send + Sync,
+    F: Fn(Progress) + Sync,
+{
+    let hooks = Hooks { progress: &progress, cancel: Some(cancel), catch_panics: true, ..Hooks::new() };
+    psrs_ord(data, &PsrsConfig::with_threads(p), hooks, Global)
+}
+
+/// [`psrs_impl`] in `T`'s own order with `sort_unstable` for the local sorts,
+/// where `u8` and `u16` inputs are counted instead.
+fn psrs_ord<T, A>(data: &mut [T], config: &PsrsConfig, hooks: Hooks<T>, alloc: A) -> Result<(), PsrsError>
+where
+    T: Clone + Ord + Send + Sync,
+    A: Allocator + Clone + Send + Sync,
+{
+    let hooks = Hooks { natural_order: true, ..hooks };
+    psrs_impl(data, config, &T::cmp, &unstable_sort::<T>, &hooks, alloc)
+}
+
+/// [`psrs_impl`] with the default config for `p` chunks and the global allocator.
+fn psrs_default<T, F, S>(data: &mut [T], p: usize, compare: &F, local_sort: &S, hooks: &Hooks<T>) -> Result<(), PsrsError>
+where
+    T: Clone + Send + Sync,
+    F: Fn(&T, &T) -> Ordering + Sync,
+    S: Fn(&mut [T]) + Sync,
+{
+    psrs_impl(data, &PsrsConfig::with_threads(p), compare, local_sort, hooks, Global)
+}
+
+/// Sorts `data` with `local_sort` doing the Phase 1 chunk sorts (and the
+/// sample sort); it must agree with `compare`. The samples, boundaries and
+/// merged partitions are allocated in `alloc`.
+fn psrs_impl<T, F, S, A>(
+    data: &mut [T],
+    config: &PsrsConfig,
+    compare: &F,
+    local_sort: &S,
+    hooks: &Hooks<T>,
+    alloc: A,
+) -> Result<(), PsrsError>
+where
+    T: Clone + Send + Sync,
+    F: Fn(&T, &T) -> Ordering + Sync,
+    S: Fn(&mut [T]) + Sync,
+    A: Allocator + Clone + Send + Sync,
+{
+    let p = config.chunks();
+    let progress = hooks.progress;
+    let n = data.len();
+    if config.check_presorted {
+        let presorted = hooks.phase(Phase::PresortedCheck, || Ok(presorted::detect(hooks.executor, data, compare)))?;
+        if presorted != Presorted::Unsorted {
+            if presorted == Presorted::Descending {
+                presorted::par_reverse(hooks.executor, data);
+            }
+            hooks.record_stats(|| PartitionStats::new(vec![n], 0));
+            hooks.record_index(|| RankIndex::new(Vec::new(), &[n]));
+            if let Some(sink) = hooks.partitions {
+                sink(data);
+            }
+            progress(Progress::Finished);
+            return Ok(());
+        }
+    }
+    // Counted `u8` and `u16` inputs look like serial runs to the hooks.
+    let counted = hooks.natural_order && counting::is_small_integer::<T>();
+    if counted || p <= 1 || n < p || n < config.serial_cutoff {
+        hooks.phase(Phase::LocalSort, || {
+            if counted {
+                let exec = executor::limited(hooks.executor, config.phase_threads.local_sort, hooks.budget);
+                counting::sort_small_integers(&exec, data, p);
+            } else {
+                presorted::sort_chunk(data, compare, local_sort);
+            }
+            progress(Progress::Completed { phase: Phase::LocalSort, done: 1, total: 1 });
+            Ok(())
+        })?;
+        hooks.record_stats(|| PartitionStats::new(vec![n], 0));
+        hooks.record_index(|| RankIndex::new(Vec::new(), &[n]));
+        if let Some(sink) = hooks.partitions {
+            sink(data);
+        }
+        progress(Progress::Finished);
+        return Ok(());
+    }
+    let exec = hooks.executor;
+    let chunk_lens = chunk_lens(n, p, &config.core_weights);
+    let num_chunks = chunk_lens.len();
+    span!("psrs", n, p);
+
+    // Phase 1: Sort each chunk in parallel. Chunks that already are runs,
+    // common in nearly sorted input, are kept (or reversed) as they are.
+    hooks.phase(Phase::LocalSort, || {
+        span!("phase1_local_sort");
+        let done = AtomicUsize::new(0);
+        let exec = executor::limited(exec, config.phase_threads.local_sort, hooks.budget);
+        executor::par_split_mut_map(&exec, data, &chunk_lens, |chunk_idx, chunk| {
+            hooks.check_cancelled()?;
+            span!("sort_chunk", chunk = chunk_idx, len = chunk.len());
+            let len = chunk.len();
+            hooks.timed(Phase::LocalSort, Some(chunk_idx), len, || presorted::sort_chunk(chunk, compare, local_sort));
+            let done = done.fetch_add(1, atomic::Ordering::Relaxed) + 1;
+            progress(Progress::Completed { phase: Phase::LocalSort, done, total: num_chunks });
+            Ok(())
+        })
+        .into_iter()
+        .collect::<Result<(), _>>()
+    })?;
+    let mut rest: &[T] = data;
+    let chunks: Vec<&[T]> = chunk_lens
+        .iter()
+        .map(|&len| {
+            let (chunk, tail) = rest.split_at(len);
+            rest = tail;
+            chunk
+        })
+        .collect();
+
+    // Phase 2: From each sorted chunk, take p * oversampling regular samples.
+    let pivots: Vec<T> = hooks.phase(Phase::Sampling, || {
+        span!("phase2_sampling");
+        let weights = &config.core_weights;
+        Ok(phases::sample_pivots(exec, &chunks, p, config.oversampling, weights, local_sort, alloc.clone()))
+    })?;
+
+    // Phase 3: Compute partition boundaries for each chunk.
+    let boundaries = hooks.phase(Phase::Boundaries, || {
+        span!("phase3_boundaries");
+        let exec = executor::limited(exec, config.phase_threads.boundaries, hooks.budget);
+        Ok(phases::partition_boundaries(&exec, &chunks, &pivots, compare, &config.core_weights, alloc.clone()))
+    })?;
+    let sizes: Vec<usize> = (0..p)
+        .map(|part_idx| boundaries.rows().map(|b| b[part_idx + 1] - b[part_idx]).sum())
+        .collect();
+    hooks.record_stats(|| {
+        let duplicates = pivots.windows(2).filter(|w| compare(&w[0], &w[1]) == Ordering::Equal).count();
+        PartitionStats::new(sizes.clone(), duplicates)
+    });
+    hooks.record_index(|| RankIndex::new(pivots.clone(), &sizes));
+
+    // Phase 4: For each group of partitions, merge the corresponding pieces of
+    // every chunk. Adjacent partitions are contiguous within each chunk, so a
+    // group of coalesced small partitions is merged like one wider partition.
+    // Partitions sized for particular cores are never grouped, so merge task
+    // `i` stays partition `i`.
+    let merged_partitions: Vec<AVec<T, A>> = hooks.phase(Phase::Merge, || {
+        span!("phase4_merge");
+        let groups = match config.core_weights.is_empty() {
+            true => coalesce_partitions(&sizes, config.min_partition_size),
+            false => (0..p).map(|i| i..i + 1).collect(),
+        };
+        let done = AtomicUsize::new(0);
+        let exec = executor::limited(exec, config.phase_threads.merge, hooks.budget);
+        let slots: Vec<OnceLock<AVec<T, A>>> = groups.iter().map(|_| OnceLock::new()).collect();
+        let delivered = Mutex::new(0);
+        executor::par_map(&exec, groups.len(), |group_idx| {
+            let group = &groups[group_idx];
+            let slices: Vec<&[T]> = chunks
+                .iter()
+                .zip(boundaries.rows())
+                .map(|(chunk, b)| {
+                    let start = b[group.start];
+                    let end = b[group.end];
+                    &chunk[start..end]
+                })
+                .collect();
+            let size = slices.iter().map(|s| s.len()).sum();
+            span!("merge_partition", partition = group.start, partitions = group.len(), size);
+            let mut merged = AVec::with_capacity_in(size, alloc.clone());
+            hooks.timed(Phase::Merge, Some(group_idx), size, || {
+                merge::merge_into(&slices, compare, config.merge, hooks.cancel, &mut |x| merged.push(x))
+            })?;
+            let done = done.fetch_add(1, atomic::Ordering::Relaxed) + 1;
+            progress(Progress::Completed { phase: Phase::Merge, done, total: groups.len() });
+            let _ = slots[group_idx].set(merged);
+            hooks.deliver_partitions(&slots, &delivered);
+            Ok(())
+        })
+        .into_iter()
+        .collect::<Result<(), _>>()?;
+        Ok(slots.into_iter().map(|slot| slot.into_inner().expect("every group was merged")).collect())
+    })?;
+
+    // Concatenate the merged partitions into one sorted output.
+    hooks.phase(Phase::Copy, || {
+        span!("copy_output");
+        let output = merged_partitions.into_iter().flatten();
+        for (slot, value) in data.iter_mut().zip(output) {
+            *slot = value;
+        }
+        Ok(())
+    })?;
+    progress(Progress::Finished);
+    Ok(())
+}
+
+/// Lengths of the chunks Phase 1 sorts: `n / p` each and a shorter
+/// remainder, or with `weights`, `p` chunks in proportion to them.
+fn chunk_lens(n: usize, p: usize, weights: &[u32]) -> Vec<usize> {
+    if weights.is_empty() {
+        let block_size = n / p;
+        let mut lens = vec![block_size; n / block_size];
+        if !n.is_multiple_of(block_size) {
+            lens.push(n % block_size);
+        }
+        return lens;
+    }
+    let mut start = 0;
+    phases::share_ends(n, p, weights)
+        .into_iter()
+        .map(|end| end - std::mem::replace(&mut start, end))
+        .collect()
+}
+
+/// Groups adjacent partitions, given their sizes, into ranges holding at
+/// least `min_size` elements each (except when all of them together hold
+/// fewer), so tiny partitions don't each pay for a merge task.
+fn coalesce_partitions(sizes: &[usize], min_size: usize) -> Vec<Range<usize>> {
+    let mut groups: Vec<Range<usize>> = Vec::with_capacity(sizes.len());
+    let mut start = 0;
+    let mut size = 0;
+    for (i, &s) in sizes.iter().enumerate() {
+        size += s;
+        if size >= min_size.max(1) {
+            groups.push(start..i + 1);
+            start = i + 1;
+            size = 0;
+        }
+    }
+    if start < sizes.len() {
+        // The small tail joins the previous group rather than standing alone.
+        match groups.last_mut() {
+            Some(last) => last.end = sizes.len(),
+            None => groups.push(start..sizes.len()),
+        }
+    }
+    groups
+}
+
+/// Like [`psrs`], but runs every phase on `executor` instead of the
+/// [`default_executor`], e.g. a [`ScopedThreadExecutor`] or an [`Executor`]
+/// wrapping your own thread pool.
+pub fn psrs_with_executor<T>(data: &mut [T], p: usize, executor: &dyn Executor)
+where
+    T: Clone + Ord + Send + Sync,
+{
+    let hooks = Hooks { executor, ..Hooks::new() };
+    psrs_ord(data, &PsrsConfig::with_threads(p), hooks, Global).expect("sort without a token cannot fail");
+}
+
+/// Like [`psrs`], but allocates the internal buffers (samples, partition
+/// boundaries and the merged partitions, which together hold a full copy of
+/// the input) in `alloc` instead of the global allocator.
+///
+/// `alloc` is cloned into every Rayon task, so it must be usable from several
+/// threads at once, e.g. a thread-safe arena handle, or `HugePageAlloc`
+/// (with the `hugepages` feature) for very large sorts.
+pub fn psrs_in<T, A>(data: &mut [T], p: usize, alloc: A)
+where
+    T: Clone + Ord + Send + Sync,
+    A: Allocator + Clone + Send + Sync,
+{
+    psrs_ord(data, &PsrsConfig::with_threads(p), Hooks::new(), alloc).expect("sort without a token cannot fail");
+}
+
+/// Like [`psrs`], but sorts chunks with [`radix_sort`] instead of `sort_unstable`.
+///
+/// This is the fast path for the integer types implementing [`RadixKey`],
+/// including signed `i32`/`i64` via sign-bit flipping.
+pub fn psrs_radix<T: RadixKey>(data: &mut [T], p: usize) {
+    psrs_default(data, p, &T::cmp, &radix_sort::<T>, &Hooks::new())
+        .expect("sort without a token cannot fail");
+}
+
+/// Like [`psrs_radix`], but sorts chunks with [`msb_radix_sort`], which
+/// suits wide keys such as `[u8; 16]` UUIDs or `[u8; 32]` digests: their
+/// leading bytes usually tell them apart, so the trailing ones are never
+/// looked at.
+pub fn psrs_msb_radix<T: RadixKey>(data: &mut [T], p: usize) {
+    psrs_default(data, p, &T::cmp, &msb_radix_sort::<T>, &Hooks::new())
+        .expect("sort without a token cannot fail");
+}
+
+/// Sorts strings (`String`, `&str`, `Vec<u8>`, ...) by PSRS in byte-wise
+/// lexicographic order, using [`multikey_quicksort`] for the local sorts.
+pub fn psrs_strings<T>(data: &mut [T], p: usize)
+where
+    T: AsRef<[u8]> + Clone + Ord + Send + Sync,
+{
+    psrs_default(data, p, &T::cmp, &multikey_quicksort::<T>, &Hooks::new())
+        .expect("sort without a token cannot fail");
+}
+
+/// Sorts strings by PSRS in [`natural_cmp`] order, so numbers embedded in
+/// them sort by value: `file2` before `file10`.
+pub fn psrs_natural<T>(data: &mut [T], p: usize)
+where
+    T: AsRef<[u8]> + Clone + Send + Sync,
+{
+    psrs_by(data, p, |a, b| natural_cmp(a.as_ref(), b.as_ref()));
+}
+
+/// Runs [`psrs`] with the settings from `config`.
+pub fn psrs_with_config<T: Clone + Ord + Send + Sync>(data: &mut [T], config: &PsrsConfig) {
+    psrs_ord(data, config, Hooks::new(), Global).expect("sort without a token cannot fail");
+}
+
+/// Like [`psrs_with_config`], but runs every phase on `executor`, e.g. a
+/// `PinnedExecutor` for a config from [`PsrsConfig::for_cores`].
+pub fn psrs_with_config_on<T>(data: &mut [T], config: &PsrsConfig, executor: &dyn Executor)
+where
+    T: Clone + Ord + Send + Sync,
+{
+    let hooks = Hooks { executor, ..Hooks::new() };
+    psrs_ord(data, config, hooks, Global).expect("sort without a token cannot fail");
+}
+
+/// Like [`psrs_with_config`], but records on `timeline` when every phase,
+/// chunk sort and merge task ran and on which worker, e.g. to find the
+/// stragglers with [`Timeline::write_chrome_trace`].
+///
+/// Serial and presorted runs record their phases but no tasks.
+pub fn psrs_with_timeline<T: Clone + Ord + Send + Sync>(data: &mut [T], config: &PsrsConfig, timeline: &Timeline) {
+    let hooks = Hooks { timeline: Some(timeline), ..Hooks::new() };
+    psrs_ord(data, config, hooks, Global).expect("sort without a token cannot fail");
+}
+
+/// Like [`psrs_with_config`], but runs each phase on as many workers as
+/// `budget` allows when it starts, so another thread can shrink or grow
+/// the sort's share of the machine while it runs.
+pub fn psrs_with_budget<T: Clone + Ord + Send + Sync>(data: &mut [T], config: &PsrsConfig, budget: &ThreadBudget) {
+    let hooks = Hooks { budget: Some(budget), ..Hooks::new() };
+    psrs_ord(data, config, hooks, Global).expect("sort without a token cannot fail");
+}
+
+/// Like [`psrs_with_config`], but also returns how evenly the input was
+/// partitioned, for judging pivot quality on a given distribution.
+///
+/// Serial and presorted runs report a single partition.
+pub fn psrs_with_stats<T: Clone + Ord + Send + Sync>(data: &mut [T], config: &PsrsConfig) -> PartitionStats {
+    let stats = OnceLock::new();
+    let hooks = Hooks { stats: Some(&stats), ..Hooks::new() };
+    psrs_ord(data, config, hooks, Global).expect("sort without a token cannot fail");
+    stats.into_inner().unwrap_or_default()
+}
+
+/// [`psrs_with_stats`] ordering elements with `compare` instead of `Ord`.
+pub fn psrs_by_with_stats<T, F>(data: &mut [T], config: &PsrsConfig, compare: F) -> PartitionStats
+where
+    T: Clone + Send + Sync,
+    F: Fn(&T, &T) -> Ordering + Sync,
+{
+    let local_sort = |chunk: &mut [T]| unstable_sort_by(chunk, &compare);
+    psrs_by_with_local_sort(data, config, &compare, local_sort)
+}
+
+/// Like [`psrs_with_stats`], but sorts the chunks and the sample with
+/// `local_sort` instead of `sort_unstable`, e.g. to measure another serial
+/// sort inside PSRS. `local_sort` must sort ascending by `Ord`.
+pub fn psrs_with_local_sort<T, S>(data: &mut [T], config: &PsrsConfig, local_sort: S) -> PartitionStats
+where
+    T: Clone + Ord + Send + Sync,
+    S: Fn(&mut [T]) + Sync,
+{
+    psrs_by_with_local_sort(data, config, T::cmp, local_sort)
+}
+
+/// [`psrs_with_local_sort`] ordering elements with `compare`, which
+/// `local_sort` must agree with.
+pub fn psrs_by_with_local_sort<T, F, S>(data: &mut [T], config: &PsrsConfig, compare: F, local_sort: S) -> PartitionStats
+where
+    T: Clone + Send + Sync,
+    F: Fn(&T, &T) -> Ordering + Sync,
+    S: Fn(&mut [T]) + Sync,
+{
+    let stats = OnceLock::new();
+    let hooks = Hooks { stats: Some(&stats), ..Hooks::new() };
+    psrs_impl(data, config, &compare, &local_sort, &hooks, Global).expect("sort without a token cannot fail");
+    stats.into_inner().unwrap_or_default()
+}
+
+/// Like [`psrs`], but hands every merged partition to `on_partition`, in
+/// order, as soon as it and all partitions before it are final, while later
+/// partitions are still merging. Concatenated, the partitions are the
+/// sorted input; `data` is sorted as well once the call returns.
+///
+/// This lets a consumer start on the smallest elements before the sort has
+/// finished, e.g. by sending `part.to_vec()` down a channel to another
+/// thread. `on_partition` runs on the worker that completed the partition,
+/// never concurrently with itself. Adjacent small partitions arrive as
+/// one, and serial and presorted runs deliver the whole input at the end.
+pub fn psrs_streaming<T, F>(data: &mut [T], p: usize, on_partition: F)
+where
+    T: Clone + Ord + Send + Sync,
+    F: FnMut(&[T]) + Send,
+{
+    let on_partition = Mutex::new(on_partition);
+    let sink = |part: &[T]| (on_partition.lock().unwrap())(part);
+    let hooks = Hooks { partitions: Some(&sink), ..Hooks::new() };
+    psrs_ord(data, &PsrsConfig::with_threads(p), hooks, Global).expect("sort without a token cannot fail");
+}
+
+/// Like [`psrs`], but also returns a [`RankIndex`] of the sorted output,
+/// built from the run's pivots and partition sizes, for answering rank and
+/// range queries over `data` without scanning it.
+///
+/// Serial and presorted runs have no pivots; their index covers the whole
+/// output as one partition.
+pub fn psrs_indexed<T: Clone + Ord + Send + Sync>(data: &mut [T], p: usize) -> RankIndex<T> {
+    let index = OnceLock::new();
+    let hooks = Hooks { index: Some(&index), ..Hooks::new() };
+    psrs_ord(data, &PsrsConfig::with_threads(p), hooks, Global).expect("sort without a token cannot fail");
+    index.into_inner().expect("every run records its index")
+}
+
+/// Sorts `f64` values by PSRS in IEEE 754 total order (`f64::total_cmp`).
+///
+/// The floats are rewritten in place into order-preserving `u64` keys, radix
+/// sorted as integers, and converted back, so no extra copy of the input is made.
+/// Positive NaNs sort after `+inf` and negative NaNs before `-inf`.
+pub fn psrs_f64(data: &mut [f64], p: usize) {
+    // SAFETY: f64 and u64 have the same size and alignment, and every bit
+    // pattern is a valid u64.
+    let keys: &mut [u64] =
+        unsafe { std::slice::from_raw_parts_mut(data.as_mut_ptr().cast(), data.len()) };
+    let exec = default_executor();
+    let chunk_size = executor::even_chunk_size(exec, keys.len());
+    executor::par_chunks_mut_map(exec, keys, chunk_size, |_, chunk| {
+        chunk.iter_mut().for_each(|k| *k = f64_bits_to_key(*k));
+    });
+    psrs_radix(keys, p);
+    executor::par_chunks_mut_map(exec, keys, chunk_size, |_, chunk| {
+        chunk.iter_mut().for_each(|k| *k = key_to_f64_bits(*k));
+    });
+}
+
+/// Maps the bits of an `f64` onto a `u64` whose unsigned order is the float
+/// total order: negative values have all bits flipped, positive values only
+/// the sign bit.
+pub(crate) fn f64_bits_to_key(bits: u64) -> u64 {
+    if bits >> 63 == 1 { !bits } else { bits | 1 << 63 }
+}
+
+fn key_to_f64_bits(key: u64) -> u64 {
+    if key >> 63 == 1 { key & !(1 << 63) } else { !key }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::testing::{edge_cases, std_sorted};
+    use allocator_api2::alloc::{AllocError, Layout};
+    use std::ptr::NonNull;
+
+    /// A way of sorting `u64`s ascending on `p` chunks, under test.
+    type EntryPoint = Box<dyn Fn(&mut [u64], usize)>;
+
+    /// Every entry point that sorts a slice of `u64` ascending, plus the
+    /// config knobs that pick another path through the pipeline, by name.
+    fn entry_points() -> Vec<(&'static str, EntryPoint)> {
+        let with_config = |config: fn(usize) -> PsrsConfig| -> EntryPoint {
+            Box::new(move |data, p| psrs_with_config(data, &config(p)))
+        };
+        #[allow(unused_mut)]
+        let mut entries: Vec<(&'static str, EntryPoint)> = vec![
+            ("psrs", Box::new(psrs)),
+            ("psrs_by", Box::new(|data, p| psrs_by(data, p, u64::cmp))),
+            ("try_psrs_by", Box::new(|data, p| try_psrs_by(data, p, u64::cmp).unwrap())),
+            ("psrs_with_progress", Box::new(|data, p| psrs_with_progress(data, p, |_| {}))),
+            ("try_psrs", Box::new(|data, p| try_psrs(data, p, &CancellationToken::new()).unwrap())),
+            (
+                "try_psrs_with_progress",
+                Box::new(|data, p| try_psrs_with_progress(data, p, &CancellationToken::new(), |_| {}).unwrap()),
+            ),
+            (
+                "psrs_with_executor",
+                Box::new(|data, p| psrs_with_executor(data, p, &ScopedThreadExecutor { threads: 3 })),
+            ),
+            ("psrs_in", Box::new(|data, p| psrs_in(data, p, Global))),
+            ("psrs_radix", Box::new(psrs_radix)),
+            ("psrs_msb_radix", Box::new(psrs_msb_radix)),
+            ("psrs_integers", Box::new(psrs_integers)),
+            ("psrs_indirect", Box::new(psrs_indirect)),
+            ("psrs_with_config", with_config(PsrsConfig::with_threads)),
+            ("oversubscribed", with_config(|p| PsrsConfig { oversubscription: 3, ..PsrsConfig::with_threads(p) })),
+            ("uncoalesced", with_config(|p| PsrsConfig { min_partition_size: 0, ..PsrsConfig::with_threads(p) })),
+            (
+                "coalesced",
+                with_config(|p| PsrsConfig { min_partition_size: usize::MAX, ..PsrsConfig::with_threads(p) }),
+            ),
+            (
+                "quaternary heap",
+                with_config(|p| PsrsConfig { merge: MergeStructure::QuaternaryHeap, ..PsrsConfig::with_threads(p) }),
+            ),
+            (
+                "loser tree",
+                with_config(|p| PsrsConfig { merge: MergeStructure::LoserTree, ..PsrsConfig::with_threads(p) }),
+            ),
+            ("presorted check", with_config(|p| PsrsConfig { check_presorted: true, ..PsrsConfig::with_threads(p) })),
+            (
+                "phase caps",
+                with_config(|p| {
+                    let phase_threads = PhaseThreads { local_sort: Some(1), boundaries: Some(2), merge: Some(1) };
+                    PsrsConfig { phase_threads, ..PsrsConfig::with_threads(p) }
+                }),
+            ),
+            (
+                "weighted",
+                with_config(|p| PsrsConfig {
+                    core_weights: vec![4, 4, 1, 1],
+                    oversampling: 32,
+                    ..PsrsConfig::with_threads(p)
+                }),
+            ),
+            (
+                "psrs_with_config_on",
+                Box::new(|data, p| {
+                    psrs_with_config_on(data, &PsrsConfig::with_threads(p), &ScopedThreadExecutor { threads: 2 })
+                }),
+            ),
+            (
+                "psrs_with_timeline",
+                Box::new(|data, p| psrs_with_timeline(data, &PsrsConfig::with_threads(p), &Timeline::new())),
+            ),
+            (
+                "psrs_with_budget",
+                Box::new(|data, p| psrs_with_budget(data, &PsrsConfig::with_threads(p), &ThreadBudget::new(2))),
+            ),
+            ("psrs_with_stats", Box::new(|data, p| drop(psrs_with_stats(data, &PsrsConfig::with_threads(p))))),
+            (
+                "psrs_by_with_stats",
+                Box::new(|data, p| drop(psrs_by_with_stats(data, &PsrsConfig::with_threads(p), u64::cmp))),
+            ),
+            (
+                "psrs_with_local_sort",
+                Box::new(|data, p| drop(psrs_with_local_sort(data, &PsrsConfig::with_threads(p), <[u64]>::sort))),
+            ),
+            ("psrs_streaming", Box::new(|data, p| psrs_streaming(data, p, |_| {}))),
+            ("psrs_indexed", Box::new(|data, p| drop(psrs_indexed(data, p)))),
+            (
+                "psrs_to",
+                Box::new(|data, p| {
+                    let src = data.to_vec();
+                    psrs_to(&src, data, p);
+                }),
+            ),
+        ];
+        #[cfg(feature = "tokio")]
+        entries.push((
+            "psrs_async",
+            Box::new(|data, p| {
+                let runtime = tokio::runtime::Builder::new_current_thread().build().unwrap();
+                let sorted = runtime.block_on(psrs_async(data.to_vec(), p));
+                data.copy_from_slice(&sorted);
+            }),
+        ));
+        #[cfg(all(feature = "hugepages", target_os = "linux"))]
+        entries.push(("huge pages", Box::new(|data, p| psrs_in(data, p, HugePageAlloc::default()))));
+        #[cfg(feature = "affinity")]
+        entries.push((
+            "pinned",
+            Box::new(|data, p| {
+                psrs_with_config_on(data, &PsrsConfig::with_threads(p), &PinnedExecutor { cores: vec![0; p] })
+            }),
+        ));
+        entries
+    }
+
+    #[test]
+    fn every_entry_point_matches_std_sort() {
+        for (entry, sort) in entry_points() {
+            for p in [1, 4, 7] {
+                for (name, values) in edge_cases(p) {
+                    let mut data = values.clone();
+                    sort(&mut data, p);
+                    assert_eq!(data, std_sorted(&values), "{entry}: {name}, p = {p}");
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn psrs_by_sorts_in_the_comparator_order() {
+        let values = crate::testing::random_values(20_000, 1_000, 5);
+        let mut data = values.clone();
+        psrs_by(&mut data, 4, |a, b| b.cmp(a));
+        let mut expected = std_sorted(&values);
+        expected.reverse();
+        assert_eq!(data, expected);
+    }
+
+    #[test]
+    fn panicking_comparator_is_reported() {
+        let values = crate::testing::random_values(10_000, 1_000, 8);
+        let mut data = values.clone();
+        let result = try_psrs_by(&mut data, 4, |a: &u64, b: &u64| {
+            assert!(*a != 500 && *b != 500, "met 500");
+            a.cmp(b)
+        });
+        match result {
+            Err(PsrsError::Panicked { message, .. }) => assert!(message.contains("met 500"), "{message}"),
+            other => panic!("expected a panic to be reported, got {other:?}"),
+        }
+        assert_eq!(std_sorted(&data), std_sorted(&values), "the data must keep its elements");
+    }
+
+    /// Hands every request to `Global`, counting the bytes asked for.
+    #[derive(Clone)]
+    struct CountingAlloc<'a>(&'a std::sync::atomic::AtomicUsize);
+
+    // SAFETY: every block comes from and goes back to `Global` unchanged.
+    unsafe impl Allocator for CountingAlloc<'_> {
+        fn allocate(&self, layout: Layout) -> Result<NonNull<[u8]>, AllocError> {
+            self.0.fetch_add(layout.size(), std::sync::atomic::Ordering::Relaxed);
+            Global.allocate(layout)
+        }
+
+        unsafe fn deallocate(&self, ptr: NonNull<u8>, layout: Layout) {
+            // SAFETY: `ptr` was allocated by `Global` with `layout`.
+            unsafe { Global.deallocate(ptr, layout) }
+        }
+    }
+
+    #[test]
+    fn psrs_in_allocates_through_the_allocator() {
+        let mut data = crate::testing::random_values(20_000, u64::MAX, 5);
+        let allocated = std::sync::atomic::AtomicUsize::new(0);
+        psrs_in(&mut data, 4, CountingAlloc(&allocated));
+        // The merged partitions hold a copy of the input.
+        assert!(allocated.into_inner() >= std::mem::size_of_val(data.as_slice()));
+    }
+
+    #[test]
+    fn psrs_f64_matches_total_cmp() {
+        let specials = [f64::NAN, -f64::NAN, f64::INFINITY, f64::NEG_INFINITY, 0.0, -0.0, f64::MIN_POSITIVE, -1e-310];
+        for p in [1, 4, 7] {
+            for (name, values) in edge_cases(p) {
+                let mut data: Vec<f64> = values
+                    .iter()
+                    .map(|&v| if v % 50 < 8 { specials[(v % 50) as usize] } else { (v % 20_000) as f64 / 7.0 - 1_000.0 })
+                    .collect();
+                let mut expected = data.clone();
+                expected.sort_by(f64::total_cmp);
+                psrs_f64(&mut data, p);
+                let bits = |xs: &[f64]| xs.iter().map(|x| x.to_bits()).collect::<Vec<_>>();
+                assert_eq!(bits(&data), bits(&expected), "{name}, p = {p}");
+            }
+        }
+    }
+
+    #[test]
+    fn streamed_partitions_concatenate_to_the_sort() {
+        for p in [1, 4, 7] {
+            for (name, mut data) in edge_cases(p) {
+                let expected = std_sorted(&data);
+                let mut streamed = Vec::new();
+                let mut parts = 0;
+                psrs_streaming(&mut data, p, |part| {
+                    streamed.extend_from_slice(part);
+                    parts += 1;
+                });
+                assert_eq!(streamed, expected, "{name}, p = {p}");
+                assert_eq!(data, expected, "{name}, p = {p}");
+                assert!(parts <= p.max(1), "{name}, p = {p}: {parts} partitions");
+            }
+        }
+    }
+
+    #[test]
+    fn weighted_chunks_follow_the_weights() {
+        assert_eq!(chunk_lens(100, 4, &[]), [25, 25, 25, 25]);
+        assert_eq!(chunk_lens(103, 4, &[]), [25, 25, 25, 25, 3]);
+        assert_eq!(chunk_lens(100, 4, &[3, 1]), [37, 13, 37, 13]);
+        assert_eq!(chunk_lens(10, 4, &[2, 1, 1, 1]), [4, 2, 2, 2]);
+    }
+
+    #[test]
+    fn small_partitions_are_coalesced() {
+        let sizes = [10, 0, 0, 30, 5, 70];
+        assert_eq!(coalesce_partitions(&sizes, 40), [0..4, 4..6]);
+        assert_eq!(coalesce_partitions(&sizes, 0), [0..1, 1..4, 4..5, 5..6]);
+        assert_eq!(coalesce_partitions(&sizes, usize::MAX), vec![0..6]);
+        // A small tail joins the group before it.
+        assert_eq!(coalesce_partitions(&[50, 5], 40), vec![0..2]);
+        assert_eq!(coalesce_partitions(&[], 40), []);
+    }
+
+    #[test]
+    fn weighted_partitions_follow_the_weights() {
+        let core_weights = vec![4, 4, 1, 1];
+        let config = PsrsConfig { core_weights: core_weights.clone(), oversampling: 32, ..PsrsConfig::with_threads(4) };
+        // Partitions of random input land close to their weighted shares.
+        let mut data = crate::testing::random_values(200_000, u64::MAX, 25);
+        let stats = psrs_with_stats(&mut data, &config);
+        for (size, weight) in stats.sizes.iter().zip(&core_weights) {
+            let share = data.len() * *weight as usize / 10;
+            assert!(size.abs_diff(share) < share / 10, "{:?}", stats.sizes);
+        }
+    }
+}
+
@@ -0,0 +1,15 @@
+//! The serial sort of every chunk and of the sample: `std`'s unstable sort,
+//! whatever features are enabled. Callers wanting another one, like the
+//! harness measuring the `quicksort` crate, pass it explicitly to
+//! [`psrs_with_local_sort`](crate::psrs_with_local_sort).
+
+pub(crate) fn unstable_sort<T: Ord>(data: &mut [T]) {
+    data.sort_unstable();
+}
+
+pub(crate) fn unstable_sort_by<T, F>(data: &mut [T], compare: F)
+where
+    F: Fn(&T, &T) -> std::cmp::Ordering,
+{
+    data.sort_unstable_by(compare);
+}
@@ -1,209 +1,742 @@
-use rayon::prelude::*;
-use rand::Rng;
-use std::cmp::Reverse;
-use std::collections::BinaryHeap;
-use std::time::{Duration, Instant};
-use quicksort::quicksort;
+use serde::{Deserialize, Serialize};
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::time::Instant;
+use datagen::{generate_data, Distribution};
+use energy::EnergyMeter;
+use perf::Counters;
+use psrs::{PartitionStats, PhaseThreads, PsrsConfig};
+use scenarios::{Dataset, ElementType};
+use sorters::{SortContext, Sorter, SORTERS};
+
+#[cfg(feature = "plots")]
+mod charts;
+mod compare;
+mod csv_sort;
+mod datagen;
+mod energy;
+mod gen;
+mod merge_files;
+mod perf;
+mod pipe;
+mod record_sort;
+mod repro;
+mod scenarios;
+mod selftest;
+mod serve;
+mod sorters;
+mod trace;
+mod tune;
 
 const LOG_RUN_INFO: bool = false;
 
-fn generate_data(n: usize, start: u32, end: u32) -> Vec<u32> {
-    let time_start = Instant::now();
-    let mut data = Vec::with_capacity(n);
-    let mut rng = rand::rng();
+/// Parameters of one benchmark sweep: every algorithm is run on every
+/// combination of `data_lens`, `distributions` and `element_types`. Can be loaded from a
+/// JSON, TOML or YAML file; missing fields take the defaults below.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default)]
+struct BenchConfig {
+    /// Minimum number of warm-up runs.
+    warm_ups: usize,
+    /// Maximum number of warm-up runs, even if runtimes never settle.
+    max_warm_ups: usize,
+    /// Warm-up ends once the coefficient of variation of the last
+    /// `warm_up_window` runtimes drops below `warm_up_cv`.
+    warm_up_window: usize,
+    warm_up_cv: f64,
+    num_runs: usize,
+    data_lens: Vec<usize>,
+    distributions: Vec<Distribution>,
+    element_types: Vec<ElementType>,
+    /// Values are drawn from `min_val..max_val`.
+    min_val: u32,
+    max_val: u32,
+    thread_counts: Vec<usize>,
+    /// PSRS chunks per thread, see [`PsrsConfig::oversubscription`].
+    oversubscription: usize,
+    /// Caps on the workers of individual PSRS phases, see [`PhaseThreads`].
+    phase_threads: PhaseThreads,
+    /// Names of the [`SORTERS`] to run.
+    algorithms: Vec<String>,
+    /// Directory to write charts into (needs the `plots` feature).
+    charts_dir: Option<String>,
+    /// `svg` or `png`.
+    chart_format: String,
+    /// Pin every worker to a core for reproducible scaling numbers (needs
+    /// the `affinity` feature).
+    pin_threads: bool,
+    /// Cores to pin workers to, worker `i` to `cores[i % cores.len()]`;
+    /// empty means every core in the order the OS lists them.
+    cores: Vec<usize>,
+    /// Read package energy (RAPL) around every run. Linux only, and
+    /// usually needs root.
+    energy: bool,
+    /// Seed of the generated datasets; `--seed` overrides it. Without one a
+    /// random seed is picked and written to the report, so any sweep can be
+    /// rerun on the same inputs.
+    seed: Option<u64>,
+    /// Directory to write a reproduction bundle into for every sorter,
+    /// config and workload with an incorrect run, to replay with `repro`;
+    /// `null` to write none.
+    repro_dir: Option<String>,
+    /// Store the input in the bundles too, instead of regenerating it from
+    /// the seed on replay. Inputs loaded with `--load-data` always are.
+    repro_data: bool,
+}
 
-    for _ in 0..n {
-        data.push(rng.random_range(start..end));
+impl Default for BenchConfig {
+    fn default() -> Self {
+        BenchConfig {
+            warm_ups: 2,
+            max_warm_ups: 20,
+            warm_up_window: 3,
+            warm_up_cv: 0.05,
+            num_runs: 5,
+            data_lens: vec![100_000_000],
+            distributions: vec![Distribution::Uniform],
+            element_types: vec![ElementType::U32],
+            min_val: 0,
+            max_val: 50,
+            thread_counts: vec![4, 8, 16, 32, 64, 128],
+            oversubscription: 1,
+            phase_threads: PhaseThreads::default(),
+            algorithms: SORTERS.iter().map(|s| s.name().to_string()).collect(),
+            charts_dir: None,
+            chart_format: "svg".to_string(),
+            pin_threads: false,
+            cores: Vec::new(),
+            energy: false,
+            seed: None,
+            repro_dir: Some("repro".to_string()),
+            repro_data: false,
+        }
     }
+}
 
-    let duration = time_start.elapsed();
-    if LOG_RUN_INFO {
-        println!("Time elapsed for generation: {:?}", duration);
-    }
-    data
+/// One cell of the experiment matrix.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+struct Workload {
+    data_len: usize,
+    distribution: Distribution,
+    element: ElementType,
 }
 
-/// Performs a k‑way merge of several sorted slices using a binary heap.
-fn k_way_merge(slices: &[&[u32]]) -> Vec<u32> {
-    let mut heap = BinaryHeap::new();
-    // Each heap entry is (value, slice_index, index_in_slice).
-    // We load up the heap with the first elements of each slice.
-    for (i, slice) in slices.iter().enumerate() {
-        if !slice.is_empty() {
-            heap.push(Reverse((slice[0], i, 0)));
-        }
-    }
+/// Outcome of a single timed sort.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+struct RunResult {
+    algorithm: String,
+    config: PsrsConfig,
+    #[serde(flatten)]
+    workload: Workload,
+    warm_up: bool,
+    runtime_ms: f64,
+    sorted: bool,
+    /// First index where the output differs from `slice::sort`, when run
+    /// with `--check-against-std`.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    mismatch: Option<usize>,
+    /// Hardware counters of the run, with the `perf` feature.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    counters: Option<Counters>,
+    /// Package energy used by the run, with `energy` set.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    energy_j: Option<f64>,
+    /// Partition sizes and duplicate pivots, for the PSRS sorters.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    partitions: Option<PartitionStats>,
+}
 
-    // Create the final sorted array by selecting the smallest element
-    // of our slices given by the min heap.
-    let mut merged = Vec::new();
-    while let Some(Reverse((val, slice_idx, idx_in_slice))) = heap.pop() {
-        merged.push(val);
-        let slice = slices[slice_idx];
-        let next_idx = idx_in_slice + 1;
-        if next_idx < slice.len() {
-            heap.push(Reverse((slice[next_idx], slice_idx, next_idx)));
-        }
-    }
-    merged
+/// Summary of the measured (non warm-up) runtimes of one configuration, in
+/// milliseconds.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+struct RunStats {
+    algorithm: String,
+    #[serde(flatten)]
+    workload: Workload,
+    threads: usize,
+    runs: usize,
+    min: f64,
+    median: f64,
+    p95: f64,
+    mean: f64,
+    stddev: f64,
+    /// Half-width of the 95% confidence interval of the mean.
+    ci95: f64,
+    /// Millions of elements sorted per second, at the median runtime.
+    #[serde(default)]
+    melem_per_sec: f64,
+    /// Input bytes sorted per second at the median runtime, in GB/s. Each
+    /// element is counted once, so a sort moving the data several times
+    /// uses a multiple of this in actual memory traffic.
+    #[serde(default)]
+    gb_per_sec: f64,
+    /// Mean hardware counters of the measured runs, with the `perf` feature.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    counters: Option<Counters>,
+    /// Mean package energy of the measured runs, with `energy` set.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    energy_j: Option<f64>,
+    /// `energy_j` per sorted element, in nanojoules.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    energy_nj_per_element: Option<f64>,
+    /// Worst largest-over-ideal partition ratio of the measured runs.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    imbalance: Option<f64>,
+    /// Most duplicate pivots picked by a measured run.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    duplicate_pivots: Option<usize>,
 }
 
-/// The PSRS implementation using Rayon for parallelism.
-fn psrs(data: &mut [u32], p: usize) {
-    let n = data.len();
-    let block_size = n / p;
+/// How one parallel algorithm scales at one thread count, relative to the
+/// fastest serial baseline.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+struct ScalingRow {
+    algorithm: String,
+    #[serde(flatten)]
+    workload: Workload,
+    threads: usize,
+    median_ms: f64,
+    speedup: f64,
+    efficiency: f64,
+    /// Experimentally determined serial fraction (Karp-Flatt metric);
+    /// undefined, and left out, at one thread.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    karp_flatt: Option<f64>,
+}
 
-    // Phase 1: Sort each chunk in parallel.
-    data.par_chunks_mut(block_size)
-        .for_each(|chunk| {
-            quicksort(chunk);
-        });
+/// Serial fraction of one parallel algorithm, fitted over all thread counts;
+/// `None` without at least two distinct thread counts to fit.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+struct ScalingFit {
+    algorithm: String,
+    #[serde(flatten)]
+    workload: Workload,
+    /// `f` in Amdahl's law, `S(p) = 1 / (f + (1 - f) / p)`.
+    amdahl_serial_fraction: Option<f64>,
+    /// `f` in Gustafson's law, `S(p) = p - f (p - 1)`.
+    gustafson_serial_fraction: Option<f64>,
+}
 
-    // Phase 2: From each sorted chunk, take p regular samples.
-    let mut samples: Vec<u32> = data
-        .par_chunks(block_size) // Assign a chunk to each thread
-        .flat_map(|chunk| {
-            let m = chunk.len();
-            let omega = m / p;
+/// Everything measured by one invocation of the harness.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+struct BenchReport {
+    config: BenchConfig,
+    runs: Vec<RunResult>,
+    stats: Vec<RunStats>,
+    scaling: Vec<ScalingRow>,
+    fits: Vec<ScalingFit>,
+}
 
-            (0..p) // Each thread gathers its respective local samples from its chunk
-                .into_par_iter()
-                .map(move |i| {
-                    // Choose index; ensure we don’t go out-of-bounds.
-                    let idx = if i * omega + 1 < m { i * omega + 1 } else { m - 1 };
-                    chunk[idx]
-                })
-        })
-        .collect();
+/// Where a workload's dataset is stored under a `--save-data`/`--load-data`
+/// directory. The stored `u32` values are shared by all element types.
+fn dataset_path(dir: &Path, workload: Workload) -> PathBuf {
+    dir.join(format!("{}_{}.u32", workload.data_len, workload.distribution.name()))
+}
 
-    // The main thread sorts the local samples
-    quicksort(&mut samples);
-
-    // Choose p-1 pivots.
-    let pivots: Vec<u32> = (1..p).map(|i| samples[i * p]).collect();
-
-    // Phase 3: Compute partition boundaries for each chunk.
-    let boundaries: Vec<Vec<usize>> = data
-        .par_chunks(block_size)
-        .map(|chunk| {
-            let mut b = Vec::with_capacity(p + 1);
-            b.push(0);
-            for &pivot in &pivots {
-                // partition_point returns the first index where x > pivot.
-                let pos = chunk.partition_point(|&x| x <= pivot);
-                b.push(pos);
-            }
-            b.push(chunk.len());
-            b
-        })
-        .collect();
+/// Writes `data` as raw little-endian `u32`s.
+fn save_data(path: &Path, data: &[u32]) {
+    let bytes: Vec<u8> = data.iter().flat_map(|v| v.to_le_bytes()).collect();
+    fs::write(path, bytes).expect("failed to write dataset");
+}
 
-    // Phase 4: For each partition index, merge the corresponding partitions.
-    let merged_partitions: Vec<Vec<u32>> = (0..p)
-        .into_par_iter()
-        .map(|part_idx| {
-            let slices: Vec<&[u32]> = data
-                .chunks(block_size)
-                .zip(boundaries.iter())
-                .map(|(chunk, b)| {
-                    let start = b[part_idx];
-                    let end = b[part_idx + 1];
-                    &chunk[start..end]
-                })
-                .collect();
-            k_way_merge(&slices)
-        })
-        .collect();
+/// Reads a dataset written by [`save_data`].
+fn load_data(path: &Path) -> Vec<u32> {
+    let bytes = fs::read(path).expect("failed to read dataset");
+    assert!(bytes.len().is_multiple_of(4), "dataset {} is not a whole number of u32s", path.display());
+    bytes.chunks_exact(4).map(|b| u32::from_le_bytes(b.try_into().unwrap())).collect()
+}
+
+/// Directories to persist generated datasets to, or reuse them from, so
+/// the same input can be sorted across algorithms and code revisions.
+#[derive(Debug, Default)]
+struct DataFiles {
+    save: Option<PathBuf>,
+    load: Option<PathBuf>,
+}
 
-    // Concatenate the merged partitions into one sorted output.
-    let mut output = Vec::with_capacity(n);
-    for part in merged_partitions {
-        output.extend(part);
+/// The input every run on `workload` sorts a copy of: loaded from
+/// `files.load` if given, otherwise generated (and saved to `files.save`),
+/// then converted to the workload's element type.
+fn dataset(bench: &BenchConfig, workload: Workload, files: &DataFiles) -> Dataset {
+    let data = input_values(bench, workload, files);
+    if let Some(dir) = &files.save {
+        fs::create_dir_all(dir).expect("failed to create dataset directory");
+        save_data(&dataset_path(dir, workload), &data);
     }
-    data.copy_from_slice(&output);
+    Dataset::from_u32(&data, workload.element)
 }
 
-fn verify_sorted(data: &[u32]) -> bool {
-    data.windows(2).all(|w| w[0] <= w[1])
+/// The `u32` values [`dataset`] is built from.
+fn input_values(bench: &BenchConfig, workload: Workload, files: &DataFiles) -> Vec<u32> {
+    match &files.load {
+        Some(dir) => {
+            let data = load_data(&dataset_path(dir, workload));
+            assert_eq!(data.len(), workload.data_len, "loaded dataset has the wrong length");
+            data
+        }
+        None => {
+            let seed = bench.seed.expect("seed is set before the sweep starts");
+            generate_data(workload.data_len, bench.min_val, bench.max_val, workload.distribution, seed)
+        }
+    }
 }
 
-fn run_tests(name: &str, mut warm_ups: i32, num_runs: i32, data_len: usize, min_val: u32, max_val: u32, p: usize) -> Vec<u128> {
-    let mut runtimes = Vec::new();
+/// Coefficient of variation (stddev / mean) of `times`.
+fn coefficient_of_variation(times: &[f64]) -> f64 {
+    let n = times.len() as f64;
+    let mean = times.iter().sum::<f64>() / n;
+    let variance = times.iter().map(|t| (t - mean).powi(2)).sum::<f64>() / n;
+    variance.sqrt() / mean
+}
+
+/// Pool of `threads` workers, pinned to `bench.cores` if `bench.pin_threads`.
+fn thread_pool(bench: &BenchConfig, threads: usize) -> rayon::ThreadPool {
+    if bench.pin_threads {
+        #[cfg(feature = "affinity")]
+        return psrs::pinned_thread_pool(threads, &bench.cores).expect("failed to build thread pool");
+        #[cfg(not(feature = "affinity"))]
+        {
+            static WARNED: std::sync::Once = std::sync::Once::new();
+            WARNED.call_once(|| eprintln!("pin_threads ignored: built without the `affinity` feature"));
+        }
+    }
+    rayon::ThreadPoolBuilder::new()
+        .num_threads(threads)
+        .build()
+        .expect("failed to build thread pool")
+}
+
+/// Runs `sorter` on copies of `dataset`. If `expected` is given every output
+/// is compared against it element by element, which unlike the sortedness
+/// check also catches dropped or duplicated elements.
+fn run_tests(
+    sorter: &dyn Sorter,
+    bench: &BenchConfig,
+    workload: Workload,
+    dataset: &Dataset,
+    expected: Option<&Dataset>,
+    config: &PsrsConfig,
+) -> Vec<RunResult> {
+    let name = sorter.name();
+    let mut results = Vec::new();
+    let pool = thread_pool(bench, config.threads);
+    let ctx = SortContext { config };
+    let energy = bench.energy.then(EnergyMeter::open).and_then(|meter| {
+        static WARNED: std::sync::Once = std::sync::Once::new();
+        meter.map_err(|e| WARNED.call_once(|| eprintln!("energy readings unavailable: {e}"))).ok()
+    });
     if LOG_RUN_INFO {
         println!("-------------------{name}--------------------------------------");
     }
-    for i in (-warm_ups + 1)..(num_runs + 1) {
-        if LOG_RUN_INFO {
-            if warm_ups > 0 {
-                println!("WARMUP!!");
-            } else {
-                println!("---------------------------");
-                println!("Run #{i} {name}");
-            }
-        }
 
-        let mut data = generate_data(data_len, min_val, max_val);
+    let run_once = |warm_up: bool| {
+        let mut data = dataset.clone();
 
+        let session = perf::start();
+        let energy_start = energy.as_ref().and_then(|meter| meter.read().ok());
         let start = Instant::now();
-        if name == "psrs" {
-            psrs(&mut data, p);
-        } else {
-            quicksort(&mut data);
-        }
+        let partitions = pool.install(|| sorter.sort(&mut data, &ctx));
         let duration = start.elapsed();
+        let energy_j = energy.as_ref().zip(energy_start).and_then(|(meter, start)| meter.joules_since(&start).ok());
+        let counters = session.and_then(perf::Session::finish);
         if LOG_RUN_INFO {
             println!("Time elapsed in {name}: {:?}", duration);
         }
-        runtimes.push(duration.as_millis());
+        let runtime_ms = duration.as_secs_f64() * 1000.0;
 
         let start = Instant::now();
-        let success = verify_sorted(&data);
+        let mut success = data.is_sorted();
+        let mismatch = expected.and_then(|expected| data.first_mismatch(expected));
         let duration = start.elapsed();
         if LOG_RUN_INFO {
             println!("Time elapsed in verification: {:?}", duration);
         }
+        if let Some(index) = mismatch {
+            println!("!!!!!!!!!!!!!!!WARNING!!!!!!!!!!!!!!!!!!!!!!!! {name} output differs from slice::sort at index {index}");
+            success = false;
+        }
+        if !success {println!("!!!!!!!!!!!!!!!WARNING!!!!!!!!!!!!!!!!!!!!!!!! Incorrect sort output!")}
+
+        RunResult {
+            algorithm: name.to_string(),
+            config: config.clone(),
+            workload,
+            warm_up,
+            runtime_ms,
+            sorted: success,
+            mismatch,
+            counters,
+            energy_j,
+            partitions,
+        }
+    };
 
-        if warm_ups > 0 {
-            warm_ups -= 1;
-        } else if LOG_RUN_INFO {
+    // Warm up until the last `warm_up_window` runtimes are stable, within
+    // the `warm_ups..=max_warm_ups` bounds.
+    let mut recent = Vec::new();
+    while recent.len() < bench.max_warm_ups {
+        if LOG_RUN_INFO {
+            println!("WARMUP!!");
+        }
+        let result = run_once(true);
+        recent.push(result.runtime_ms);
+        results.push(result);
+
+        let window = &recent[recent.len().saturating_sub(bench.warm_up_window)..];
+        if recent.len() >= bench.warm_ups
+            && window.len() >= bench.warm_up_window
+            && coefficient_of_variation(window) < bench.warm_up_cv
+        {
+            break;
+        }
+    }
+    if LOG_RUN_INFO {
+        println!("{} warm-up runs", recent.len());
+    }
+
+    for i in 1..=bench.num_runs {
+        if LOG_RUN_INFO {
+            println!("---------------------------");
+            println!("Run #{i} {name}");
+        }
+        let result = run_once(false);
+        if LOG_RUN_INFO {
             println!(
                 "\nRun #{} success status: {}",
                 i,
-                if success { "success." } else { "FAIL." }
+                if result.sorted { "success." } else { "FAIL." }
             );
         }
-        if !success {println!("!!!!!!!!!!!!!!!WARNING!!!!!!!!!!!!!!!!!!!!!!!! Incorrect sort output!")}
+        results.push(result);
     }
     if LOG_RUN_INFO {
         println!("------------------------------------------");
     }
 
-    runtimes
+    results
+}
+
+/// Two-sided 95% Student's t critical value for `df` degrees of freedom.
+fn t_critical_95(df: usize) -> f64 {
+    const TABLE: [f64; 30] = [
+        12.706, 4.303, 3.182, 2.776, 2.571, 2.447, 2.365, 2.306, 2.262, 2.228, 2.201, 2.179, 2.160, 2.145, 2.131,
+        2.120, 2.110, 2.101, 2.093, 2.086, 2.080, 2.074, 2.069, 2.064, 2.060, 2.056, 2.052, 2.048, 2.045, 2.042,
+    ];
+    TABLE.get(df.wrapping_sub(1)).copied().unwrap_or(1.960)
+}
+
+/// Order statistics, mean and spread of the measured (non warm-up) runs.
+fn run_stats(algorithm: &str, workload: Workload, threads: usize, runs: &[RunResult]) -> RunStats {
+    let mut times: Vec<f64> = runs.iter().filter(|r| !r.warm_up).map(|r| r.runtime_ms).collect();
+    times.sort_by(f64::total_cmp);
+    let n = times.len();
+    // Nearest-rank percentile.
+    let percentile = |q: f64| times.get(((q * n as f64).ceil() as usize).clamp(1, n.max(1)) - 1).copied().unwrap_or(f64::NAN);
+    let median = match n {
+        0 => f64::NAN,
+        _ if n % 2 == 1 => times[n / 2],
+        _ => (times[n / 2 - 1] + times[n / 2]) / 2.0,
+    };
+    let mean = times.iter().sum::<f64>() / n as f64;
+    let energies: Vec<f64> = runs.iter().filter(|r| !r.warm_up).filter_map(|r| r.energy_j).collect();
+    let energy_j = (!energies.is_empty()).then(|| energies.iter().sum::<f64>() / energies.len() as f64);
+    let partitions: Vec<&PartitionStats> = runs.iter().filter(|r| !r.warm_up).filter_map(|r| r.partitions.as_ref()).collect();
+    let stddev = if n > 1 {
+        (times.iter().map(|t| (t - mean).powi(2)).sum::<f64>() / (n - 1) as f64).sqrt()
+    } else {
+        0.0
+    };
+    RunStats {
+        algorithm: algorithm.to_string(),
+        workload,
+        threads,
+        runs: n,
+        min: times.first().copied().unwrap_or(f64::NAN),
+        median,
+        p95: percentile(0.95),
+        mean,
+        stddev,
+        ci95: t_critical_95(n.saturating_sub(1)) * stddev / (n as f64).sqrt(),
+        melem_per_sec: workload.data_len as f64 / median / 1e3,
+        gb_per_sec: (workload.data_len * workload.element.bytes()) as f64 / median / 1e6,
+        counters: Counters::mean(&runs.iter().filter(|r| !r.warm_up).filter_map(|r| r.counters).collect::<Vec<_>>()),
+        energy_j,
+        energy_nj_per_element: energy_j.map(|j| j * 1e9 / workload.data_len as f64),
+        imbalance: partitions.iter().map(|p| p.imbalance).max_by(f64::total_cmp),
+        duplicate_pivots: partitions.iter().map(|p| p.duplicate_pivots).max(),
+    }
+}
+
+fn print_stats(stats: &RunStats) {
+    println!(
+        "{}\t{}\t{}\t{:.3}\t{:.3}\t{:.3}\t{:.3}\t{:.3}\t±{:.3}\t{:.2}\t{:.3}",
+        stats.algorithm,
+        stats.threads,
+        stats.runs,
+        stats.min,
+        stats.median,
+        stats.p95,
+        stats.mean,
+        stats.stddev,
+        stats.ci95,
+        stats.melem_per_sec,
+        stats.gb_per_sec
+    );
+    if let Some(c) = &stats.counters {
+        println!(
+            "\tinstructions {}\tcache misses {}\tbranch misses {}",
+            c.instructions, c.cache_misses, c.branch_misses
+        );
+    }
+    if let (Some(joules), Some(per_element)) = (stats.energy_j, stats.energy_nj_per_element) {
+        println!("\tenergy {joules:.3} J\t{per_element:.3} nJ/element");
+    }
+    if let (Some(imbalance), Some(duplicates)) = (stats.imbalance, stats.duplicate_pivots) {
+        println!("\timbalance {imbalance:.3}\tduplicate pivots {duplicates}");
+    }
+}
+
+/// Speedup, efficiency and Karp-Flatt metric for each configuration's
+/// median runtime against a serial runtime of `baseline_ms`.
+fn scaling_rows(baseline_ms: f64, stats: &[RunStats]) -> Vec<ScalingRow> {
+    stats
+        .iter()
+        .map(|s| {
+            let p = s.threads as f64;
+            let speedup = baseline_ms / s.median;
+            ScalingRow {
+                algorithm: s.algorithm.clone(),
+                workload: s.workload,
+                threads: s.threads,
+                median_ms: s.median,
+                speedup,
+                efficiency: speedup / p,
+                karp_flatt: (s.threads > 1).then(|| (1.0 / speedup - 1.0 / p) / (1.0 - 1.0 / p)),
+            }
+        })
+        .collect()
+}
+
+/// Least-squares serial fractions for one algorithm's rows on one workload.
+/// Both laws are linear in `f`, so each fit is a regression through the
+/// origin.
+fn fit_serial_fraction(algorithm: &str, workload: Workload, rows: &[ScalingRow]) -> ScalingFit {
+    let (mut amdahl_xy, mut amdahl_xx) = (0.0, 0.0);
+    let (mut gustafson_xy, mut gustafson_xx) = (0.0, 0.0);
+    let mut thread_counts = Vec::new();
+    for row in rows.iter().filter(|r| r.algorithm == algorithm && r.workload == workload && r.speedup.is_finite()) {
+        if !thread_counts.contains(&row.threads) {
+            thread_counts.push(row.threads);
+        }
+        let p = row.threads as f64;
+        // 1/S - 1/p = f (1 - 1/p)
+        let x = 1.0 - 1.0 / p;
+        amdahl_xy += x * (1.0 / row.speedup - 1.0 / p);
+        amdahl_xx += x * x;
+        // p - S = f (p - 1)
+        let x = p - 1.0;
+        gustafson_xy += x * (p - row.speedup);
+        gustafson_xx += x * x;
+    }
+    // One thread count leaves a single point, or none at all if it is 1.
+    let fitted = thread_counts.len() >= 2;
+    ScalingFit {
+        algorithm: algorithm.to_string(),
+        workload,
+        amdahl_serial_fraction: fitted.then(|| amdahl_xy / amdahl_xx),
+        gustafson_serial_fraction: fitted.then(|| gustafson_xy / gustafson_xx),
+    }
+}
+
+/// Everything measured on one workload.
+struct Experiment {
+    runs: Vec<RunResult>,
+    stats: Vec<RunStats>,
+    scaling: Vec<ScalingRow>,
+    fits: Vec<ScalingFit>,
+}
+
+/// Runs the selected serial baselines once and the selected parallel
+/// algorithms at every thread count on `workload`, printing the tables.
+fn run_experiment(bench: &BenchConfig, workload: Workload, files: &DataFiles, check_against_std: bool) -> Experiment {
+    let selected: Vec<&dyn Sorter> = bench
+        .algorithms
+        .iter()
+        .filter_map(|name| sorters::find(name))
+        .filter(|s| s.supports(workload.element))
+        .collect();
+
+    println!("\n== {} {:?} elements, {:?} ==", workload.data_len, workload.element, workload.distribution);
+    let data = dataset(bench, workload, files);
+    let expected = check_against_std.then(|| data.sorted_by_std());
+    let mut runs = Vec::new();
+    let mut stats = Vec::new();
+    let mut baseline: Option<(&str, f64)> = None;
+    println!("algorithm\tthreads\truns\tmin\tmedian\tp95\tmean\tstddev\tci95 (ms)\tMelem/s\tGB/s");
+    for &sorter in selected.iter().filter(|s| !s.parallel()) {
+        let name = sorter.name();
+        let serial_runs = run_tests(sorter, bench, workload, &data, expected.as_ref(), &PsrsConfig::with_threads(1));
+        repro::write_bundle(bench, files, &serial_runs);
+        let serial_stats = run_stats(name, workload, 1, &serial_runs);
+        print_stats(&serial_stats);
+        if baseline.is_none_or(|(_, best)| serial_stats.median < best) {
+            baseline = Some((name, serial_stats.median));
+        }
+        stats.push(serial_stats);
+        runs.extend(serial_runs);
+    }
+    let parallel: Vec<&dyn Sorter> = selected.iter().copied().filter(|s| s.parallel()).collect();
+    let mut parallel_stats = Vec::new();
+    for &num_threads in &bench.thread_counts {
+        for &sorter in &parallel {
+            let config = PsrsConfig {
+                oversubscription: bench.oversubscription,
+                phase_threads: bench.phase_threads,
+                ..PsrsConfig::with_threads(num_threads)
+            };
+            let parallel_runs = run_tests(sorter, bench, workload, &data, expected.as_ref(), &config);
+            repro::write_bundle(bench, files, &parallel_runs);
+            let stats = run_stats(sorter.name(), workload, num_threads, &parallel_runs);
+            print_stats(&stats);
+            parallel_stats.push(stats);
+            runs.extend(parallel_runs);
+        }
+    }
+
+    let (scaling, fits) = match baseline {
+        Some((baseline_name, baseline_ms)) => {
+            let scaling = scaling_rows(baseline_ms, &parallel_stats);
+            println!("\nscaling vs {baseline_name} (median {baseline_ms:.3} ms)");
+            println!("algorithm\tthreads\tmedian ms\tspeedup\tefficiency\tkarp-flatt");
+            for row in &scaling {
+                let karp_flatt = row.karp_flatt.map_or("-".to_string(), |e| format!("{e:.3}"));
+                println!(
+                    "{}\t{}\t{:.3}\t{:.2}\t{:.2}\t{karp_flatt}",
+                    row.algorithm, row.threads, row.median_ms, row.speedup, row.efficiency
+                );
+            }
+            let fits: Vec<ScalingFit> = parallel.iter().map(|s| fit_serial_fraction(s.name(), workload, &scaling)).collect();
+            println!("\nalgorithm\tamdahl f\tgustafson f");
+            let fraction = |f: Option<f64>| f.map_or("n/a".to_string(), |f| format!("{f:.3}"));
+            for fit in &fits {
+                let (amdahl, gustafson) = (fraction(fit.amdahl_serial_fraction), fraction(fit.gustafson_serial_fraction));
+                println!("{}\t{amdahl}\t{gustafson}", fit.algorithm);
+            }
+            (scaling, fits)
+        }
+        None => (Vec::new(), Vec::new()),
+    };
+    stats.extend(parallel_stats);
+
+    Experiment { runs, stats, scaling, fits }
+}
+
+/// Parses a benchmark config, picking the format from the file extension
+/// (`.toml`, `.yaml`/`.yml`, anything else is JSON).
+fn load_config(path: &str) -> BenchConfig {
+    let text = fs::read_to_string(path).expect("failed to read benchmark config");
+    match Path::new(path).extension().and_then(|e| e.to_str()) {
+        Some("toml") => toml::from_str(&text).expect("invalid benchmark config"),
+        Some("yaml" | "yml") => serde_norway::from_str(&text).expect("invalid benchmark config"),
+        _ => serde_json::from_str(&text).expect("invalid benchmark config"),
+    }
 }
 
 fn main() {
-    let num_runs = 5;
-
-    // let num_threads = 50;
-    // for data_len in (0..100_000_001).step_by(10_000_000) {
-    //     if data_len == 0 {
-    //         continue;
-    //     }
-    //     let psrs_runs = run_tests("psrs", 2, num_runs, data_len, 0, 50, num_threads);
-    //     let serial_runs = run_tests("serial", 2, num_runs, data_len, 0, 50, num_threads);
-    //
-    //     let psrs_avg = psrs_runs.iter().sum::<u128>() / psrs_runs.len() as u128;
-    //     let serial_avg = serial_runs.iter().sum::<u128>() / serial_runs.len() as u128;
-    //
-    //     println!("{data_len}\t{psrs_avg}\t{serial_avg}")
-    // }
-
-    let serial_runs = run_tests("serial", 2, num_runs, 100_000_000, 0, 50, 1);
-    let serial_avg = serial_runs.iter().sum::<u128>() / serial_runs.len() as u128;
-    println!("serial baseline {}", serial_avg);
-    for num_threads in [4, 8, 16, 32, 64, 128] {
-        let psrs_runs = run_tests("psrs", 2, num_runs, 100_000_000, 0, 50, num_threads);
-        let psrs_avg = psrs_runs.iter().sum::<u128>() / psrs_runs.len() as u128;
-        println!("{num_threads}\t{psrs_avg}")
+    // Usage: [--save-data dir] [--load-data dir] [--check-against-std] [--seed N]
+    //        [config.{json,toml,yaml}] [report.json]
+    //    or: csv --key-column N [options], see `csv_sort`
+    //    or: compare old.json new.json [--threshold percent]
+    //    or: merge-files (--type T | --text) out in1 in2 ..., see `merge_files`
+    //    or: pipe [--binary type] [--reverse], see `pipe`
+    //    or: records --record-size N --key-offset N --key-type T [options], see `record_sort`
+    //    or: repro bundle_dir [--runs N], see `repro`
+    //    or: gen --size SIZE --out data.bin [--distribution uniform|zipf], see `gen`
+    //    or: tune [--threads N] [--sizes N,N,...] [--out profile.json], see `tune`
+    //    or: serve [--listen [HOST]:PORT] [--threads N], see `serve`
+    //    or: trace --len N --out trace.json [options], see `trace`
+    //    or: selftest
+    let mut args = std::env::args().skip(1).peekable();
+    if args.next_if_eq("csv").is_some() {
+        return csv_sort::main(args);
+    }
+    if args.next_if_eq("compare").is_some() {
+        return compare::main(args);
+    }
+    if args.next_if_eq("merge-files").is_some() {
+        return merge_files::main(args);
+    }
+    if args.next_if_eq("pipe").is_some() {
+        return pipe::main(args);
+    }
+    if args.next_if_eq("records").is_some() {
+        return record_sort::main(args);
+    }
+    if args.next_if_eq("repro").is_some() {
+        return repro::main(args);
+    }
+    if args.next_if_eq("gen").is_some() {
+        return gen::main(args);
+    }
+    if args.next_if_eq("tune").is_some() {
+        return tune::main(args);
+    }
+    if args.next_if_eq("serve").is_some() {
+        return serve::main(args);
+    }
+    if args.next_if_eq("trace").is_some() {
+        return trace::main(args);
+    }
+    if args.next_if_eq("selftest").is_some() {
+        return selftest::main(args);
+    }
+    let mut files = DataFiles::default();
+    let mut check_against_std = false;
+    let mut seed = None;
+    let mut positional = Vec::new();
+    while let Some(arg) = args.next() {
+        match arg.as_str() {
+            "--save-data" => files.save = Some(args.next().expect("--save-data needs a directory").into()),
+            "--load-data" => files.load = Some(args.next().expect("--load-data needs a directory").into()),
+            "--check-against-std" => check_against_std = true,
+            "--seed" => seed = Some(args.next().and_then(|s| s.parse().ok()).expect("--seed needs a number")),
+            _ => positional.push(arg),
+        }
+    }
+    let mut positional = positional.into_iter();
+    let mut bench = match positional.next() {
+        Some(path) => load_config(&path),
+        None => BenchConfig::default(),
+    };
+    bench.seed = seed.or(bench.seed).or_else(|| Some(rand::random()));
+    let report_path = positional.next();
+    for name in bench.algorithms.iter().filter(|name| sorters::find(name).is_none()) {
+        eprintln!("unknown algorithm {name:?} ignored");
+    }
+
+    let mut report = BenchReport { config: bench.clone(), runs: Vec::new(), stats: Vec::new(), scaling: Vec::new(), fits: Vec::new() };
+    for &data_len in &bench.data_lens {
+        for &distribution in &bench.distributions {
+            for &element in &bench.element_types {
+                let workload = Workload { data_len, distribution, element };
+                let experiment = run_experiment(&bench, workload, &files, check_against_std);
+                report.runs.extend(experiment.runs);
+                report.stats.extend(experiment.stats);
+                report.scaling.extend(experiment.scaling);
+                report.fits.extend(experiment.fits);
+            }
+        }
+    }
+
+    if let Some(dir) = &bench.charts_dir {
+        #[cfg(feature = "plots")]
+        charts::write_charts(Path::new(dir), &bench.chart_format, &report.runs, &report.scaling).expect("failed to write charts");
+        #[cfg(not(feature = "plots"))]
+        eprintln!("charts_dir {dir:?} ignored: built without the `plots` feature");
+    }
+
+    if let Some(path) = report_path {
+        let json = serde_json::to_string_pretty(&report).expect("failed to serialize report");
+        fs::write(&path, json).expect("failed to write report");
     }
 }
@@ -0,0 +1,613 @@
+use std::cmp::{Ordering, Reverse};
+use std::collections::BinaryHeap;
+use std::iter::FusedIterator;
+
+use crate::{CancellationToken, MergeStructure, PsrsError};
+
+/// How many merged elements pass between cancellation checks.
+const CANCEL_CHECK_INTERVAL: usize = 1 << 16;
+
+/// Size of the per-slice staging buffers of [`merge_into`]: one page, so a
+/// refill touches a single TLB entry.
+const STAGING_BYTES: usize = 4096;
+
+/// Performs a k‑way merge of several sorted slices using a binary heap.
+pub fn k_way_merge<T: Clone + Ord>(slices: &[&[T]]) -> Vec<T> {
+    k_way_merge_by(slices, &T::cmp)
+}
+
+/// Like [`k_way_merge`], with the slices sorted by `compare`.
+///
+/// Equal elements are emitted in slice order, so merging the pieces of a
+/// stably split input keeps it stable. Every element is cloned exactly once,
+/// into the output.
+pub fn k_way_merge_by<T, F>(slices: &[&[T]], compare: &F) -> Vec<T>
+where
+    T: Clone,
+    F: Fn(&T, &T) -> Ordering,
+{
+    let mut merged = Vec::with_capacity(slices.iter().map(|s| s.len()).sum());
+    merge_into(slices, compare, MergeStructure::default(), None, &mut |x| merged.push(x))
+        .expect("merge without a token cannot be cancelled");
+    merged
+}
+
+/// Lazy k‑way merge of several sorted slices, yielding references to the
+/// elements in order. Nothing is merged ahead of the caller, so taking the
+/// first few elements of large shards costs `O(k + m log k)` for `m` taken.
+///
+/// Like [`k_way_merge_by`], equal elements come out in slice order.
+pub struct KWayMergeIter<'a, T, F = fn(&T, &T) -> Ordering> {
+    /// What is left of each slice; the head is the next candidate.
+    slices: Vec<&'a [T]>,
+    /// Binary min-heap of indices into `slices`, ordered by their heads.
+    heap: Vec<usize>,
+    remaining: usize,
+    compare: F,
+}
+
+impl<'a, T: Ord> KWayMergeIter<'a, T> {
+    /// Merges `slices`, each sorted by `Ord`.
+    pub fn new(slices: &[&'a [T]]) -> Self {
+        KWayMergeIter::new_by(slices, T::cmp)
+    }
+}
+
+impl<'a, T, F: Fn(&T, &T) -> Ordering> KWayMergeIter<'a, T, F> {
+    /// Merges `slices`, each sorted by `compare`.
+    pub fn new_by(slices: &[&'a [T]], compare: F) -> Self {
+        let mut iter = KWayMergeIter {
+            slices: slices.to_vec(),
+            heap: (0..slices.len()).filter(|&i| !slices[i].is_empty()).collect(),
+            remaining: slices.iter().map(|s| s.len()).sum(),
+            compare,
+        };
+        for i in (0..iter.heap.len() / 2).rev() {
+            iter.sift_down(i);
+        }
+        iter
+    }
+
+    /// Whether slice `a`'s head goes out before slice `b`'s.
+    fn less(&self, a: usize, b: usize) -> bool {
+        (self.compare)(&self.slices[a][0], &self.slices[b][0]).then(a.cmp(&b)) == Ordering::Less
+    }
+
+    fn sift_down(&mut self, mut i: usize) {
+        loop {
+            let mut smallest = i;
+            for child in [2 * i + 1, 2 * i + 2] {
+                if child < self.heap.len() && self.less(self.heap[child], self.heap[smallest]) {
+                    smallest = child;
+                }
+            }
+            if smallest == i {
+                return;
+            }
+            self.heap.swap(i, smallest);
+            i = smallest;
+        }
+    }
+}
+
+impl<'a, T, F: Fn(&T, &T) -> Ordering> Iterator for KWayMergeIter<'a, T, F> {
+    type Item = &'a T;
+
+    fn next(&mut self) -> Option<&'a T> {
+        let &top = self.heap.first()?;
+        let (head, rest) = self.slices[top].split_first()?;
+        self.slices[top] = rest;
+        if rest.is_empty() {
+            self.heap.swap_remove(0);
+        }
+        self.sift_down(0);
+        self.remaining -= 1;
+        Some(head)
+    }
+
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        (self.remaining, Some(self.remaining))
+    }
+}
+
+impl<T, F: Fn(&T, &T) -> Ordering> ExactSizeIterator for KWayMergeIter<'_, T, F> {}
+
+impl<T, F: Fn(&T, &T) -> Ordering> FusedIterator for KWayMergeIter<'_, T, F> {}
+
+/// Heap entry ordered by `compare` on the value, then by slice index.
+struct Entry<'a, T, F> {
+    val: &'a T,
+    slice_idx: usize,
+    idx_in_slice: usize,
+    compare: &'a F,
+}
+
+impl<T, F: Fn(&T, &T) -> Ordering> Ord for Entry<'_, T, F> {
+    fn cmp(&self, other: &Self) -> Ordering {
+        (self.compare)(self.val, other.val).then(self.slice_idx.cmp(&other.slice_idx))
+    }
+}
+
+impl<T, F: Fn(&T, &T) -> Ordering> PartialOrd for Entry<'_, T, F> {
+    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+impl<T, F: Fn(&T, &T) -> Ordering> PartialEq for Entry<'_, T, F> {
+    fn eq(&self, other: &Self) -> bool {
+        self.cmp(other) == Ordering::Equal
+    }
+}
+
+impl<T, F: Fn(&T, &T) -> Ordering> Eq for Entry<'_, T, F> {}
+
+/// Merges `slices` through `structure`, handing each output element to
+/// `push` in order, so the caller decides where (and in which allocator) the
+/// output lives.
+///
+/// Elements are not read from the slices one at a time: each slice is
+/// copied, [`STAGING_BYTES`] at a time, into a small staging buffer of its
+/// own and the merge reads from those. With many slices far apart in
+/// memory, this turns one cache and TLB miss per element into one
+/// sequential copy per block.
+pub(crate) fn merge_into<T, F>(
+    slices: &[&[T]],
+    compare: &F,
+    structure: MergeStructure,
+    cancel: Option<&CancellationToken>,
+    push: &mut impl FnMut(T),
+) -> Result<(), PsrsError>
+where
+    T: Clone,
+    F: Fn(&T, &T) -> Ordering,
+{
+    // With skewed data many partitions draw from only one or two chunks,
+    // which need no heap at all.
+    let mut non_empty = slices.iter().filter(|s| !s.is_empty());
+    match (non_empty.next(), non_empty.next(), non_empty.next()) {
+        (None, _, _) => return Ok(()),
+        (Some(only), None, _) => {
+            for chunk in only.chunks(CANCEL_CHECK_INTERVAL) {
+                if cancel.is_some_and(|c| c.is_cancelled()) {
+                    return Err(PsrsError::Cancelled);
+                }
+                chunk.iter().cloned().for_each(&mut *push);
+            }
+            return Ok(());
+        }
+        (Some(a), Some(b), None) => return merge_two(a, b, compare, cancel, push),
+        _ => {}
+    }
+
+    let mut staging = Staging::new(slices);
+    match structure {
+        MergeStructure::BinaryHeap => {
+            // The heap owns the head of every slice, so comparisons never
+            // reach back into the staging buffers.
+            let entry = |slice_idx, val| Reverse(Staged { val, slice_idx, compare });
+            let mut heap: BinaryHeap<_> =
+                (0..slices.len()).filter_map(|i| staging.take(i).map(|val| entry(i, val))).collect();
+            let mut emitted = 0usize;
+            while let Some(Reverse(Staged { val, slice_idx, .. })) = heap.pop() {
+                push(val);
+                emitted += 1;
+                check_cancelled(emitted, cancel)?;
+                heap.extend(staging.take(slice_idx).map(|val| entry(slice_idx, val)));
+            }
+        }
+        MergeStructure::QuaternaryHeap => {
+            let mut heap: Vec<Staged<T, F>> = (0..slices.len())
+                .filter_map(|slice_idx| staging.take(slice_idx).map(|val| Staged { val, slice_idx, compare }))
+                .collect();
+            for i in (0..heap.len().div_ceil(4)).rev() {
+                sift_down_quaternary(&mut heap, i);
+            }
+            let mut emitted = 0usize;
+            while !heap.is_empty() {
+                let slice_idx = heap[0].slice_idx;
+                let val = match staging.take(slice_idx) {
+                    // Replacing the top and sifting it down once is cheaper
+                    // than a pop followed by a push.
+                    Some(next) => std::mem::replace(&mut heap[0].val, next),
+                    None => heap.swap_remove(0).val,
+                };
+                sift_down_quaternary(&mut heap, 0);
+                push(val);
+                emitted += 1;
+                check_cancelled(emitted, cancel)?;
+            }
+        }
+        MergeStructure::LoserTree => {
+            let mut tree = LoserTree::new(slices.len(), |i| staging.take(i), compare);
+            let mut emitted = 0usize;
+            while let Some(val) = tree.pop(|i| staging.take(i)) {
+                push(val);
+                emitted += 1;
+                check_cancelled(emitted, cancel)?;
+            }
+        }
+    }
+    Ok(())
+}
+
+/// Returns [`PsrsError::Cancelled`] every [`CANCEL_CHECK_INTERVAL`] merged
+/// elements once `cancel` has been cancelled.
+fn check_cancelled(emitted: usize, cancel: Option<&CancellationToken>) -> Result<(), PsrsError> {
+    if emitted.is_multiple_of(CANCEL_CHECK_INTERVAL) && cancel.is_some_and(|c| c.is_cancelled()) {
+        return Err(PsrsError::Cancelled);
+    }
+    Ok(())
+}
+
+/// The per-slice staging buffers of [`merge_into`].
+struct Staging<'a, T> {
+    slices: &'a [&'a [T]],
+    /// Each buffer holds the next elements of its slice in reverse, so the
+    /// next one is moved out with `pop()`.
+    buffers: Vec<Vec<T>>,
+    /// Position in each slice of the first element not yet staged.
+    next: Vec<usize>,
+    block: usize,
+}
+
+impl<'a, T: Clone> Staging<'a, T> {
+    fn new(slices: &'a [&'a [T]]) -> Self {
+        let block = (STAGING_BYTES / std::mem::size_of::<T>().max(1)).max(1);
+        Staging {
+            slices,
+            buffers: slices.iter().map(|s| Vec::with_capacity(block.min(s.len()))).collect(),
+            next: vec![0; slices.len()],
+            block,
+        }
+    }
+
+    /// Moves out the next element of slice `slice_idx`, refilling its
+    /// buffer when it runs dry.
+    fn take(&mut self, slice_idx: usize) -> Option<T> {
+        let buffer = &mut self.buffers[slice_idx];
+        if buffer.is_empty() {
+            let slice = self.slices[slice_idx];
+            let start = self.next[slice_idx];
+            let end = (start + self.block).min(slice.len());
+            buffer.extend(slice[start..end].iter().rev().cloned());
+            self.next[slice_idx] = end;
+        }
+        buffer.pop()
+    }
+}
+
+/// Restores the order of a 4-ary min-heap below position `i`. Each level
+/// compares four siblings that share a cache line (for small elements),
+/// and the heap is half as deep as a binary one.
+fn sift_down_quaternary<T, F: Fn(&T, &T) -> Ordering>(heap: &mut [Staged<T, F>], mut i: usize) {
+    loop {
+        let first = 4 * i + 1;
+        if first >= heap.len() {
+            return;
+        }
+        let last = (first + 4).min(heap.len());
+        let mut smallest = first;
+        for child in first + 1..last {
+            if heap[child] < heap[smallest] {
+                smallest = child;
+            }
+        }
+        if heap[smallest] >= heap[i] {
+            return;
+        }
+        heap.swap(i, smallest);
+        i = smallest;
+    }
+}
+
+/// Tournament tree over the slice heads: every internal node holds the
+/// slice that lost the match played there and `winner` the overall one.
+/// Replacing the winner replays only its leaf-to-root path, one comparison
+/// per level, with no swaps.
+struct LoserTree<'a, T, F> {
+    /// Head of each slice; `None` once it is exhausted, which loses to
+    /// everything.
+    heads: Vec<Option<T>>,
+    /// `losers[node]` for internal nodes `1..leaves`.
+    losers: Vec<usize>,
+    winner: usize,
+    /// Leaf count, the slice count rounded up to a power of two.
+    leaves: usize,
+    compare: &'a F,
+}
+
+impl<'a, T, F: Fn(&T, &T) -> Ordering> LoserTree<'a, T, F> {
+    fn new(k: usize, mut take: impl FnMut(usize) -> Option<T>, compare: &'a F) -> Self {
+        let leaves = k.next_power_of_two();
+        let mut tree = LoserTree {
+            heads: (0..leaves).map(|i| if i < k { take(i) } else { None }).collect(),
+            losers: vec![0; leaves],
+            winner: 0,
+            leaves,
+            compare,
+        };
+        // Play every match bottom-up; `winners[node]` is who went up.
+        let mut winners = vec![0; 2 * leaves];
+        for leaf in 0..leaves {
+            winners[leaves + leaf] = leaf;
+        }
+        for node in (1..leaves).rev() {
+            let (a, b) = (winners[2 * node], winners[2 * node + 1]);
+            let (win, lose) = if tree.beats(a, b) { (a, b) } else { (b, a) };
+            winners[node] = win;
+            tree.losers[node] = lose;
+        }
+        tree.winner = winners[1];
+        tree
+    }
+
+    /// Whether slice `a`'s head goes before slice `b`'s; ties go to the
+    /// lower slice index.
+    fn beats(&self, a: usize, b: usize) -> bool {
+        match (&self.heads[a], &self.heads[b]) {
+            (Some(x), Some(y)) => (self.compare)(x, y).then(a.cmp(&b)) == Ordering::Less,
+            (Some(_), None) => true,
+            (None, _) => false,
+        }
+    }
+
+    /// Removes the smallest head and refills its slot from `take`.
+    fn pop(&mut self, take: impl FnOnce(usize) -> Option<T>) -> Option<T> {
+        let slice_idx = self.winner;
+        let val = self.heads[slice_idx].take()?;
+        self.heads[slice_idx] = take(slice_idx);
+        let mut winner = slice_idx;
+        let mut node = (self.leaves + slice_idx) / 2;
+        while node >= 1 {
+            let loser = self.losers[node];
+            if self.beats(loser, winner) {
+                self.losers[node] = winner;
+                winner = loser;
+            }
+            node /= 2;
+        }
+        self.winner = winner;
+        Some(val)
+    }
+}
+
+/// Two-way merge of `a` and `b` for [`merge_into`]. Ties go to `a`.
+///
+/// The next element is picked by indexing the pair of heads with the
+/// comparison result, and both cursors advance by arithmetic on it, so for
+/// primitive keys the loop has no data-dependent branch for a merge of
+/// random data to mispredict about half the time: with `u64` on x86-64
+/// (rustc 1.95, `-O`) the pick is a `cmovb` and the cursors advance by a
+/// `setb` and an `sbb`, leaving only the loop bounds and the output's
+/// capacity check to branch on. Types whose comparison or `clone` is not
+/// inlined to a few instructions still branch inside them.
+fn merge_two<T, F>(
+    a: &[T],
+    b: &[T],
+    compare: &F,
+    cancel: Option<&CancellationToken>,
+    push: &mut impl FnMut(T),
+) -> Result<(), PsrsError>
+where
+    T: Clone,
+    F: Fn(&T, &T) -> Ordering,
+{
+    let (mut i, mut j) = (0, 0);
+    let mut emitted = 0usize;
+    while i < a.len() && j < b.len() {
+        let heads = [&a[i], &b[j]];
+        let take_b = compare(heads[1], heads[0]) == Ordering::Less;
+        push(heads[take_b as usize].clone());
+        j += take_b as usize;
+        i += !take_b as usize;
+        emitted += 1;
+        if emitted.is_multiple_of(CANCEL_CHECK_INTERVAL) && cancel.is_some_and(|c| c.is_cancelled()) {
+            return Err(PsrsError::Cancelled);
+        }
+    }
+    a[i..].iter().chain(&b[j..]).cloned().for_each(push);
+    Ok(())
+}
+
+/// Heap entry of [`merge_into`]: the next element of a slice, moved out of
+/// its staging buffer. Ordered like [`Entry`].
+struct Staged<'a, T, F> {
+    val: T,
+    slice_idx: usize,
+    compare: &'a F,
+}
+
+impl<T, F: Fn(&T, &T) -> Ordering> Ord for Staged<'_, T, F> {
+    fn cmp(&self, other: &Self) -> Ordering {
+        (self.compare)(&self.val, &other.val).then(self.slice_idx.cmp(&other.slice_idx))
+    }
+}
+
+impl<T, F: Fn(&T, &T) -> Ordering> PartialOrd for Staged<'_, T, F> {
+    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+impl<T, F: Fn(&T, &T) -> Ordering> PartialEq for Staged<'_, T, F> {
+    fn eq(&self, other: &Self) -> bool {
+        self.cmp(other) == Ordering::Equal
+    }
+}
+
+impl<T, F: Fn(&T, &T) -> Ordering> Eq for Staged<'_, T, F> {}
+
+/// Like [`merge_into`], but hands `push` the slice and the index within it
+/// of each output element instead of a clone, so data stored next to the
+/// merged values can follow them.
+pub(crate) fn merge_positions<T, F>(
+    slices: &[&[T]],
+    compare: &F,
+    cancel: Option<&CancellationToken>,
+    push: &mut impl FnMut(usize, usize),
+) -> Result<(), PsrsError>
+where
+    F: Fn(&T, &T) -> Ordering,
+{
+    let entry = |slice_idx: usize, idx_in_slice: usize| {
+        Reverse(Entry { val: &slices[slice_idx][idx_in_slice], slice_idx, idx_in_slice, compare })
+    };
+
+    let mut heap = BinaryHeap::with_capacity(slices.len());
+    // We load up the heap with the first elements of each slice.
+    for (i, slice) in slices.iter().enumerate() {
+        if !slice.is_empty() {
+            heap.push(entry(i, 0));
+        }
+    }
+
+    // Create the final sorted array by selecting the smallest element
+    // of our slices given by the min heap.
+    let mut emitted = 0usize;
+    while let Some(Reverse(Entry { slice_idx, idx_in_slice, .. })) = heap.pop() {
+        push(slice_idx, idx_in_slice);
+        emitted += 1;
+        if emitted.is_multiple_of(CANCEL_CHECK_INTERVAL) && cancel.is_some_and(|c| c.is_cancelled()) {
+            return Err(PsrsError::Cancelled);
+        }
+        let next_idx = idx_in_slice + 1;
+        if next_idx < slices[slice_idx].len() {
+            heap.push(entry(slice_idx, next_idx));
+        }
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::testing::{edge_cases, random_values, std_sorted};
+
+    /// `values` dealt round-robin into `k` slices, each sorted by key, with
+    /// every element tagged by its slice so the tie order can be checked.
+    fn sorted_slices(values: &[u64], k: usize) -> Vec<Vec<(u64, usize)>> {
+        let mut slices = vec![Vec::new(); k];
+        for (i, &v) in values.iter().enumerate() {
+            slices[i % k].push((v, i % k));
+        }
+        for slice in &mut slices {
+            slice.sort_by_key(|&(v, _)| v);
+        }
+        slices
+    }
+
+    /// What a stable merge of `slices` by key must produce: ties in slice
+    /// order.
+    fn stable_merge(slices: &[Vec<(u64, usize)>]) -> Vec<(u64, usize)> {
+        let mut expected = slices.concat();
+        expected.sort_by_key(|&(v, _)| v);
+        expected
+    }
+
+    fn by_key(a: &(u64, usize), b: &(u64, usize)) -> Ordering {
+        a.0.cmp(&b.0)
+    }
+
+    #[test]
+    fn k_way_merge_is_stable() {
+        for k in [1, 2, 3, 8] {
+            for (name, values) in edge_cases(k) {
+                let values: Vec<u64> = values.iter().map(|v| v % 100).collect();
+                let slices = sorted_slices(&values, k);
+                let refs: Vec<&[(u64, usize)]> = slices.iter().map(Vec::as_slice).collect();
+                assert_eq!(k_way_merge_by(&refs, &by_key), stable_merge(&slices), "{name}, k = {k}");
+            }
+        }
+        assert!(k_way_merge::<u8>(&[]).is_empty());
+    }
+
+    /// Merges `slices` with [`merge_into`] through `structure`.
+    fn merge_with<T: Clone>(
+        slices: &[Vec<T>],
+        compare: &impl Fn(&T, &T) -> Ordering,
+        structure: MergeStructure,
+    ) -> Vec<T> {
+        let refs: Vec<&[T]> = slices.iter().map(Vec::as_slice).collect();
+        let mut merged = Vec::new();
+        merge_into(&refs, compare, structure, None, &mut |x| merged.push(x)).unwrap();
+        merged
+    }
+
+    #[test]
+    fn staging_buffers_refill_across_blocks() {
+        // Elements of 1 KiB stage four at a time, and one-byte ones 4096.
+        let values = random_values(30_000, 1_000, 18);
+        let wide: Vec<Vec<(u64, usize, [u8; 1000])>> = sorted_slices(&values[..2_000], 5)
+            .into_iter()
+            .map(|slice| slice.into_iter().map(|(v, s)| (v, s, [v as u8; 1000])).collect())
+            .collect();
+        let mut expected = wide.concat();
+        expected.sort_by_key(|&(v, _, _)| v);
+        assert_eq!(merge_with(&wide, &|a, b| a.0.cmp(&b.0), MergeStructure::default()), expected);
+
+        let narrow: Vec<Vec<u8>> = (0..3)
+            .map(|i| std_sorted(&values.iter().skip(i).step_by(3).map(|&v| v as u8).collect::<Vec<_>>()))
+            .collect();
+        assert_eq!(merge_with(&narrow, &u8::cmp, MergeStructure::default()), std_sorted(&narrow.concat()));
+    }
+
+    #[test]
+    fn one_or_two_sources_bypass_the_heap_stably() {
+        for (name, values) in edge_cases(2) {
+            let values: Vec<u64> = values.iter().map(|v| v % 50).collect();
+            let pair = sorted_slices(&values, 2);
+            // Empty slices around the sources must not count as sources.
+            let padded = [vec![], pair[0].clone(), vec![], pair[1].clone(), vec![]];
+            let expected = stable_merge(&pair);
+            assert_eq!(merge_with(&padded, &by_key, MergeStructure::default()), expected, "{name} (two)");
+
+            let single = [vec![], pair[0].clone(), vec![]];
+            assert_eq!(merge_with(&single, &by_key, MergeStructure::default()), pair[0], "{name} (one)");
+        }
+    }
+
+    const STRUCTURES: [MergeStructure; 3] =
+        [MergeStructure::BinaryHeap, MergeStructure::QuaternaryHeap, MergeStructure::LoserTree];
+
+    #[test]
+    fn every_merge_structure_is_stable() {
+        for structure in STRUCTURES {
+            // Slice counts below, at and above powers of two and of four.
+            for k in [3, 4, 5, 16, 17] {
+                for (name, values) in edge_cases(k) {
+                    let values: Vec<u64> = values.iter().map(|v| v % 100).collect();
+                    let slices = sorted_slices(&values, k);
+                    let merged = merge_with(&slices, &by_key, structure);
+                    assert_eq!(merged, stable_merge(&slices), "{structure:?}, {name}, k = {k}");
+                }
+            }
+        }
+    }
+
+
+    #[test]
+    fn lazy_merge_matches_k_way_merge() {
+        for k in [1, 2, 5] {
+            for (name, values) in edge_cases(k) {
+                let values: Vec<u64> = values.iter().map(|v| v % 100).collect();
+                let slices = sorted_slices(&values, k);
+                let refs: Vec<&[(u64, usize)]> = slices.iter().map(Vec::as_slice).collect();
+                let iter = KWayMergeIter::new_by(&refs, by_key);
+                assert_eq!(iter.len(), values.len(), "{name}, k = {k}");
+                let merged: Vec<(u64, usize)> = iter.copied().collect();
+                assert_eq!(merged, stable_merge(&slices), "{name}, k = {k}");
+            }
+        }
+    }
+
+    #[test]
+    fn lazy_merge_yields_only_what_is_taken() {
+        let slices: [&[u32]; 3] = [&[1, 4, 7], &[2, 5], &[0, 3, 6, 8]];
+        let mut iter = KWayMergeIter::new(&slices);
+        assert_eq!(iter.by_ref().take(4).copied().collect::<Vec<_>>(), [0, 1, 2, 3]);
+        assert_eq!(iter.size_hint(), (5, Some(5)));
+        assert_eq!(iter.copied().collect::<Vec<_>>(), [4, 5, 6, 7, 8]);
+        assert_eq!(KWayMergeIter::<u32>::new(&[]).next(), None);
+    }
+}
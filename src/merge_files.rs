@@ -0,0 +1,150 @@
+//! `merge-files` subcommand: merges already-sorted files into one sorted
+//! output, e.g. shards produced by separate jobs.
+//!
+//! Binary files are merged with [`merge_sorted_files`]. Text files are
+//! merged line by line, like `sort -m`; with `--numeric` lines compare by
+//! their numeric value, with lines that are not numbers last.
+
+use std::cmp::{Ordering, Reverse};
+use std::collections::BinaryHeap;
+use std::fs::File;
+use std::io::{self, BufRead, BufReader, BufWriter, Write};
+use std::path::Path;
+
+use psrs::external::merge_sorted_files;
+
+/// Options of `merge-files`, see [`usage`].
+struct MergeArgs {
+    element: Option<String>,
+    text: bool,
+    numeric: bool,
+    output: String,
+    inputs: Vec<String>,
+}
+
+fn usage() -> ! {
+    eprintln!(
+        "usage: merge-files (--type u8|u16|u32|u64|i32|i64 | --text [--numeric]) out in1 in2 ...\n\
+         Merges files that are each sorted into `out`. Binary files hold little-endian \
+         values of the given type; text files hold one element per line, compared as \
+         bytes or, with --numeric, as numbers."
+    );
+    std::process::exit(2)
+}
+
+fn parse_args(mut args: impl Iterator<Item = String>) -> MergeArgs {
+    let mut element = None;
+    let mut text = false;
+    let mut numeric = false;
+    let mut paths = Vec::new();
+    while let Some(arg) = args.next() {
+        match arg.as_str() {
+            "--type" => element = Some(args.next().unwrap_or_else(|| usage())),
+            "--text" => text = true,
+            "--numeric" => numeric = true,
+            _ => paths.push(arg),
+        }
+    }
+    if text == element.is_some() || numeric && !text || paths.len() < 2 {
+        usage();
+    }
+    let output = paths.remove(0);
+    MergeArgs { element, text, numeric, output, inputs: paths }
+}
+
+/// Runs `merge-files` with the arguments that follow the subcommand name.
+pub fn main(args: impl Iterator<Item = String>) {
+    let args = parse_args(args);
+    let output = Path::new(&args.output);
+    let result = match args.element.as_deref() {
+        _ if args.text => merge_lines(&args.inputs, output, args.numeric),
+        Some("u8") => merge_sorted_files::<u8>(&args.inputs, output),
+        Some("u16") => merge_sorted_files::<u16>(&args.inputs, output),
+        Some("u32") => merge_sorted_files::<u32>(&args.inputs, output),
+        Some("u64") => merge_sorted_files::<u64>(&args.inputs, output),
+        Some("i32") => merge_sorted_files::<i32>(&args.inputs, output),
+        Some("i64") => merge_sorted_files::<i64>(&args.inputs, output),
+        _ => usage(),
+    };
+    if let Err(e) = result {
+        eprintln!("merge-files: {e}");
+        std::process::exit(1);
+    }
+}
+
+/// The next line of a text input, ordered for the merge heap by `key`
+/// (when `--numeric`), then bytes, then input index.
+struct Line {
+    key: f64,
+    bytes: Vec<u8>,
+    input: usize,
+}
+
+impl Line {
+    fn cmp_key(&self, other: &Self) -> Ordering {
+        self.key.total_cmp(&other.key).then_with(|| self.bytes.cmp(&other.bytes))
+    }
+}
+
+impl Ord for Line {
+    fn cmp(&self, other: &Self) -> Ordering {
+        self.cmp_key(other).then(self.input.cmp(&other.input))
+    }
+}
+
+impl PartialOrd for Line {
+    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+impl PartialEq for Line {
+    fn eq(&self, other: &Self) -> bool {
+        self.cmp(other) == Ordering::Equal
+    }
+}
+
+impl Eq for Line {}
+
+/// Merges sorted text files line by line. Equal lines come out in input
+/// order.
+fn merge_lines(inputs: &[String], output: &Path, numeric: bool) -> io::Result<()> {
+    let mut readers = inputs.iter().map(|path| File::open(path).map(BufReader::new)).collect::<io::Result<Vec<_>>>()?;
+    let mut out = BufWriter::new(File::create(output)?);
+    let read_line = |reader: &mut BufReader<File>, input: usize| -> io::Result<Option<Line>> {
+        let mut bytes = Vec::new();
+        if reader.read_until(b'\n', &mut bytes)? == 0 {
+            return Ok(None);
+        }
+        if bytes.last() == Some(&b'\n') {
+            bytes.pop();
+        }
+        let key = if numeric { numeric_key(&bytes) } else { 0.0 };
+        Ok(Some(Line { key, bytes, input }))
+    };
+
+    let mut heap = BinaryHeap::with_capacity(readers.len());
+    for (input, reader) in readers.iter_mut().enumerate() {
+        if let Some(line) = read_line(reader, input)? {
+            heap.push(Reverse(line));
+        }
+    }
+    while let Some(Reverse(line)) = heap.pop() {
+        out.write_all(&line.bytes)?;
+        out.write_all(b"\n")?;
+        if let Some(next) = read_line(&mut readers[line.input], line.input)? {
+            if next.cmp_key(&line) == Ordering::Less {
+                let message = format!("{} is not sorted", inputs[line.input]);
+                return Err(io::Error::new(io::ErrorKind::InvalidData, message));
+            }
+            heap.push(Reverse(next));
+        }
+    }
+    out.flush()
+}
+
+/// Parses a line as a number. Anything else becomes NaN, which sorts after
+/// every number.
+fn numeric_key(line: &[u8]) -> f64 {
+    std::str::from_utf8(line).ok().and_then(|text| text.trim().parse().ok()).unwrap_or(f64::NAN)
+}
@@ -0,0 +1,90 @@
+use std::cmp::Ordering;
+
+use crate::psrs_by;
+
+/// Where [`psrs_nullable`] puts the `None`s.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum NullOrder {
+    /// All `None`s before the first value, as in SQL's `NULLS FIRST`.
+    NullsFirst,
+    /// All `None`s after the last value, as in SQL's `NULLS LAST`.
+    #[default]
+    NullsLast,
+}
+
+/// Sorts nullable values by PSRS, with every `None` placed according to
+/// `nulls`.
+///
+/// The `None`s are moved to their end in a single pass first, so the PSRS
+/// phases only sample, split and merge the values.
+pub fn psrs_nullable<T: Clone + Ord + Send + Sync>(data: &mut [Option<T>], p: usize, nulls: NullOrder) {
+    psrs_nullable_by(data, p, nulls, T::cmp);
+}
+
+/// Like [`psrs_nullable`], but orders the values with `compare`.
+pub fn psrs_nullable_by<T, F>(data: &mut [Option<T>], p: usize, nulls: NullOrder, compare: F)
+where
+    T: Clone + Send + Sync,
+    F: Fn(&T, &T) -> Ordering + Sync,
+{
+    let values = move_nulls_aside(data, nulls);
+    psrs_by(values, p, |a, b| match (a, b) {
+        (Some(a), Some(b)) => compare(a, b),
+        _ => unreachable!("nulls were moved aside"),
+    });
+}
+
+/// Swaps every `None` to the end `nulls` asks for and returns the values
+/// left between them.
+fn move_nulls_aside<T>(data: &mut [Option<T>], nulls: NullOrder) -> &mut [Option<T>] {
+    // Elements matching `front` are packed, in one pass, before the rest.
+    let front = |x: &Option<T>| match nulls {
+        NullOrder::NullsFirst => x.is_none(),
+        NullOrder::NullsLast => x.is_some(),
+    };
+    let mut split = 0;
+    for i in 0..data.len() {
+        if front(&data[i]) {
+            data.swap(split, i);
+            split += 1;
+        }
+    }
+    match nulls {
+        NullOrder::NullsFirst => &mut data[split..],
+        NullOrder::NullsLast => &mut data[..split],
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::testing::{edge_cases, std_sorted};
+
+    #[test]
+    fn nulls_go_where_asked() {
+        for p in [1, 4, 7] {
+            for (name, values) in edge_cases(p) {
+                // About every third value is a null.
+                let mut data: Vec<Option<u64>> = values.iter().map(|&v| (v % 3 != 0).then_some(v)).collect();
+                // `Option`'s own order puts `None` first.
+                let nulls_first = std_sorted(&data);
+                let nulls = nulls_first.iter().take_while(|x| x.is_none()).count();
+                let nulls_last: Vec<Option<u64>> =
+                    nulls_first[nulls..].iter().chain(&nulls_first[..nulls]).copied().collect();
+
+                let mut first = data.clone();
+                psrs_nullable(&mut first, p, NullOrder::NullsFirst);
+                assert_eq!(first, nulls_first, "{name}, p = {p}");
+                psrs_nullable(&mut data, p, NullOrder::NullsLast);
+                assert_eq!(data, nulls_last, "{name}, p = {p}");
+            }
+        }
+    }
+
+    #[test]
+    fn nullable_by_orders_values_with_compare() {
+        let mut data = vec![Some(3), None, Some(1), Some(2), None];
+        psrs_nullable_by(&mut data, 2, NullOrder::NullsLast, |a: &i32, b| b.cmp(a));
+        assert_eq!(data, [Some(3), Some(2), Some(1), None, None]);
+    }
+}
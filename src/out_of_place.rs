@@ -0,0 +1,84 @@
+use std::sync::Mutex;
+
+use allocator_api2::alloc::Global;
+
+use crate::executor::{self, default_executor};
+use crate::local_sort::unstable_sort;
+use crate::{counting, merge, phases, presorted, MergeStructure};
+
+/// Sorts a copy of `src` into `dst`, which must be as long, leaving `src`
+/// untouched.
+///
+/// [`psrs`](crate::psrs) already merges into a second buffer and copies it
+/// back; here the chunks are copied out of `src` as they are sorted, and
+/// every partition is merged straight into its place in `dst`, so the cost
+/// is the same as an in-place sort.
+pub fn psrs_to<T: Clone + Ord + Send + Sync>(src: &[T], dst: &mut [T], p: usize) {
+    assert_eq!(src.len(), dst.len(), "psrs_to needs a destination as long as the source");
+    let n = src.len();
+    let exec = default_executor();
+    if counting::is_small_integer::<T>() {
+        dst.clone_from_slice(src);
+        counting::sort_small_integers(exec, dst, p);
+        return;
+    }
+    if p <= 1 || n < p {
+        dst.clone_from_slice(src);
+        presorted::sort_chunk(dst, &T::cmp, &unstable_sort::<T>);
+        return;
+    }
+
+    // Phase 1: Copy and sort each chunk.
+    let sorted: Vec<Vec<T>> = executor::par_chunks_map(exec, src, n / p, |_, chunk| {
+        let mut copy = chunk.to_vec();
+        presorted::sort_chunk(&mut copy, &T::cmp, &unstable_sort::<T>);
+        copy
+    });
+    let chunks: Vec<&[T]> = sorted.iter().map(Vec::as_slice).collect();
+
+    // Phases 2 and 3 as in `psrs`.
+    let pivots = phases::sample_pivots(exec, &chunks, p, 1, &[], &unstable_sort::<T>, Global);
+    let boundaries = phases::partition_boundaries(exec, &chunks, &pivots, &T::cmp, &[], Global);
+
+    // Phase 4: Merge every partition into its own slice of `dst`.
+    let mut parts: Vec<Mutex<&mut [T]>> = Vec::with_capacity(p);
+    let mut rest = dst;
+    for part_idx in 0..p {
+        let size = boundaries.rows().map(|b| b[part_idx + 1] - b[part_idx]).sum();
+        let (part, tail) = rest.split_at_mut(size);
+        parts.push(Mutex::new(part));
+        rest = tail;
+    }
+    executor::par_map(exec, p, |part_idx| {
+        let slices: Vec<&[T]> =
+            chunks.iter().zip(boundaries.rows()).map(|(chunk, b)| &chunk[b[part_idx]..b[part_idx + 1]]).collect();
+        let mut part = parts[part_idx].lock().unwrap();
+        let mut out = part.iter_mut();
+        merge::merge_into(&slices, &T::cmp, MergeStructure::default(), None, &mut |x| {
+            *out.next().expect("partition sizes match the merged slices") = x;
+        })
+        .expect("merge without a token cannot fail");
+    });
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::testing::{random_values, std_sorted};
+
+    #[test]
+    fn every_destination_element_is_overwritten() {
+        let src = random_values(50_000, u64::MAX, 19);
+        for p in [1, 4] {
+            let mut dst = vec![u64::MAX; src.len()];
+            psrs_to(&src, &mut dst, p);
+            assert_eq!(dst, std_sorted(&src), "p = {p}");
+        }
+    }
+
+    #[test]
+    #[should_panic(expected = "as long as the source")]
+    fn shorter_destination_panics() {
+        psrs_to(&[3, 1, 2], &mut [0; 2], 2);
+    }
+}
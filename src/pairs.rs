@@ -0,0 +1,114 @@
+use allocator_api2::alloc::Global;
+
+use crate::executor::{self, default_executor};
+use crate::local_sort::unstable_sort;
+use crate::{merge, phases};
+
+/// Sorts `keys` and applies the same reordering to `payloads`, which must
+/// be as long.
+///
+/// Unlike sorting `(key, payload)` tuples with [`psrs`](crate::psrs),
+/// payloads never travel through the sort: each chunk sorts `(key, index)`
+/// pairs, the sampling, partitioning and merge heap only ever look at keys,
+/// and each payload is copied once, by index, when its key leaves the merge
+/// (and once more back into `payloads`). The order of payloads with equal
+/// keys is unspecified.
+pub fn psrs_pairs<K, V>(keys: &mut [K], payloads: &mut [V], p: usize)
+where
+    K: Clone + Ord + Send + Sync,
+    V: Clone + Send + Sync,
+{
+    assert_eq!(keys.len(), payloads.len(), "psrs_pairs needs one payload per key");
+    let n = keys.len();
+    if p <= 1 || n < p {
+        let order = sort_chunk(keys);
+        let sorted: Vec<V> = order.iter().map(|&i| payloads[i].clone()).collect();
+        payloads.clone_from_slice(&sorted);
+        return;
+    }
+    let exec = default_executor();
+    let block_size = n / p;
+
+    // Phase 1: Sort the keys of each chunk, remembering where each came
+    // from within the chunk.
+    let orders: Vec<Vec<usize>> = executor::par_chunks_mut_map(exec, keys, block_size, |_, chunk| sort_chunk(chunk));
+    let key_chunks: Vec<&[K]> = keys.chunks(block_size).collect();
+    let payload_chunks: Vec<&[V]> = payloads.chunks(block_size).collect();
+
+    // Phases 2 and 3 only need the keys.
+    let pivots = phases::sample_pivots(exec, &key_chunks, p, 1, &[], &unstable_sort::<K>, Global);
+    let boundaries = phases::partition_boundaries(exec, &key_chunks, &pivots, &K::cmp, &[], Global);
+
+    // Phase 4: Merge the keys of each partition; every payload follows its
+    // key through the chunk's order.
+    let merged: Vec<(Vec<K>, Vec<V>)> = executor::par_map(exec, p, |part_idx| {
+        let ranges: Vec<_> = boundaries.rows().map(|b| b[part_idx]..b[part_idx + 1]).collect();
+        let slices: Vec<&[K]> = key_chunks.iter().zip(&ranges).map(|(chunk, r)| &chunk[r.clone()]).collect();
+        let size = slices.iter().map(|s| s.len()).sum();
+        let (mut out_keys, mut out_payloads) = (Vec::with_capacity(size), Vec::with_capacity(size));
+        merge::merge_positions(&slices, &K::cmp, None, &mut |chunk_idx, idx| {
+            out_keys.push(slices[chunk_idx][idx].clone());
+            let source = orders[chunk_idx][ranges[chunk_idx].start + idx];
+            out_payloads.push(payload_chunks[chunk_idx][source].clone());
+        })
+        .expect("merge without a token cannot fail");
+        (out_keys, out_payloads)
+    });
+
+    // Concatenate the merged partitions into the outputs.
+    let mut start = 0;
+    for (part_keys, part_payloads) in merged {
+        let end = start + part_keys.len();
+        keys[start..end].clone_from_slice(&part_keys);
+        payloads[start..end].clone_from_slice(&part_payloads);
+        start = end;
+    }
+}
+
+/// Sorts one chunk of keys and returns, for every sorted position, the
+/// position its key had before.
+fn sort_chunk<K: Clone + Ord>(keys: &mut [K]) -> Vec<usize> {
+    let mut pairs: Vec<(K, usize)> = keys.iter().cloned().zip(0..).collect();
+    pairs.sort_unstable_by(|a, b| a.0.cmp(&b.0));
+    pairs
+        .into_iter()
+        .zip(keys.iter_mut())
+        .map(|((key, index), k)| {
+            *k = key;
+            index
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::testing::{edge_cases, std_sorted};
+
+    #[test]
+    fn payloads_follow_their_keys() {
+        for p in [1, 4, 7] {
+            for (name, values) in edge_cases(p) {
+                let mut keys: Vec<u32> = values.iter().map(|&v| (v % 100_000) as u32).collect();
+                // Each payload names its key, so a payload that lost its
+                // key shows up as a mismatched pair.
+                let mut payloads: Vec<u64> = keys.iter().map(|&k| u64::from(k) << 32).collect();
+                let expected = std_sorted(&keys);
+                psrs_pairs(&mut keys, &mut payloads, p);
+                assert_eq!(keys, expected, "{name}, p = {p}");
+                assert!(keys.iter().zip(&payloads).all(|(&k, &v)| v >> 32 == u64::from(k)), "{name}, p = {p}");
+            }
+        }
+    }
+
+    #[test]
+    fn payloads_are_a_permutation() {
+        let mut keys: Vec<u8> = crate::testing::random_values(20_000, 4, 7).iter().map(|&v| v as u8).collect();
+        let mut payloads: Vec<usize> = (0..keys.len()).collect();
+        let original = keys.clone();
+        psrs_pairs(&mut keys, &mut payloads, 6);
+        assert!(keys.is_sorted());
+        assert!(payloads.iter().zip(&keys).all(|(&i, &k)| original[i] == k));
+        assert_eq!(std_sorted(&payloads), (0..keys.len()).collect::<Vec<_>>());
+    }
+}
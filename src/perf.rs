@@ -0,0 +1,94 @@
+//! Hardware performance counters around each harness run, with the `perf`
+//! feature on Linux. Elsewhere [`start`] always returns `None`.
+
+use serde::{Deserialize, Serialize};
+
+/// Hardware events counted during one run, summed over every thread of the
+/// process (user space only).
+#[derive(Debug, Clone, Copy, Default, PartialEq, Serialize, Deserialize)]
+pub struct Counters {
+    pub instructions: u64,
+    pub cache_misses: u64,
+    pub branch_misses: u64,
+}
+
+impl Counters {
+    /// Mean of `counters`, or `None` if there are none.
+    pub fn mean(counters: &[Counters]) -> Option<Counters> {
+        let n = counters.len() as u64;
+        (n > 0).then(|| Counters {
+            instructions: counters.iter().map(|c| c.instructions).sum::<u64>() / n,
+            cache_misses: counters.iter().map(|c| c.cache_misses).sum::<u64>() / n,
+            branch_misses: counters.iter().map(|c| c.branch_misses).sum::<u64>() / n,
+        })
+    }
+}
+
+/// Counters opened on every thread of the process, see [`start`].
+#[cfg(all(feature = "perf", target_os = "linux"))]
+pub struct Session {
+    /// Instructions, cache misses and branch misses of each thread.
+    threads: Vec<[perf_event::Counter; 3]>,
+}
+
+#[cfg(not(all(feature = "perf", target_os = "linux")))]
+pub struct Session;
+
+/// Starts counting on every thread currently in the process (the harness
+/// thread and the worker pools) and on the threads they spawn. Returns
+/// `None`, after warning once, if the kernel refuses, e.g. because of
+/// `perf_event_paranoid` or a seccomp filter.
+#[cfg(all(feature = "perf", target_os = "linux"))]
+pub fn start() -> Option<Session> {
+    use perf_event::events::Hardware;
+    use perf_event::Builder;
+
+    let open = || -> std::io::Result<Session> {
+        let mut threads = Vec::new();
+        for task in std::fs::read_dir("/proc/self/task")? {
+            let Some(tid) = task?.file_name().to_str().and_then(|t| t.parse().ok()) else { continue };
+            let counter = |event| Builder::new(event).observe_pid(tid).exclude_kernel(true).inherit(true).build();
+            threads.push([
+                counter(Hardware::INSTRUCTIONS)?,
+                counter(Hardware::CACHE_MISSES)?,
+                counter(Hardware::BRANCH_MISSES)?,
+            ]);
+        }
+        for counter in threads.iter_mut().flatten() {
+            counter.enable()?;
+        }
+        Ok(Session { threads })
+    };
+    open()
+        .map_err(|e| {
+            static WARNED: std::sync::Once = std::sync::Once::new();
+            WARNED.call_once(|| eprintln!("performance counters unavailable: {e}"));
+        })
+        .ok()
+}
+
+#[cfg(not(all(feature = "perf", target_os = "linux")))]
+pub fn start() -> Option<Session> {
+    None
+}
+
+impl Session {
+    /// Stops counting and sums the counts over all threads.
+    #[cfg(all(feature = "perf", target_os = "linux"))]
+    pub fn finish(mut self) -> Option<Counters> {
+        let mut totals = [0; 3];
+        for counters in &mut self.threads {
+            for (total, counter) in totals.iter_mut().zip(counters) {
+                counter.disable().ok()?;
+                *total += counter.read().ok()?;
+            }
+        }
+        let [instructions, cache_misses, branch_misses] = totals;
+        Some(Counters { instructions, cache_misses, branch_misses })
+    }
+
+    #[cfg(not(all(feature = "perf", target_os = "linux")))]
+    pub fn finish(self) -> Option<Counters> {
+        None
+    }
+}
@@ -0,0 +1,368 @@
+//! The PSRS phases as standalone building blocks.
+//!
+//! With `chunk_len = n / p`, [`psrs`](crate::psrs) amounts to running
+//! [`local_sort`] on the data, then [`select_pivots`] and
+//! [`compute_boundaries`] on `data.chunks(chunk_len)`, then
+//! [`merge_partitions`] and concatenating the partitions it returns. Any
+//! phase can be replaced by another that keeps the contract documented on
+//! it, e.g. to try a different pivot strategy, and the later phases work on
+//! any chunks that are already sorted, wherever they came from.
+//!
+//! Every phase runs on the [`default_executor`].
+
+use std::cmp::Ordering;
+use std::ops::Range;
+
+use allocator_api2::alloc::{Allocator, Global};
+use allocator_api2::vec::Vec as AVec;
+
+use crate::executor::{self, default_executor, Executor};
+use crate::local_sort::unstable_sort;
+use crate::{merge, MergeStructure};
+
+/// Phase 1: sorts every `chunk_len` chunk of `data` in parallel.
+pub fn local_sort<T: Ord + Send>(data: &mut [T], chunk_len: usize) {
+    executor::par_chunks_mut_map(default_executor(), data, chunk_len.max(1), |_, chunk| unstable_sort(chunk));
+}
+
+/// Phase 2: picks `p - 1` pivots by regular sampling from sorted `chunks`.
+/// The pivots are in ascending order and may repeat.
+pub fn select_pivots<T: Clone + Ord + Send + Sync>(chunks: &[&[T]], p: usize) -> Vec<T> {
+    sample_pivots(default_executor(), chunks, p, 1, &[], &unstable_sort::<T>, Global)
+}
+
+/// Phase 3: splits every sorted chunk at ascending `pivots`. Returns, per
+/// chunk, `pivots.len() + 2` non-decreasing offsets starting at 0 and ending
+/// at the chunk length; partition `i` of a chunk is `b[i]..b[i + 1]`.
+///
+/// Elements equal to a pivot may end up on either side of it: runs of them
+/// are spread over adjacent partitions to keep partition sizes even.
+pub fn compute_boundaries<T: Ord + Sync>(chunks: &[&[T]], pivots: &[T]) -> Vec<Vec<usize>> {
+    partition_boundaries(default_executor(), chunks, pivots, &T::cmp, &[], Global)
+        .rows()
+        .map(<[usize]>::to_vec)
+        .collect()
+}
+
+/// Phase 4: k-way merges partition `i` of every chunk into one sorted
+/// partition, for each `i`, given `boundaries` as returned by
+/// [`compute_boundaries`]. Concatenated, the partitions are the sorted input.
+pub fn merge_partitions<T: Clone + Ord + Send + Sync>(chunks: &[&[T]], boundaries: &[Vec<usize>]) -> Vec<Vec<T>> {
+    let partitions = boundaries.first().map_or(0, |b| b.len().saturating_sub(1));
+    executor::par_map(default_executor(), partitions, |part_idx| {
+        let slices: Vec<&[T]> = chunks
+            .iter()
+            .zip(boundaries)
+            .map(|(chunk, b)| &chunk[b[part_idx]..b[part_idx + 1]])
+            .collect();
+        let mut merged = Vec::with_capacity(slices.iter().map(|s| s.len()).sum());
+        merge::merge_into(&slices, &T::cmp, MergeStructure::default(), None, &mut |x| merged.push(x))
+            .expect("merge without a token cannot fail");
+        merged
+    })
+}
+
+/// Where each of `p` partitions should end for shares of `n` in proportion
+/// to `weights`, cycled over the partitions, or for equal shares when
+/// `weights` is empty.
+pub(crate) fn share_ends(n: usize, p: usize, weights: &[u32]) -> Vec<usize> {
+    let weight = |i: usize| weights.get(i % weights.len().max(1)).map_or(1, |&w| w.max(1) as u128);
+    let total: u128 = (0..p).map(weight).sum();
+    let mut cumulative = 0;
+    (0..p)
+        .map(|i| {
+            cumulative += weight(i);
+            (n as u128 * cumulative / total) as usize
+        })
+        .collect()
+}
+
+/// Regular sampling: `p * oversampling` samples from each sorted chunk,
+/// sorted with `local_sort`, and every `p * oversampling`-th of those as one
+/// of the `p - 1` pivots.
+///
+/// With `weights`, the chunks and the partitions to come are sized in
+/// proportion to them instead (see [`share_ends`]): each chunk then gives
+/// samples in proportion to its length, and the pivots are the samples at
+/// the partitions' cumulative shares.
+pub(crate) fn sample_pivots<T, S, A>(
+    exec: &dyn Executor,
+    chunks: &[&[T]],
+    p: usize,
+    oversampling: usize,
+    weights: &[u32],
+    local_sort: &S,
+    alloc: A,
+) -> Vec<T>
+where
+    T: Clone + Send + Sync,
+    S: Fn(&mut [T]) + Sync,
+    A: Allocator + Clone + Send + Sync,
+{
+    if !weights.is_empty() {
+        return sample_weighted_pivots(exec, chunks, p, oversampling, weights, local_sort, alloc);
+    }
+    let per_chunk = p * oversampling.max(1);
+    // Assign a chunk to each thread
+    let local_samples: Vec<AVec<T, A>> = executor::par_map(exec, chunks.len(), |chunk_idx| {
+        let chunk = chunks[chunk_idx];
+        let m = chunk.len();
+        let omega = m / per_chunk;
+
+        // Each thread gathers its respective local samples from its chunk
+        let mut local = AVec::with_capacity_in(per_chunk, alloc.clone());
+        local.extend((0..per_chunk).map(|i| {
+            // Choose index; ensure we don’t go out-of-bounds.
+            let idx = if i * omega + 1 < m { i * omega + 1 } else { m - 1 };
+            chunk[idx].clone()
+        }));
+        local
+    });
+    let mut samples = AVec::with_capacity_in(local_samples.len() * per_chunk, alloc.clone());
+    for local in local_samples {
+        samples.extend(local);
+    }
+
+    // The main thread sorts the local samples
+    {
+        span!("sample_sort", samples = samples.len());
+        local_sort(&mut samples);
+    }
+
+    // Choose p-1 pivots.
+    (1..p).map(|i| samples[i * per_chunk].clone()).collect()
+}
+
+/// [`sample_pivots`] for chunks and partitions sized by `weights`.
+fn sample_weighted_pivots<T, S, A>(
+    exec: &dyn Executor,
+    chunks: &[&[T]],
+    p: usize,
+    oversampling: usize,
+    weights: &[u32],
+    local_sort: &S,
+    alloc: A,
+) -> Vec<T>
+where
+    T: Clone + Send + Sync,
+    S: Fn(&mut [T]) + Sync,
+    A: Allocator + Clone + Send + Sync,
+{
+    let n: usize = chunks.iter().map(|c| c.len()).sum();
+    let total = p * p * oversampling.max(1);
+    let local_samples: Vec<AVec<T, A>> = executor::par_map(exec, chunks.len(), |chunk_idx| {
+        let chunk = chunks[chunk_idx];
+        let m = chunk.len();
+        let count = ((total as u128 * m as u128).div_ceil(n as u128) as usize).min(m);
+        let mut local = AVec::with_capacity_in(count, alloc.clone());
+        local.extend((0..count).map(|k| chunk[k * m / count].clone()));
+        local
+    });
+    let mut samples = AVec::with_capacity_in(local_samples.iter().map(|l| l.len()).sum(), alloc.clone());
+    for local in local_samples {
+        samples.extend(local);
+    }
+    {
+        span!("sample_sort", samples = samples.len());
+        local_sort(&mut samples);
+    }
+    let ends = share_ends(n, p, weights);
+    let at = |end: usize| ((samples.len() as u128 * end as u128 / n as u128) as usize).min(samples.len() - 1);
+    ends[..p - 1].iter().map(|&end| samples[at(end)].clone()).collect()
+}
+
+/// Bytes per cache line, which every row of a [`Boundaries`] buffer is
+/// padded to.
+const CACHE_LINE: usize = 64;
+
+/// Number of `T` that pads a row of `len` of them to whole cache lines.
+fn padded_len<T>(len: usize) -> usize {
+    len.next_multiple_of((CACHE_LINE / std::mem::size_of::<T>()).max(1))
+}
+
+/// The partition boundaries of every chunk in one flat buffer: row `c`
+/// holds chunk `c`'s `p + 1` offsets, padded to whole cache lines so the
+/// tasks filling neighbouring rows never share one.
+pub(crate) struct Boundaries<A: Allocator> {
+    offsets: AVec<usize, A>,
+    /// Offsets per row, `p + 1`.
+    len: usize,
+    /// Distance between the starts of two rows.
+    stride: usize,
+}
+
+impl<A: Allocator> Boundaries<A> {
+    /// The offsets of every chunk, in chunk order; partition `i` of a chunk
+    /// is `row[i]..row[i + 1]`.
+    pub(crate) fn rows(&self) -> impl Iterator<Item = &[usize]> {
+        self.offsets.chunks(self.stride).map(|row| &row[..self.len])
+    }
+}
+
+/// Partition boundaries of every chunk for ascending `pivots`, see
+/// [`compute_boundaries`]. Runs of ties are split towards partitions sized
+/// by `weights`, as for [`share_ends`].
+pub(crate) fn partition_boundaries<T, F, A>(
+    exec: &dyn Executor,
+    chunks: &[&[T]],
+    pivots: &[T],
+    compare: &F,
+    weights: &[u32],
+    alloc: A,
+) -> Boundaries<A>
+where
+    T: Sync,
+    F: Fn(&T, &T) -> Ordering + Sync,
+    A: Allocator + Clone + Send + Sync,
+{
+    // With few distinct values many pivots coincide, so search for each
+    // value once and remember which one every boundary splits at.
+    let mut distinct: Vec<&T> = Vec::with_capacity(pivots.len());
+    let mut pivot_of_boundary = Vec::with_capacity(pivots.len());
+    for pivot in pivots {
+        if distinct.last().is_none_or(|last| compare(last, pivot) != Ordering::Equal) {
+            distinct.push(pivot);
+        }
+        pivot_of_boundary.push(distinct.len() - 1);
+    }
+
+    // Elements equal to a pivot sort the same on either side of it, so only
+    // the range of such elements is located here; `split_ties` places the
+    // boundaries inside it. Each chunk's task fills its own padded row.
+    let stride = padded_len::<Range<usize>>(distinct.len()).max(1);
+    let mut equal_ranges = AVec::new_in(alloc.clone());
+    equal_ranges.resize(chunks.len() * stride, 0..0);
+    executor::par_chunks_mut_map(exec, &mut equal_ranges, stride, |chunk_idx, row| {
+        let chunk = chunks[chunk_idx];
+        for (range, pivot) in row.iter_mut().zip(&distinct) {
+            let lo = chunk.partition_point(|x| compare(x, pivot) == Ordering::Less);
+            let hi = lo + chunk[lo..].partition_point(|x| compare(x, pivot) != Ordering::Greater);
+            *range = lo..hi;
+        }
+    });
+    split_ties(chunks, &equal_ranges, stride, &pivot_of_boundary, weights, alloc)
+}
+
+/// Places every chunk's partition boundaries given, per chunk and distinct
+/// pivot, the range of elements equal to that pivot (row `c` of
+/// `equal_ranges`, `stride` apart, for chunk `c`), and the pivot each of
+/// the `p - 1` boundaries splits at. The boundary after partition `i` takes
+/// elements from the equal ranges, chunk by chunk, until the first `i + 1`
+/// partitions hold as close to their share of `n` (`(i + 1) * n / p` for
+/// equal `weights`) as the ranges allow, so a run of duplicates is spread
+/// over adjacent partitions instead of landing in a single one.
+fn split_ties<T, A: Allocator + Clone>(
+    chunks: &[&[T]],
+    equal_ranges: &[Range<usize>],
+    stride: usize,
+    pivot_of_boundary: &[usize],
+    weights: &[u32],
+    alloc: A,
+) -> Boundaries<A> {
+    let n: usize = chunks.iter().map(|c| c.len()).sum();
+    let p = pivot_of_boundary.len() + 1;
+    let mut below = vec![0; stride];
+    for row in equal_ranges.chunks(stride) {
+        for (sum, range) in below.iter_mut().zip(row) {
+            *sum += range.start;
+        }
+    }
+    // Elements each boundary still takes from the equal ranges of the
+    // chunks not visited yet. Filling greedily from the first chunk keeps
+    // each chunk's boundaries non-decreasing when consecutive pivots are
+    // equal.
+    let mut extra: Vec<usize> = pivot_of_boundary
+        .iter()
+        .zip(share_ends(n, p, weights))
+        .map(|(&pivot, target)| target.saturating_sub(below[pivot]))
+        .collect();
+
+    let row_stride = padded_len::<usize>(p + 1);
+    let mut offsets = AVec::new_in(alloc);
+    offsets.resize(chunks.len() * row_stride, 0);
+    for ((row, ranges), chunk) in offsets.chunks_mut(row_stride).zip(equal_ranges.chunks(stride)).zip(chunks) {
+        for ((slot, &pivot), extra) in row[1..p].iter_mut().zip(pivot_of_boundary).zip(&mut extra) {
+            let range = &ranges[pivot];
+            let take = (*extra).min(range.len());
+            *extra -= take;
+            *slot = range.start + take;
+        }
+        row[p] = chunk.len();
+    }
+    Boundaries { offsets, len: p + 1, stride: row_stride }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::testing::{edge_cases, std_sorted};
+
+    /// Sorts `data` by running the four phases by hand, as the module docs
+    /// describe.
+    fn sort_by_phases(data: &mut [u64], p: usize) -> Vec<Vec<u64>> {
+        let chunk_len = (data.len() / p).max(1);
+        local_sort(data, chunk_len);
+        let chunks: Vec<&[u64]> = data.chunks(chunk_len).collect();
+        let pivots = select_pivots(&chunks, p);
+        let boundaries = compute_boundaries(&chunks, &pivots);
+        merge_partitions(&chunks, &boundaries)
+    }
+
+    #[test]
+    fn phases_compose_into_a_sort() {
+        for p in [1, 4, 7] {
+            for (name, mut data) in edge_cases(p) {
+                if data.len() < p {
+                    continue;
+                }
+                let expected = std_sorted(&data);
+                let partitions = sort_by_phases(&mut data, p);
+                assert_eq!(partitions.len(), p, "{name}, p = {p}");
+                assert_eq!(partitions.concat(), expected, "{name}, p = {p}");
+            }
+        }
+    }
+
+    /// Sizes of the partitions `boundaries` cut `chunks` into.
+    fn partition_sizes(boundaries: &[Vec<usize>]) -> Vec<usize> {
+        let p = boundaries[0].len() - 1;
+        (0..p).map(|i| boundaries.iter().map(|b| b[i + 1] - b[i]).sum()).collect()
+    }
+
+    #[test]
+    fn ties_are_split_across_partitions() {
+        for p in [2, 4, 7] {
+            // One value throughout, and one value making up most of the input.
+            let mostly_equal: Vec<u64> = (0..7_000).map(|i| if i % 10 == 0 { i } else { 500 }).collect();
+            for (name, mut data) in [("all equal", vec![42u64; 7_000]), ("mostly equal", mostly_equal)] {
+                let chunk_len = data.len() / p;
+                local_sort(&mut data, chunk_len);
+                let chunks: Vec<&[u64]> = data.chunks(chunk_len).collect();
+                let boundaries = compute_boundaries(&chunks, &select_pivots(&chunks, p));
+                for (b, chunk) in boundaries.iter().zip(&chunks) {
+                    assert!(b.is_sorted() && b[0] == 0 && b[p] == chunk.len(), "{name}, p = {p}: {b:?}");
+                }
+                let sizes = partition_sizes(&boundaries);
+                let (largest, share) = (*sizes.iter().max().unwrap(), data.len() / p);
+                assert!(largest <= share + share / 4, "{name}, p = {p}: {sizes:?}");
+                assert_eq!(merge_partitions(&chunks, &boundaries).concat(), std_sorted(&data), "{name}, p = {p}");
+            }
+        }
+    }
+
+    #[test]
+    fn repeated_pivots_give_ordered_boundaries() {
+        let p = 16;
+        for distinct in [1, 2, 3, 5] {
+            let mut data = crate::testing::random_values(16_000, distinct, 11);
+            let chunk_len = data.len() / p;
+            local_sort(&mut data, chunk_len);
+            let chunks: Vec<&[u64]> = data.chunks(chunk_len).collect();
+            let pivots = select_pivots(&chunks, p);
+            assert!(pivots.windows(2).any(|w| w[0] == w[1]), "{distinct} values must repeat pivots");
+            let boundaries = compute_boundaries(&chunks, &pivots);
+            assert!(boundaries.iter().all(|b| b.len() == p + 1 && b.is_sorted()), "{distinct} values");
+            assert_eq!(merge_partitions(&chunks, &boundaries).concat(), std_sorted(&data), "{distinct} values");
+        }
+    }
+}
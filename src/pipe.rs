@@ -0,0 +1,132 @@
+//! `pipe` subcommand: sorts numbers from stdin to stdout, for use in shell
+//! pipelines in place of `sort -n`.
+//!
+//! Text input is one number per line; lines come out unchanged, ordered by
+//! their value, with lines that are not numbers last. Binary input is a
+//! stream of little-endian fixed-width values, sorted as that type.
+
+use std::io::{self, BufWriter, Read, Write};
+
+use psrs::{psrs_by, psrs_f64, psrs_radix};
+use rayon::prelude::*;
+
+/// Element type of `--binary` input.
+#[derive(Clone, Copy)]
+enum Binary {
+    U32,
+    U64,
+    I32,
+    I64,
+    F64,
+}
+
+/// Options of `pipe`, see [`usage`].
+struct PipeArgs {
+    binary: Option<Binary>,
+    reverse: bool,
+    threads: usize,
+}
+
+fn usage() -> ! {
+    eprintln!(
+        "usage: pipe [--binary u32|u64|i32|i64|f64] [--reverse] [--threads N]\n\
+         Sorts stdin to stdout. Text input has one number per line and its lines are \
+         written back unchanged; lines that are not numbers go last. With --binary the \
+         input is little-endian values of that type."
+    );
+    std::process::exit(2)
+}
+
+fn parse_args(mut args: impl Iterator<Item = String>) -> PipeArgs {
+    let mut parsed = PipeArgs {
+        binary: None,
+        reverse: false,
+        threads: std::thread::available_parallelism().map_or(1, |n| n.get()),
+    };
+    while let Some(arg) = args.next() {
+        let mut value = || args.next().unwrap_or_else(|| usage());
+        match arg.as_str() {
+            "--binary" => {
+                parsed.binary = Some(match value().as_str() {
+                    "u32" => Binary::U32,
+                    "u64" => Binary::U64,
+                    "i32" => Binary::I32,
+                    "i64" => Binary::I64,
+                    "f64" => Binary::F64,
+                    _ => usage(),
+                })
+            }
+            "-r" | "--reverse" => parsed.reverse = true,
+            "--threads" => parsed.threads = value().parse().unwrap_or_else(|_| usage()),
+            _ => usage(),
+        }
+    }
+    parsed
+}
+
+/// Runs `pipe` with the arguments that follow the subcommand name.
+pub fn main(args: impl Iterator<Item = String>) {
+    let args = parse_args(args);
+    let mut input = Vec::new();
+    io::stdin().lock().read_to_end(&mut input).expect("failed to read stdin");
+    let mut output = BufWriter::new(io::stdout().lock());
+    match args.binary {
+        None => sort_lines(&input, &args, &mut output),
+        Some(Binary::U32) => sort_binary(&input, &args, &mut output, u32::from_le_bytes, u32::to_le_bytes, psrs_radix),
+        Some(Binary::U64) => sort_binary(&input, &args, &mut output, u64::from_le_bytes, u64::to_le_bytes, psrs_radix),
+        Some(Binary::I32) => sort_binary(&input, &args, &mut output, i32::from_le_bytes, i32::to_le_bytes, psrs_radix),
+        Some(Binary::I64) => sort_binary(&input, &args, &mut output, i64::from_le_bytes, i64::to_le_bytes, psrs_radix),
+        Some(Binary::F64) => sort_binary(&input, &args, &mut output, f64::from_le_bytes, f64::to_le_bytes, psrs_f64),
+    }
+    output.flush().expect("failed to write stdout");
+}
+
+/// Sorts the lines of `input` by their numeric value. Only `(value, line
+/// index)` pairs go through PSRS; the index breaks ties, so equal values
+/// keep their input order.
+fn sort_lines(input: &[u8], args: &PipeArgs, output: &mut impl Write) {
+    let input = input.strip_suffix(b"\n").unwrap_or(input);
+    let lines: Vec<&[u8]> = if input.is_empty() { Vec::new() } else { input.split(|&b| b == b'\n').collect() };
+    let mut keyed: Vec<(f64, usize)> = lines.par_iter().enumerate().map(|(i, line)| (numeric_key(line), i)).collect();
+    psrs_by(&mut keyed, args.threads, |a, b| a.0.total_cmp(&b.0).then(a.1.cmp(&b.1)));
+    if args.reverse {
+        // Lines that are not numbers stay last.
+        let numbers = keyed.partition_point(|(key, _)| !key.is_nan());
+        keyed[..numbers].reverse();
+    }
+    for (_, i) in keyed {
+        output.write_all(lines[i]).and_then(|()| output.write_all(b"\n")).expect("failed to write stdout");
+    }
+}
+
+/// Parses a line as a number. Anything else, including lines spelling out
+/// a NaN, becomes a positive NaN, which sorts after every number.
+fn numeric_key(line: &[u8]) -> f64 {
+    let key = std::str::from_utf8(line).ok().and_then(|text| text.trim().parse().ok()).unwrap_or(f64::NAN);
+    if key.is_nan() { f64::NAN } else { key }
+}
+
+/// Decodes `input` as `N`-byte values, sorts them with `sort` and writes
+/// them back in the same encoding.
+fn sort_binary<T: Copy + Send + Sync, const N: usize>(
+    input: &[u8],
+    args: &PipeArgs,
+    output: &mut impl Write,
+    decode: fn([u8; N]) -> T,
+    encode: fn(T) -> [u8; N],
+    sort: fn(&mut [T], usize),
+) {
+    if !input.len().is_multiple_of(N) {
+        eprintln!("input is {} bytes, not a whole number of {N}-byte values", input.len());
+        std::process::exit(1);
+    }
+    let mut values: Vec<T> =
+        input.par_chunks_exact(N).map(|bytes| decode(bytes.try_into().expect("chunk of N bytes"))).collect();
+    sort(&mut values, args.threads);
+    if args.reverse {
+        values.reverse();
+    }
+    for value in values {
+        output.write_all(&encode(value)).expect("failed to write stdout");
+    }
+}
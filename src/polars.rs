@@ -0,0 +1,109 @@
+//! Polars helpers that sort numeric `Series` with PSRS instead of Polars'
+//! built-in sort, e.g. for benchmarking inside dataframe workloads.
+//!
+//! Like Polars' default `SortOptions`, results are ascending with nulls
+//! first. Floats are ordered by `f64::total_cmp`.
+
+use polars::prelude::*;
+use polars::series::IsSorted;
+
+use crate::{f64_bits_to_key, psrs_f64, psrs_radix};
+
+/// Returns a sorted copy of `series`, which must be `UInt32`, `UInt64` or `Float64`.
+pub fn sort_series(series: &Series, p: usize) -> PolarsResult<Series> {
+    match series.dtype() {
+        DataType::UInt32 => Ok(sort_chunked(series.u32()?, p, psrs_radix).into_series()),
+        DataType::UInt64 => Ok(sort_chunked(series.u64()?, p, psrs_radix).into_series()),
+        DataType::Float64 => Ok(sort_chunked(series.f64()?, p, psrs_f64).into_series()),
+        dt => polars_bail!(opq = psrs_sort, dt),
+    }
+}
+
+/// Returns the indices that would sort `series`, null slots first.
+///
+/// Equal values keep their original relative order.
+pub fn arg_sort_series(series: &Series, p: usize) -> PolarsResult<IdxCa> {
+    match series.dtype() {
+        DataType::UInt32 => arg_sort_chunked(series.u32()?, p, |v| v as u64),
+        DataType::UInt64 => arg_sort_chunked(series.u64()?, p, |v| v),
+        DataType::Float64 => arg_sort_chunked(series.f64()?, p, |v| f64_bits_to_key(v.to_bits())),
+        dt => polars_bail!(opq = psrs_arg_sort, dt),
+    }
+}
+
+fn sort_chunked<T: PolarsNumericType>(
+    ca: &ChunkedArray<T>,
+    p: usize,
+    sort: fn(&mut [T::Native], usize),
+) -> ChunkedArray<T> {
+    let nulls = ca.null_count();
+    let mut values: Vec<T::Native> = ca.iter().flatten().collect();
+    sort(&mut values, p);
+
+    let mut out = if nulls == 0 {
+        ChunkedArray::from_vec(ca.name().clone(), values)
+    } else {
+        let iter = std::iter::repeat_n(None, nulls).chain(values.into_iter().map(Some));
+        ChunkedArray::from_iter_options(ca.name().clone(), iter)
+    };
+    out.set_sorted_flag(IsSorted::Ascending);
+    out
+}
+
+fn arg_sort_chunked<T: PolarsNumericType>(
+    ca: &ChunkedArray<T>,
+    p: usize,
+    key: fn(T::Native) -> u64,
+) -> PolarsResult<IdxCa> {
+    polars_ensure!(
+        ca.len() <= IdxSize::MAX as usize,
+        ComputeError: "series too long for the index type"
+    );
+    let mut indices: Vec<IdxSize> = Vec::with_capacity(ca.len());
+    let mut pairs = Vec::with_capacity(ca.len() - ca.null_count());
+    for (i, value) in ca.iter().enumerate() {
+        match value {
+            Some(v) => pairs.push((key(v), i as IdxSize)),
+            None => indices.push(i as IdxSize),
+        }
+    }
+
+    // The index breaks ties, which also makes the result stable.
+    psrs_radix(&mut pairs, p);
+    indices.extend(pairs.into_iter().map(|(_, i)| i));
+    Ok(IdxCa::from_vec(ca.name().clone(), indices))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::testing::edge_cases;
+
+    #[test]
+    fn series_sorts_match_a_stable_sort_with_nulls_first() {
+        for p in [1, 4, 7] {
+            for (name, values) in edge_cases(p) {
+                let options: Vec<Option<u64>> =
+                    values.iter().map(|&v| (v % 7 != 0).then_some(v % 1_000)).collect();
+                let series = Series::new("x".into(), &options);
+
+                let mut expected = options.clone();
+                expected.sort();
+                let sorted = sort_series(&series, p).unwrap();
+                assert_eq!(sorted.u64().unwrap().iter().collect::<Vec<_>>(), expected, "{name}, p = {p}");
+
+                let mut order: Vec<IdxSize> = (0..options.len() as IdxSize).collect();
+                order.sort_by_key(|&i| options[i as usize]);
+                let indices = arg_sort_series(&series, p).unwrap();
+                assert_eq!(indices.into_no_null_iter().collect::<Vec<_>>(), order, "{name}, p = {p}");
+            }
+        }
+    }
+
+    #[test]
+    fn unsupported_types_are_errors() {
+        let series = Series::new("x".into(), &[1i32, 2]);
+        assert!(sort_series(&series, 2).is_err());
+        assert!(arg_sort_series(&series, 2).is_err());
+    }
+}
@@ -0,0 +1,166 @@
+use std::cmp::Ordering;
+use std::sync::Mutex;
+
+use crate::executor::{self, Executor};
+
+/// Order an input already has, as found by [`detect`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(crate) enum Presorted {
+    Ascending,
+    Descending,
+    Unsorted,
+}
+
+/// Checks in parallel whether `data` is already non-decreasing or
+/// non-increasing under `compare`. Each chunk scans its own pairs, stopping
+/// at the first pair that rules out both orders; the pairs straddling chunk
+/// edges are checked afterwards.
+pub(crate) fn detect<T, F>(exec: &dyn Executor, data: &[T], compare: &F) -> Presorted
+where
+    T: Sync,
+    F: Fn(&T, &T) -> Ordering + Sync,
+{
+    let chunk_size = executor::even_chunk_size(exec, data.len());
+    let chunks = executor::par_chunks_map(exec, data, chunk_size, |_, chunk| order_of(chunk, compare));
+    let edges = data.chunks(chunk_size).zip(data.chunks(chunk_size).skip(1));
+    let pairs = edges.map(|(a, b)| {
+        let order = compare(a.last().unwrap(), &b[0]);
+        (order != Ordering::Greater, order != Ordering::Less)
+    });
+    let (ascending, descending) = chunks
+        .into_iter()
+        .chain(pairs)
+        .fold((true, true), |(asc, desc), (a, d)| (asc && a, desc && d));
+    match (ascending, descending) {
+        (true, _) => Presorted::Ascending,
+        (false, true) => Presorted::Descending,
+        (false, false) => Presorted::Unsorted,
+    }
+}
+
+/// Sorts one Phase 1 chunk with `local_sort`, unless it already is a
+/// natural run: an ascending chunk is left as it is and a descending one
+/// reversed. The scan stops at the first pair ruling out both, which on
+/// unsorted chunks comes within a few elements.
+pub(crate) fn sort_chunk<T, F, S>(chunk: &mut [T], compare: &F, local_sort: &S)
+where
+    F: Fn(&T, &T) -> Ordering,
+    S: Fn(&mut [T]),
+{
+    match order_of(chunk, compare) {
+        (true, _) => {}
+        (false, true) => chunk.reverse(),
+        (false, false) => local_sort(chunk),
+    }
+}
+
+/// Whether `slice` is non-decreasing and whether it is non-increasing.
+fn order_of<T, F: Fn(&T, &T) -> Ordering>(slice: &[T], compare: &F) -> (bool, bool) {
+    let (mut ascending, mut descending) = (true, true);
+    for w in slice.windows(2) {
+        match compare(&w[0], &w[1]) {
+            Ordering::Less => descending = false,
+            Ordering::Greater => ascending = false,
+            Ordering::Equal => {}
+        }
+        if !ascending && !descending {
+            break;
+        }
+    }
+    (ascending, descending)
+}
+
+/// Reverses `data` in parallel by swapping matching chunks of its two halves.
+pub(crate) fn par_reverse<T: Send>(exec: &dyn Executor, data: &mut [T]) {
+    let n = data.len();
+    let half = n / 2;
+    let (left, right) = data.split_at_mut(half);
+    // An odd middle element stays where it is.
+    let right = &mut right[n % 2..];
+    let chunk_size = executor::even_chunk_size(exec, half);
+    let pairs: Vec<Mutex<(&mut [T], &mut [T])>> =
+        left.chunks_mut(chunk_size).zip(right.rchunks_mut(chunk_size)).map(Mutex::new).collect();
+    executor::par_map(exec, pairs.len(), |i| {
+        let (l, r) = &mut *pairs[i].lock().unwrap();
+        let len = l.len();
+        for j in 0..len {
+            std::mem::swap(&mut l[j], &mut r[len - 1 - j]);
+        }
+    });
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::executor::ScopedThreadExecutor;
+    use crate::testing::std_sorted;
+    use crate::PsrsConfig;
+
+    #[test]
+    fn detect_finds_presorted_inputs() {
+        let exec = ScopedThreadExecutor { threads: 4 };
+        let cases: [(&str, Vec<u64>, Presorted); 6] = [
+            ("empty", vec![], Presorted::Ascending),
+            ("all equal", vec![3; 100], Presorted::Ascending),
+            ("ascending", (0..1000).collect(), Presorted::Ascending),
+            ("descending", (0..1000).rev().collect(), Presorted::Descending),
+            // Only the pair straddling two chunks is out of order.
+            ("edge", (0..1000).map(|i| if i == 750 { 0 } else { i }).collect(), Presorted::Unsorted),
+            ("random", crate::testing::random_values(1000, 100, 14), Presorted::Unsorted),
+        ];
+        for (name, data, expected) in cases {
+            assert_eq!(detect(&exec, &data, &u64::cmp), expected, "{name}");
+        }
+    }
+
+    #[test]
+    fn par_reverse_matches_reverse() {
+        let exec = ScopedThreadExecutor { threads: 3 };
+        for n in [0, 1, 2, 5, 6, 1001] {
+            let mut data: Vec<usize> = (0..n).collect();
+            par_reverse(&exec, &mut data);
+            assert!(data.iter().rev().copied().eq(0..n), "n = {n}");
+        }
+    }
+
+    #[test]
+    fn presorted_inputs_skip_the_pipeline() {
+        let config = PsrsConfig { check_presorted: true, ..PsrsConfig::with_threads(4) };
+        let inputs: [(&str, Vec<u64>, bool); 3] = [
+            ("ascending", (0..100_000).collect(), false),
+            ("descending", (0..100_000).rev().collect(), false),
+            ("random", crate::testing::random_values(100_000, u64::MAX, 15), true),
+        ];
+        for (name, mut data, pipelined) in inputs {
+            let expected = std_sorted(&data);
+            let timeline = crate::Timeline::new();
+            crate::psrs_with_timeline(&mut data, &config, &timeline);
+            assert_eq!(data, expected, "{name}");
+            assert_eq!(timeline.events().iter().any(|e| e.task.is_some()), pipelined, "{name}");
+        }
+    }
+
+    #[test]
+    fn chunk_runs_skip_the_local_sort() {
+        let refuse = |_: &mut [u64]| panic!("a run must not be sorted again");
+        let mut ascending: Vec<u64> = vec![1, 2, 2, 5];
+        sort_chunk(&mut ascending, &u64::cmp, &refuse);
+        assert_eq!(ascending, [1, 2, 2, 5]);
+        let mut descending: Vec<u64> = vec![9, 4, 4, 0];
+        sort_chunk(&mut descending, &u64::cmp, &refuse);
+        assert_eq!(descending, [0, 4, 4, 9]);
+
+        let mut unsorted: Vec<u64> = vec![3, 1, 2];
+        sort_chunk(&mut unsorted, &u64::cmp, &|chunk: &mut [u64]| chunk.sort());
+        assert_eq!(unsorted, [1, 2, 3]);
+    }
+
+    #[test]
+    fn runs_and_sorted_chunks_merge_into_a_sort() {
+        // Alternating ascending and descending runs of uneven length.
+        let mut data: Vec<u64> = (0..20_000u64).map(|i| if (i / 3_000) % 2 == 0 { i } else { 40_000 - i }).collect();
+        let expected = std_sorted(&data);
+        crate::psrs(&mut data, 6);
+        assert_eq!(data, expected);
+    }
+}
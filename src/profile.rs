@@ -0,0 +1,125 @@
+//! Per-machine tuning profiles, as written by the harness's `tune`
+//! subcommand.
+
+use std::io;
+use std::path::{Path, PathBuf};
+use std::sync::OnceLock;
+
+use serde::{Deserialize, Serialize};
+
+use crate::{MergeStructure, PsrsConfig};
+
+/// Environment variable naming the profile [`TuningProfile::installed`]
+/// loads, instead of the one at [`TuningProfile::default_path`].
+pub const PROFILE_ENV: &str = "PSRS_PROFILE";
+
+/// The best settings measured on one machine, per thread count and input
+/// size. [`PsrsConfig::tuned_for`] consults the installed one.
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct TuningProfile {
+    pub entries: Vec<TunedEntry>,
+}
+
+/// The winning settings for inputs of `bytes` bytes on `threads` workers.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct TunedEntry {
+    pub threads: usize,
+    pub bytes: usize,
+    pub oversubscription: usize,
+    pub oversampling: usize,
+    pub merge: MergeStructure,
+    pub serial_cutoff: usize,
+}
+
+impl TuningProfile {
+    /// `$XDG_CACHE_HOME/psrs/profile.json`, or `~/.cache/psrs/profile.json`
+    /// when `XDG_CACHE_HOME` is not set.
+    pub fn default_path() -> Option<PathBuf> {
+        let cache = std::env::var_os("XDG_CACHE_HOME")
+            .map(PathBuf::from)
+            .or_else(|| std::env::var_os("HOME").map(|home| Path::new(&home).join(".cache")))?;
+        Some(cache.join("psrs").join("profile.json"))
+    }
+
+    /// The profile at `$PSRS_PROFILE`, or else at [`default_path`], loaded
+    /// on first use. `None` if there is none or it cannot be read.
+    ///
+    /// [`default_path`]: TuningProfile::default_path
+    pub fn installed() -> Option<&'static TuningProfile> {
+        static INSTALLED: OnceLock<Option<TuningProfile>> = OnceLock::new();
+        INSTALLED
+            .get_or_init(|| {
+                let path = std::env::var_os(PROFILE_ENV).map(PathBuf::from).or_else(TuningProfile::default_path)?;
+                TuningProfile::load(&path).ok()
+            })
+            .as_ref()
+    }
+
+    pub fn load(path: &Path) -> io::Result<TuningProfile> {
+        let text = std::fs::read_to_string(path)?;
+        serde_json::from_str(&text).map_err(|e| io::Error::new(io::ErrorKind::InvalidData, e))
+    }
+
+    /// Writes the profile as JSON, creating the parent directory if needed.
+    pub fn save(&self, path: &Path) -> io::Result<()> {
+        if let Some(dir) = path.parent() {
+            std::fs::create_dir_all(dir)?;
+        }
+        let json = serde_json::to_string_pretty(self).map_err(io::Error::other)?;
+        std::fs::write(path, json)
+    }
+
+    /// The config for sorting `bytes` bytes on `threads` workers: the
+    /// entry for the nearest measured thread count, and among those the
+    /// one whose size is nearest on a log scale. `None` if the profile is
+    /// empty.
+    pub fn config_for(&self, bytes: usize, threads: usize) -> Option<PsrsConfig> {
+        let log = |x: usize| (x.max(1) as f64).log2();
+        let entry = self.entries.iter().min_by(|a, b| {
+            let key = |e: &TunedEntry| (e.threads.abs_diff(threads), (log(e.bytes) - log(bytes)).abs());
+            let (ka, kb) = (key(a), key(b));
+            ka.0.cmp(&kb.0).then(ka.1.total_cmp(&kb.1))
+        })?;
+        Some(PsrsConfig {
+            oversubscription: entry.oversubscription,
+            oversampling: entry.oversampling,
+            merge: entry.merge,
+            serial_cutoff: entry.serial_cutoff,
+            ..PsrsConfig::with_threads(threads)
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::testing::scratch_dir;
+
+    fn entry(threads: usize, bytes: usize, oversubscription: usize) -> TunedEntry {
+        TunedEntry { threads, bytes, oversubscription, oversampling: 1, merge: MergeStructure::LoserTree, serial_cutoff: 0 }
+    }
+
+    #[test]
+    fn config_for_picks_the_nearest_entry() {
+        let profile = TuningProfile { entries: vec![entry(4, 1 << 20, 1), entry(4, 1 << 30, 4), entry(16, 1 << 30, 8)] };
+        let oversubscription = |bytes, threads| profile.config_for(bytes, threads).unwrap().oversubscription;
+        assert_eq!(oversubscription(1 << 22, 4), 1);
+        assert_eq!(oversubscription(1 << 27, 4), 4);
+        assert_eq!(oversubscription(1 << 20, 12), 8);
+        let config = profile.config_for(1 << 20, 6).unwrap();
+        assert_eq!((config.threads, config.merge), (6, MergeStructure::LoserTree));
+        assert_eq!(TuningProfile::default().config_for(1 << 20, 4), None);
+    }
+
+    #[test]
+    fn profiles_round_trip_through_json() {
+        let dir = scratch_dir("profile");
+        let path = dir.join("nested").join("profile.json");
+        let profile = TuningProfile { entries: vec![entry(8, 1 << 24, 2)] };
+        profile.save(&path).unwrap();
+        assert_eq!(TuningProfile::load(&path).unwrap(), profile);
+        std::fs::write(&path, "not json").unwrap();
+        assert_eq!(TuningProfile::load(&path).unwrap_err().kind(), io::ErrorKind::InvalidData);
+        std::fs::remove_dir_all(dir).unwrap();
+    }
+}
@@ -0,0 +1,83 @@
+/// The steps of a PSRS run, in the order they execute.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum Phase {
+    /// Optional scan for input that is already (reverse) sorted, see
+    /// [`PsrsConfig::check_presorted`](crate::PsrsConfig::check_presorted).
+    PresortedCheck,
+    /// Phase 1: every chunk is sorted on its own.
+    LocalSort,
+    /// Phase 2: regular samples are drawn and sorted to pick the pivots.
+    Sampling,
+    /// Phase 3: each chunk is split at the pivots.
+    Boundaries,
+    /// Phase 4: matching pieces of every chunk are k‑way merged.
+    Merge,
+    /// The merged partitions are copied back into the input.
+    Copy,
+}
+
+/// Event reported to the callback of [`psrs_with_progress`](crate::psrs_with_progress).
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Progress {
+    /// `phase` has started.
+    PhaseStarted(Phase),
+    /// `done` of the `total` work items of `phase` have completed: chunks in
+    /// [`Phase::LocalSort`], partitions in [`Phase::Merge`].
+    Completed { phase: Phase, done: usize, total: usize },
+    /// The data is fully sorted.
+    Finished,
+}
+
+impl Progress {
+    /// Completion of the current phase as a percentage, if it reports one.
+    pub fn percent(&self) -> Option<f64> {
+        match *self {
+            Progress::Completed { done, total, .. } => Some(100.0 * done as f64 / total as f64),
+            _ => None,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::psrs_with_progress;
+    use crate::testing::edge_cases;
+    use std::sync::Mutex;
+
+    #[test]
+    fn progress_reports_every_work_item() {
+        for p in [1, 4, 7] {
+            for (name, mut data) in edge_cases(p) {
+                let events = Mutex::new(Vec::new());
+                psrs_with_progress(&mut data, p, |event| events.lock().unwrap().push(event));
+
+                let events = events.into_inner().unwrap();
+                assert_eq!(events.first(), Some(&Progress::PhaseStarted(Phase::LocalSort)), "{name}, p = {p}");
+                assert_eq!(events.last(), Some(&Progress::Finished), "{name}, p = {p}");
+                for phase in [Phase::LocalSort, Phase::Merge] {
+                    let done: Vec<(usize, usize)> = events
+                        .iter()
+                        .filter_map(|e| match *e {
+                            Progress::Completed { phase: q, done, total } if q == phase => Some((done, total)),
+                            _ => None,
+                        })
+                        .collect();
+                    // Counts arrive from several workers, so only their set
+                    // is fixed: each of `1..=total` exactly once.
+                    let mut counts: Vec<usize> = done.iter().map(|&(d, _)| d).collect();
+                    counts.sort_unstable();
+                    let total = done.first().map_or(0, |&(_, t)| t);
+                    assert!(counts.iter().copied().eq(1..=total), "{name}, p = {p}, {phase:?}: {done:?}");
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn percent_only_for_completions() {
+        assert_eq!(Progress::Completed { phase: Phase::Merge, done: 1, total: 4 }.percent(), Some(25.0));
+        assert_eq!(Progress::PhaseStarted(Phase::Merge).percent(), None);
+        assert_eq!(Progress::Finished.percent(), None);
+    }
+}
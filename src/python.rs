@@ -0,0 +1,55 @@
+//! Python bindings: `psrs.sort(array, threads=N)` sorts a contiguous 1-D
+//! NumPy array of `uint32`, `uint64` or `float64` in place.
+
+use numpy::{Element, PyArray1, PyArrayMethods};
+use pyo3::exceptions::{PyTypeError, PyValueError};
+use pyo3::prelude::*;
+
+use crate::{psrs_f64, psrs_radix};
+
+/// Sorts `array` in place with PSRS, releasing the GIL while sorting.
+///
+/// `threads` sets both the number of PSRS chunks and the size of the worker
+/// pool; it defaults to the number of logical CPUs.
+#[pyfunction]
+#[pyo3(signature = (array, threads = None))]
+fn sort(py: Python<'_>, array: &Bound<'_, PyAny>, threads: Option<usize>) -> PyResult<()> {
+    let threads = threads.unwrap_or_else(rayon::current_num_threads);
+    if threads == 0 {
+        return Err(PyValueError::new_err("threads must be at least 1"));
+    }
+
+    if let Ok(array) = array.downcast::<PyArray1<u32>>() {
+        sort_array(py, array, threads, psrs_radix)
+    } else if let Ok(array) = array.downcast::<PyArray1<u64>>() {
+        sort_array(py, array, threads, psrs_radix)
+    } else if let Ok(array) = array.downcast::<PyArray1<f64>>() {
+        sort_array(py, array, threads, psrs_f64)
+    } else {
+        Err(PyTypeError::new_err(
+            "expected a 1-D numpy array of uint32, uint64 or float64",
+        ))
+    }
+}
+
+fn sort_array<T: Element + Send>(
+    py: Python<'_>,
+    array: &Bound<'_, PyArray1<T>>,
+    threads: usize,
+    sort: fn(&mut [T], usize),
+) -> PyResult<()> {
+    let mut array = array.try_readwrite()?;
+    // Fails for strided views instead of silently sorting a copy.
+    let data = array.as_slice_mut()?;
+    let pool = rayon::ThreadPoolBuilder::new()
+        .num_threads(threads)
+        .build()
+        .map_err(|e| PyValueError::new_err(e.to_string()))?;
+    py.allow_threads(|| pool.install(|| sort(data, threads)));
+    Ok(())
+}
+
+#[pymodule(name = "psrs")]
+fn psrs_module(m: &Bound<'_, PyModule>) -> PyResult<()> {
+    m.add_function(wrap_pyfunction!(sort, m)?)
+}
@@ -0,0 +1,156 @@
+use std::ops::Range;
+
+use crate::executor::{self, default_executor};
+
+/// Samples [`psrs_quantiles`] takes from each chunk (fewer if the chunk is
+/// shorter), which keeps the error bounds around a thousandth of the input.
+pub const QUANTILE_SAMPLES_PER_CHUNK: usize = 1024;
+
+/// An approximate quantile as returned by [`psrs_quantiles`].
+#[derive(Debug, Clone, PartialEq)]
+pub struct Quantile<T> {
+    /// The requested quantile, clamped to `0.0..=1.0`.
+    pub q: f64,
+    pub value: T,
+    /// Where `value` is guaranteed to sit in the sorted input: at least
+    /// `ranks.start` elements are smaller than it and at most `ranks.end`
+    /// are smaller or equal.
+    pub ranks: Range<usize>,
+    /// Upper bound on how far the true quantile of `value` is from `q`,
+    /// as a fraction of the input length.
+    pub error: f64,
+}
+
+/// Approximate quantiles of `data`, one for each of `qs` (e.g. `0.5` for
+/// the median), computed with PSRS's regular sampling and without sorting
+/// or changing `data`.
+///
+/// The input is split into `p` chunks, and up to
+/// [`QUANTILE_SAMPLES_PER_CHUNK`] evenly spaced order statistics of each
+/// are found in parallel by selection, which costs far less than sorting
+/// the chunks. Each quantile is read off the combined samples, and where
+/// its value falls among every chunk's samples bounds its true rank, which
+/// is returned with it. Returns nothing for empty input.
+///
+/// A slice that is already sorted has its exact quantiles at
+/// `data[(q * (n - 1) as f64) as usize]`, so right after a sort no sampling
+/// is needed.
+pub fn psrs_quantiles<T: Clone + Ord + Send + Sync>(data: &[T], qs: &[f64], p: usize) -> Vec<Quantile<T>> {
+    let n = data.len();
+    if n == 0 {
+        return Vec::new();
+    }
+    let chunk_size = n.div_ceil(p.max(1));
+    // Per chunk: its length, and the positions and values of its samples in
+    // its sorted order.
+    let samples: Vec<(usize, Vec<usize>, Vec<T>)> =
+        executor::par_chunks_map(default_executor(), data, chunk_size, |_, chunk| {
+            let m = chunk.len();
+            let s = QUANTILE_SAMPLES_PER_CHUNK.min(m);
+            let positions: Vec<usize> = (0..s).map(|k| (2 * k + 1) * m / (2 * s)).collect();
+            let mut copy = chunk.to_vec();
+            select_positions(&mut copy, &positions, 0);
+            let values = positions.iter().map(|&i| copy[i].clone()).collect();
+            (m, positions, values)
+        });
+
+    // Every sample stands for the `m / s` elements of its chunk around it.
+    let mut weighted: Vec<(&T, f64)> = samples
+        .iter()
+        .flat_map(|(m, positions, values)| {
+            let weight = *m as f64 / positions.len() as f64;
+            values.iter().map(move |v| (v, weight))
+        })
+        .collect();
+    weighted.sort_unstable_by(|a, b| a.0.cmp(b.0));
+
+    qs.iter()
+        .map(|&q| {
+            let q = q.clamp(0.0, 1.0);
+            let target = q * (n - 1) as f64;
+            let mut seen = 0.0;
+            let value = weighted
+                .iter()
+                .find(|(_, weight)| {
+                    seen += weight;
+                    seen > target
+                })
+                .unwrap_or(&weighted[weighted.len() - 1])
+                .0;
+            let ranks = rank_bounds(&samples, value);
+            let error = (target - ranks.start as f64).max(ranks.end as f64 - 1.0 - target).max(0.0) / n as f64;
+            Quantile { q, value: value.clone(), ranks, error }
+        })
+        .collect()
+}
+
+/// Bounds on the rank of `value` from the samples of every chunk: in a
+/// chunk, everything up to the last sample below `value` is smaller, and
+/// everything from the first sample above it is larger.
+fn rank_bounds<T: Ord>(samples: &[(usize, Vec<usize>, Vec<T>)], value: &T) -> Range<usize> {
+    let (mut lower, mut upper) = (0, 0);
+    for (m, positions, values) in samples {
+        let below = values.partition_point(|v| v < value);
+        let not_above = values.partition_point(|v| v <= value);
+        lower += if below > 0 { positions[below - 1] + 1 } else { 0 };
+        upper += positions.get(not_above).copied().unwrap_or(*m);
+    }
+    lower..upper
+}
+
+/// Rearranges `data` so that the elements at the ascending `positions`
+/// (relative to `offset`) are the ones a sort would put there, selecting
+/// the middle position first and recursing into both sides.
+fn select_positions<T: Ord>(data: &mut [T], positions: &[usize], offset: usize) {
+    if positions.is_empty() {
+        return;
+    }
+    let mid = positions.len() / 2;
+    let at = positions[mid] - offset;
+    data.select_nth_unstable(at);
+    let (left, right) = data.split_at_mut(at);
+    select_positions(left, &positions[..mid], offset);
+    select_positions(&mut right[1..], &positions[mid + 1..], offset + at + 1);
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::testing::edge_cases;
+
+    const QS: [f64; 7] = [0.0, 0.01, 0.25, 0.5, 0.9, 0.999, 1.0];
+
+    #[test]
+    fn rank_bounds_hold_against_the_sorted_input() {
+        for p in [1, 4, 7] {
+            for (name, values) in edge_cases(p) {
+                let original = values.clone();
+                let quantiles = psrs_quantiles(&values, &QS, p);
+                assert_eq!(values, original, "the input must not change");
+                if values.is_empty() {
+                    assert!(quantiles.is_empty());
+                    continue;
+                }
+                let n = values.len();
+                for Quantile { q, value, ranks, error } in quantiles {
+                    let less = values.iter().filter(|&&v| v < value).count();
+                    let not_more = values.iter().filter(|&&v| v <= value).count();
+                    assert!(ranks.start <= less && not_more <= ranks.end, "{name}, p = {p}, q = {q}: {ranks:?}");
+                    // Distance from the target rank to the nearest position
+                    // `value` actually occupies.
+                    let target = q * (n - 1) as f64;
+                    let off = (less as f64 - target).max(target - (not_more - 1) as f64).max(0.0);
+                    assert!(off / n as f64 <= error + 1e-12, "{name}, p = {p}, q = {q}: {off} over {error}");
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn large_inputs_have_tight_bounds() {
+        let values = crate::testing::random_values(200_000, u64::MAX, 19);
+        for quantile in psrs_quantiles(&values, &QS, 8) {
+            assert!(quantile.error < 0.002, "{quantile:?}");
+        }
+    }
+}
@@ -0,0 +1,269 @@
+/// Types whose order is the unsigned order of a fixed-width byte key, so
+/// they can be sorted by [`radix_sort`].
+///
+/// The key must agree with `Ord`, which PSRS still uses to pick pivots and
+/// merge. Signed integers flip their sign bit, which moves negative values
+/// below positive ones without changing the order within either half.
+pub trait RadixKey: Copy + Ord + Send + Sync {
+    /// Width of the key in bytes, i.e. the maximum number of radix passes.
+    const BYTES: usize;
+
+    /// Byte `i` of the key, counting from the least significant byte.
+    fn key_byte(&self, i: usize) -> u8;
+}
+
+macro_rules! impl_radix_key {
+    ($($t:ty => $unsigned:ty, $flip:expr;)*) => {$(
+        impl RadixKey for $t {
+            const BYTES: usize = std::mem::size_of::<$t>();
+
+            #[inline]
+            fn key_byte(&self, i: usize) -> u8 {
+                (((*self as $unsigned) ^ $flip) >> (8 * i)) as u8
+            }
+        }
+    )*};
+}
+
+impl_radix_key! {
+    u8 => u8, 0;
+    u16 => u16, 0;
+    u32 => u32, 0;
+    u64 => u64, 0;
+    i8 => u8, 1 << 7;
+    i16 => u16, 1 << 15;
+    i32 => u32, 1 << 31;
+    i64 => u64, 1 << 63;
+    u128 => u128, 0;
+    i128 => u128, 1 << 127;
+}
+
+/// Tuples compare their first field first, so the last field supplies the
+/// least significant key bytes.
+impl<A: RadixKey, B: RadixKey> RadixKey for (A, B) {
+    const BYTES: usize = A::BYTES + B::BYTES;
+
+    #[inline]
+    fn key_byte(&self, i: usize) -> u8 {
+        if i < B::BYTES {
+            self.1.key_byte(i)
+        } else {
+            self.0.key_byte(i - B::BYTES)
+        }
+    }
+}
+
+impl<A: RadixKey, B: RadixKey, C: RadixKey> RadixKey for (A, B, C) {
+    const BYTES: usize = A::BYTES + B::BYTES + C::BYTES;
+
+    #[inline]
+    fn key_byte(&self, i: usize) -> u8 {
+        if i < C::BYTES {
+            self.2.key_byte(i)
+        } else if i < B::BYTES + C::BYTES {
+            self.1.key_byte(i - C::BYTES)
+        } else {
+            self.0.key_byte(i - B::BYTES - C::BYTES)
+        }
+    }
+}
+
+/// Byte arrays such as hashes and UUIDs compare lexicographically, so their
+/// first byte is the most significant.
+impl<const N: usize> RadixKey for [u8; N] {
+    const BYTES: usize = N;
+
+    #[inline]
+    fn key_byte(&self, i: usize) -> u8 {
+        self[N - 1 - i]
+    }
+}
+
+/// Below this length the per-pass histogram overhead outweighs the gain and
+/// [`radix_sort`] falls back to a comparison sort.
+const RADIX_SORT_MIN_LEN: usize = 256;
+
+/// Sorts `data` with an LSD radix sort over its key bytes.
+///
+/// All byte histograms are gathered in a single read pass, and passes whose
+/// byte is identical across the whole slice are skipped, so narrow key
+/// ranges only pay for the bytes that actually vary.
+pub fn radix_sort<T: RadixKey>(data: &mut [T]) {
+    let n = data.len();
+    if n < RADIX_SORT_MIN_LEN {
+        data.sort_unstable();
+        return;
+    }
+
+    let mut counts = vec![[0usize; 256]; T::BYTES];
+    for x in data.iter() {
+        for (byte, count) in counts.iter_mut().enumerate() {
+            count[x.key_byte(byte) as usize] += 1;
+        }
+    }
+
+    let mut buf = data.to_vec();
+    let mut src: &mut [T] = data;
+    let mut dst: &mut [T] = &mut buf;
+    let mut passes = 0;
+    for (byte, count) in counts.iter().enumerate() {
+        if count.contains(&n) {
+            continue;
+        }
+        let mut offsets = [0usize; 256];
+        let mut sum = 0;
+        for (offset, &c) in offsets.iter_mut().zip(count.iter()) {
+            *offset = sum;
+            sum += c;
+        }
+        for x in src.iter() {
+            let b = x.key_byte(byte) as usize;
+            dst[offsets[b]] = *x;
+            offsets[b] += 1;
+        }
+        std::mem::swap(&mut src, &mut dst);
+        passes += 1;
+    }
+
+    // After an odd number of passes the sorted keys live in the scratch buffer.
+    if passes % 2 == 1 {
+        dst.copy_from_slice(src);
+    }
+}
+
+/// Sorts `data` with an MSD radix sort: one pass splits it by its most
+/// significant key byte, then every bucket is sorted by the next byte, and
+/// buckets shorter than [`RADIX_SORT_MIN_LEN`] by comparison.
+///
+/// Unlike [`radix_sort`], which always makes a pass per varying byte, this
+/// stops as soon as the buckets are small, so wide keys whose leading
+/// bytes already tell them apart, such as 32-byte hashes, need only one or
+/// two passes.
+pub fn msb_radix_sort<T: RadixKey>(data: &mut [T]) {
+    if T::BYTES == 0 || data.len() < RADIX_SORT_MIN_LEN {
+        data.sort_unstable();
+        return;
+    }
+    let mut buf = data.to_vec();
+    msb_radix_pass(data, &mut buf, T::BYTES - 1);
+}
+
+/// Sorts `data`, whose keys agree above byte `byte`, using `buf` (as long)
+/// as scratch space.
+fn msb_radix_pass<T: RadixKey>(data: &mut [T], buf: &mut [T], byte: usize) {
+    if data.len() < RADIX_SORT_MIN_LEN {
+        data.sort_unstable();
+        return;
+    }
+    let mut counts = [0usize; 256];
+    for x in data.iter() {
+        counts[x.key_byte(byte) as usize] += 1;
+    }
+    let mut starts = [0usize; 257];
+    for (b, &c) in counts.iter().enumerate() {
+        starts[b + 1] = starts[b] + c;
+    }
+    // A byte shared by the whole slice needs no scatter.
+    if !counts.contains(&data.len()) {
+        let mut offsets = starts;
+        for x in data.iter() {
+            let b = x.key_byte(byte) as usize;
+            buf[offsets[b]] = *x;
+            offsets[b] += 1;
+        }
+        data.copy_from_slice(buf);
+    }
+    if byte == 0 {
+        return;
+    }
+    for bucket in starts.windows(2) {
+        if bucket[1] - bucket[0] > 1 {
+            let range = bucket[0]..bucket[1];
+            msb_radix_pass(&mut data[range.clone()], &mut buf[range], byte - 1);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::testing::{edge_cases, std_sorted};
+
+    #[test]
+    fn radix_sort_matches_std_sort() {
+        for (name, values) in edge_cases(4) {
+            let mut unsigned: Vec<u64> = values.clone();
+            let expected = std_sorted(&unsigned);
+            radix_sort(&mut unsigned);
+            assert_eq!(unsigned, expected, "{name}");
+
+            // Reinterpreting the bits puts half of the values below zero.
+            let mut signed: Vec<i64> = values.iter().map(|&v| v as i64).collect();
+            let expected = std_sorted(&signed);
+            radix_sort(&mut signed);
+            assert_eq!(signed, expected, "{name} (signed)");
+
+            let mut narrow: Vec<i16> = values.iter().map(|&v| v as i16).collect();
+            let expected = std_sorted(&narrow);
+            radix_sort(&mut narrow);
+            assert_eq!(narrow, expected, "{name} (i16)");
+        }
+    }
+
+
+    #[test]
+    fn wide_and_tuple_keys_match_std_sort() {
+        for (name, values) in edge_cases(4) {
+            let mut wide: Vec<i128> = values.iter().map(|&v| i128::from(v as i64) << 40).collect();
+            let expected = std_sorted(&wide);
+            radix_sort(&mut wide);
+            assert_eq!(wide, expected, "{name} (i128)");
+
+            // Few distinct first fields, so the later fields decide most pairs.
+            let mut pairs: Vec<(u8, i32)> = values.iter().map(|&v| ((v % 3) as u8, v as i32)).collect();
+            let expected = std_sorted(&pairs);
+            radix_sort(&mut pairs);
+            assert_eq!(pairs, expected, "{name} (pairs)");
+
+            let mut triples: Vec<(i8, u16, u32)> =
+                values.iter().map(|&v| ((v % 5) as i8 - 2, (v >> 8) as u16 % 4, v as u32)).collect();
+            let expected = std_sorted(&triples);
+            radix_sort(&mut triples);
+            assert_eq!(triples, expected, "{name} (triples)");
+        }
+    }
+
+    #[test]
+    fn msb_radix_sort_matches_std_sort() {
+        for (name, values) in edge_cases(4) {
+            let mut data: Vec<i64> = values.iter().map(|&v| v as i64).collect();
+            let expected = std_sorted(&data);
+            msb_radix_sort(&mut data);
+            assert_eq!(data, expected, "{name}");
+        }
+    }
+
+    #[test]
+    fn byte_arrays_sort_lexicographically() {
+        for p in [1, 4, 7] {
+            for (name, values) in edge_cases(p) {
+                // Short shared prefixes send buckets through several passes.
+                let mut hashes: Vec<[u8; 12]> = values
+                    .iter()
+                    .map(|&v| {
+                        let mut key = [0; 12];
+                        key[2..10].copy_from_slice(&(v % 4096).to_be_bytes());
+                        key[11] = v as u8;
+                        key
+                    })
+                    .collect();
+                let expected = std_sorted(&hashes);
+                let mut lsd = hashes.clone();
+                radix_sort(&mut lsd);
+                assert_eq!(lsd, expected, "{name} (LSD)");
+                crate::psrs_msb_radix(&mut hashes, p);
+                assert_eq!(hashes, expected, "{name}, p = {p}");
+            }
+        }
+    }
+}
@@ -0,0 +1,121 @@
+use std::ops::Range;
+
+/// Where values fall in the output of a sort, built from its pivots and
+/// partition sizes, as returned by [`psrs_indexed`](crate::psrs_indexed).
+///
+/// Partition `i` of the output holds only values between pivots `i - 1` and
+/// `i`, so a binary search over the `p - 1` pivots narrows any value down to
+/// one partition in O(log p) without touching the data. Finding its exact
+/// position then only searches that partition of the sorted slice.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct RankIndex<T> {
+    pivots: Vec<T>,
+    /// Start of every partition in the output, followed by its length.
+    offsets: Vec<usize>,
+}
+
+impl<T> RankIndex<T> {
+    /// The index of an output made of partitions of `sizes` elements split
+    /// at `pivots`, one fewer than the partitions.
+    pub(crate) fn new(pivots: Vec<T>, sizes: &[usize]) -> Self {
+        debug_assert_eq!(pivots.len() + 1, sizes.len());
+        let mut offsets = Vec::with_capacity(sizes.len() + 1);
+        offsets.push(0);
+        for size in sizes {
+            offsets.push(offsets[offsets.len() - 1] + size);
+        }
+        RankIndex { pivots, offsets }
+    }
+
+    /// Length of the indexed output.
+    pub fn len(&self) -> usize {
+        self.offsets[self.offsets.len() - 1]
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    /// The pivots the output was partitioned at, in order.
+    pub fn pivots(&self) -> &[T] {
+        &self.pivots
+    }
+
+    /// The range of the output each partition occupies, in order.
+    pub fn partitions(&self) -> impl Iterator<Item = Range<usize>> + '_ {
+        self.offsets.windows(2).map(|w| w[0]..w[1])
+    }
+}
+
+impl<T: Ord> RankIndex<T> {
+    /// Bounds on the rank of `x`, the number of output elements smaller
+    /// than it: the range of the partition it would be inserted into.
+    pub fn rank(&self, x: &T) -> Range<usize> {
+        let part = self.pivots.partition_point(|pivot| pivot < x);
+        self.offsets[part]..self.offsets[part + 1]
+    }
+
+    /// A range of the output that holds every value in `a..b`, and outside
+    /// of which there are none. Empty if `a..b` is.
+    pub fn range(&self, a: &T, b: &T) -> Range<usize> {
+        let start = self.rank(a).start;
+        if a >= b {
+            return start..start;
+        }
+        start..self.rank(b).end
+    }
+
+    /// The exact rank of `x` in `sorted`, the output this index was built
+    /// for, searching only the partition [`rank`](RankIndex::rank) picks.
+    pub fn rank_in(&self, sorted: &[T], x: &T) -> usize {
+        let bounds = self.rank(x);
+        bounds.start + sorted[bounds].partition_point(|y| y < x)
+    }
+
+    /// The exact range of `sorted`, the output this index was built for,
+    /// that holds the values in `a..b`.
+    pub fn range_in(&self, sorted: &[T], a: &T, b: &T) -> Range<usize> {
+        let start = self.rank_in(sorted, a);
+        start..self.rank_in(sorted, b).max(start)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::psrs_indexed;
+    use crate::testing::{edge_cases, std_sorted};
+
+    #[test]
+    fn ranks_match_the_sorted_output() {
+        for p in [1, 4, 7] {
+            for (name, values) in edge_cases(p) {
+                let values: Vec<u64> = values.iter().map(|v| v % 1_000).collect();
+                let mut data = values.clone();
+                let index = psrs_indexed(&mut data, p);
+                assert_eq!(data, std_sorted(&values), "{name}, p = {p}");
+                assert_eq!(index.len(), data.len(), "{name}, p = {p}");
+                assert_eq!(index.partitions().map(|r| r.len()).sum::<usize>(), data.len(), "{name}, p = {p}");
+
+                for x in (0..1_010).step_by(7).chain(index.pivots().iter().copied()) {
+                    let rank = rank_of(&data, x);
+                    let bounds = index.rank(&x);
+                    assert!(bounds.start <= rank && rank <= bounds.end, "{name}, p = {p}, x = {x}: {bounds:?}");
+                    assert_eq!(index.rank_in(&data, &x), rank, "{name}, p = {p}, x = {x}");
+
+                    let (a, b) = (x.saturating_sub(30), x);
+                    let range = index.range(&a, &b);
+                    let inside = data.iter().enumerate().filter(|(_, y)| (a..b).contains(y));
+                    assert!(inside.clone().all(|(i, _)| range.contains(&i)), "{name}, p = {p}, {a}..{b}");
+                    let exact = rank_of(&data, a)..rank.max(rank_of(&data, a));
+                    assert_eq!(index.range_in(&data, &a, &b), exact, "{name}, p = {p}, {a}..{b}");
+                    assert_eq!(inside.count(), exact.len(), "{name}, p = {p}, {a}..{b}");
+                }
+            }
+        }
+    }
+
+    /// Elements of `sorted` smaller than `x`.
+    fn rank_of(sorted: &[u64], x: u64) -> usize {
+        sorted.partition_point(|&y| y < x)
+    }
+}
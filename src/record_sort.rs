@@ -0,0 +1,98 @@
+//! `records` subcommand: sorts a file of fixed-size binary records, such
+//! as an array of C structs, by a key field inside each record.
+//!
+//! Whole records are permuted; only the keys go through PSRS (see
+//! [`psrs_binary_records`]), and records with equal keys keep their input
+//! order.
+
+use std::fs::File;
+use std::io::{self, Read, Write};
+
+use psrs::{psrs_binary_records, RecordKey, RecordLayout};
+
+/// Options of `records`, see [`usage`].
+struct RecordArgs {
+    input: Option<String>,
+    output: Option<String>,
+    layout: RecordLayout,
+    threads: usize,
+}
+
+fn usage() -> ! {
+    eprintln!(
+        "usage: records [--input file.bin] [--output sorted.bin] --record-size N --key-offset N \
+         --key-type u8|u16|u32|u64|i8|i16|i32|i64|f32|f64|bytes [--key-width N] [--big-endian] \
+         [--threads N]\n\
+         Reads stdin and writes stdout when --input or --output is missing. Numeric keys are \
+         little-endian unless --big-endian is given; --key-width must match their size if \
+         given. bytes keys need --key-width and compare lexicographically."
+    );
+    std::process::exit(2)
+}
+
+fn parse_args(mut args: impl Iterator<Item = String>) -> RecordArgs {
+    let (mut record_size, mut key_offset, mut key_type, mut key_width) = (None, None, None, None);
+    let mut parsed = RecordArgs {
+        input: None,
+        output: None,
+        layout: RecordLayout { record_size: 0, key_offset: 0, key: RecordKey::U8, big_endian: false },
+        threads: std::thread::available_parallelism().map_or(1, |n| n.get()),
+    };
+    while let Some(arg) = args.next() {
+        let mut value = || args.next().unwrap_or_else(|| usage());
+        match arg.as_str() {
+            "--input" => parsed.input = Some(value()),
+            "--output" => parsed.output = Some(value()),
+            "--record-size" => record_size = Some(value().parse().unwrap_or_else(|_| usage())),
+            "--key-offset" => key_offset = Some(value().parse().unwrap_or_else(|_| usage())),
+            "--key-type" => key_type = Some(value()),
+            "--key-width" => key_width = Some(value().parse().unwrap_or_else(|_| usage())),
+            "--big-endian" => parsed.layout.big_endian = true,
+            "--threads" => parsed.threads = value().parse().unwrap_or_else(|_| usage()),
+            _ => usage(),
+        }
+    }
+    let (Some(record_size), Some(key_offset), Some(key_type)) = (record_size, key_offset, key_type) else { usage() };
+    let key = match key_type.as_str() {
+        "u8" => RecordKey::U8,
+        "u16" => RecordKey::U16,
+        "u32" => RecordKey::U32,
+        "u64" => RecordKey::U64,
+        "i8" => RecordKey::I8,
+        "i16" => RecordKey::I16,
+        "i32" => RecordKey::I32,
+        "i64" => RecordKey::I64,
+        "f32" => RecordKey::F32,
+        "f64" => RecordKey::F64,
+        "bytes" => RecordKey::Bytes(key_width.unwrap_or_else(|| usage())),
+        _ => usage(),
+    };
+    if key_width.is_some_and(|width| width != key.width()) || record_size == 0 || key_offset + key.width() > record_size {
+        usage();
+    }
+    parsed.layout = RecordLayout { record_size, key_offset, key, ..parsed.layout };
+    parsed
+}
+
+/// Runs `records` with the arguments that follow the subcommand name.
+pub fn main(args: impl Iterator<Item = String>) {
+    let args = parse_args(args);
+    let mut data = Vec::new();
+    match &args.input {
+        Some(path) => File::open(path).and_then(|mut file| file.read_to_end(&mut data)),
+        None => io::stdin().lock().read_to_end(&mut data),
+    }
+    .expect("failed to read the records");
+    if !data.len().is_multiple_of(args.layout.record_size) {
+        eprintln!("records: {} bytes is not a whole number of {}-byte records", data.len(), args.layout.record_size);
+        std::process::exit(1);
+    }
+
+    psrs_binary_records(&mut data, &args.layout, args.threads);
+
+    match &args.output {
+        Some(path) => File::create(path).and_then(|mut file| file.write_all(&data)),
+        None => io::stdout().lock().write_all(&data),
+    }
+    .expect("failed to write the records");
+}
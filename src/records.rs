@@ -0,0 +1,336 @@
+use std::mem;
+use std::sync::Mutex;
+
+use crate::executor::{self, default_executor};
+use crate::psrs;
+
+/// Records that are ordered by a small key embedded in them, e.g.
+/// `Event { ts: u64, payload: ... }` keyed by `ts`.
+pub trait SortKey {
+    type Key: Clone + Ord + Send + Sync;
+
+    fn key(&self) -> Self::Key;
+}
+
+/// Sorts records by [`SortKey::key`] without moving whole records through
+/// the PSRS phases.
+///
+/// Only `(key, index)` pairs go through the sort; the records are then
+/// permuted into place once. Records with equal keys keep their original
+/// relative order.
+pub fn psrs_records<T: SortKey + Send + Sync>(data: &mut [T], p: usize) {
+    let mut order = sorted_order(data, p, T::key);
+    apply_permutation(data, &mut order);
+}
+
+/// Sorts large elements indirectly: only `(&element, index)` pairs go
+/// through the PSRS phases, and the elements are then moved into place by a
+/// parallel gather. Equal elements keep their original relative order.
+///
+/// Only pays off for large elements (hundreds of bytes), where moving them
+/// through the local sort and merge costs more than comparing through
+/// references; if the order comes from a small key,
+/// [`psrs_indirect_by_key`] avoids that indirection too.
+pub fn psrs_indirect<T: Clone + Ord + Send + Sync>(data: &mut [T], p: usize) {
+    let order = sorted_order(data, p, |x| x);
+    par_apply_permutation(data, &order);
+}
+
+/// Like [`psrs_indirect`], but orders elements by `key`, e.g. a field of a
+/// large record, like [`psrs_records`] does with [`SortKey`]. Unlike
+/// [`psrs_records`], the permutation is applied in parallel.
+pub fn psrs_indirect_by_key<T, K, F>(data: &mut [T], p: usize, key: F)
+where
+    T: Clone + Send + Sync,
+    K: Clone + Ord + Send + Sync,
+    F: Fn(&T) -> K + Sync,
+{
+    let order = sorted_order(data, p, key);
+    par_apply_permutation(data, &order);
+}
+
+/// Reorders the rows of a row-major matrix `data`, `row_len` elements per
+/// row, by the value in column `key_col`. Rows with equal keys keep their
+/// original relative order.
+///
+/// Only `(key, row index)` pairs go through the PSRS phases; each row is
+/// then moved once, by a parallel gather, so wide rows cost no more to sort
+/// than narrow ones beyond that copy.
+pub fn psrs_rows<T: Clone + Ord + Send + Sync>(data: &mut [T], row_len: usize, key_col: usize, p: usize) {
+    assert!(key_col < row_len, "key column {key_col} out of range for rows of {row_len}");
+    assert!(data.len().is_multiple_of(row_len), "data is not a whole number of rows");
+    let rows: Vec<&[T]> = data.chunks(row_len).collect();
+    let order = sorted_order(&rows, p, |row| row[key_col].clone());
+    par_permute_rows(data, row_len, &order);
+}
+
+/// Type of the key embedded in every record of a [`RecordLayout`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum RecordKey {
+    U8,
+    U16,
+    U32,
+    U64,
+    I8,
+    I16,
+    I32,
+    I64,
+    /// Ordered like `f32::total_cmp`.
+    F32,
+    /// Ordered like `f64::total_cmp`.
+    F64,
+    /// That many bytes compared lexicographically, e.g. a hash or a
+    /// fixed-width name.
+    Bytes(usize),
+}
+
+impl RecordKey {
+    /// Width of the key in bytes.
+    pub fn width(self) -> usize {
+        match self {
+            RecordKey::U8 | RecordKey::I8 => 1,
+            RecordKey::U16 | RecordKey::I16 => 2,
+            RecordKey::U32 | RecordKey::I32 | RecordKey::F32 => 4,
+            RecordKey::U64 | RecordKey::I64 | RecordKey::F64 => 8,
+            RecordKey::Bytes(width) => width,
+        }
+    }
+
+    /// The numeric key in `bytes` as a `u64` in the same order, with sign
+    /// bits flipped and negative floats inverted.
+    fn order_key(self, bytes: &[u8], big_endian: bool) -> u64 {
+        let fold = |raw: u64, &b: &u8| raw << 8 | b as u64;
+        let raw = if big_endian { bytes.iter().fold(0, fold) } else { bytes.iter().rev().fold(0, fold) };
+        let sign = 1u64 << (8 * bytes.len() - 1);
+        match self {
+            RecordKey::I8 | RecordKey::I16 | RecordKey::I32 | RecordKey::I64 => raw ^ sign,
+            RecordKey::F32 | RecordKey::F64 if raw & sign != 0 => !raw & (sign | (sign - 1)),
+            RecordKey::F32 | RecordKey::F64 => raw | sign,
+            _ => raw,
+        }
+    }
+}
+
+/// Where the key sits in fixed-size binary records, as sorted by
+/// [`psrs_binary_records`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct RecordLayout {
+    /// Bytes per record.
+    pub record_size: usize,
+    /// Offset of the key within a record.
+    pub key_offset: usize,
+    pub key: RecordKey,
+    /// Numeric keys are stored most significant byte first, instead of
+    /// little-endian.
+    pub big_endian: bool,
+}
+
+/// Sorts the fixed-size records packed in `data`, e.g. an array of C
+/// structs read from a file, by the key that `layout` locates in each.
+/// Records with equal keys keep their original relative order.
+///
+/// As with [`psrs_rows`], only `(key, record index)` pairs go through the
+/// PSRS phases and each record is moved once, by a parallel gather.
+pub fn psrs_binary_records(data: &mut [u8], layout: &RecordLayout, p: usize) {
+    let RecordLayout { record_size, key_offset, key, big_endian } = *layout;
+    let key_range = key_offset..key_offset + key.width();
+    assert!(record_size > 0, "records must not be empty");
+    assert!(key_range.end <= record_size, "key {key_range:?} out of range for records of {record_size} bytes");
+    assert!(data.len().is_multiple_of(record_size), "data is not a whole number of records");
+    let records: Vec<&[u8]> = data.chunks(record_size).collect();
+    let order = match key {
+        RecordKey::Bytes(_) => sorted_order(&records, p, |record| &record[key_range.clone()]),
+        _ => sorted_order(&records, p, |record| key.order_key(&record[key_range.clone()], big_endian)),
+    };
+    par_permute_rows(data, record_size, &order);
+}
+
+/// Sorts `(key(element), index)` pairs of `data` with PSRS and returns the
+/// indices in sorted order.
+fn sorted_order<'a, T, K, F>(data: &'a [T], p: usize, key: F) -> Vec<usize>
+where
+    T: Sync,
+    K: Clone + Ord + Send + Sync,
+    F: Fn(&'a T) -> K + Sync,
+{
+    let exec = default_executor();
+    let chunk_size = executor::even_chunk_size(exec, data.len());
+    // Chunked by hand so the keys may borrow from `data`.
+    let chunks: Vec<&'a [T]> = data.chunks(chunk_size).collect();
+    let keyed_chunks = executor::par_map(exec, chunks.len(), |chunk_idx| {
+        let offset = chunk_idx * chunk_size;
+        chunks[chunk_idx].iter().enumerate().map(|(i, record)| (key(record), offset + i)).collect::<Vec<_>>()
+    });
+    let mut keyed: Vec<(K, usize)> = keyed_chunks.into_iter().flatten().collect();
+    psrs(&mut keyed, p);
+    keyed.into_iter().map(|(_, i)| i).collect()
+}
+
+/// Reorders `data` so that `data[j]` becomes the old `data[order[j]]`, in
+/// parallel.
+fn par_apply_permutation<T: Clone + Send + Sync>(data: &mut [T], order: &[usize]) {
+    par_permute_rows(data, 1, order);
+}
+
+/// Reorders the `row_len`-element rows of `data` so that row `j` becomes
+/// the old row `order[j]`: each chunk of `order` is gathered into a buffer
+/// of its own, then the buffers are moved back over `data`.
+fn par_permute_rows<T: Clone + Send + Sync>(data: &mut [T], row_len: usize, order: &[usize]) {
+    let exec = default_executor();
+    let chunk_size = executor::even_chunk_size(exec, order.len());
+    let shared: &[T] = data;
+    let gathered: Vec<Mutex<Vec<T>>> = executor::par_chunks_map(exec, order, chunk_size, |_, indices| {
+        let rows = indices.iter().map(|&i| &shared[i * row_len..(i + 1) * row_len]);
+        Mutex::new(rows.flatten().cloned().collect())
+    });
+    executor::par_chunks_mut_map(exec, data, chunk_size * row_len, |chunk_idx, chunk| {
+        let values = mem::take(&mut *gathered[chunk_idx].lock().unwrap());
+        for (slot, value) in chunk.iter_mut().zip(values) {
+            *slot = value;
+        }
+    });
+}
+
+/// Reorders `data` so that `data[j]` becomes the old `data[order[j]]`, by
+/// following the permutation's cycles with swaps. `order` is consumed (every
+/// entry is reset to its own position).
+pub(crate) fn apply_permutation<T>(data: &mut [T], order: &mut [usize]) {
+    for start in 0..order.len() {
+        let mut j = start;
+        loop {
+            let k = order[j];
+            order[j] = j;
+            if k == start {
+                break;
+            }
+            data.swap(j, k);
+            j = k;
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::testing::edge_cases;
+    use std::cmp::Ordering;
+
+    /// A record keyed by `ts`, with `seq` recording its original position.
+    #[derive(Debug, Clone, PartialEq, Eq)]
+    struct Event {
+        ts: u16,
+        seq: usize,
+    }
+
+    impl SortKey for Event {
+        type Key = u16;
+
+        fn key(&self) -> u16 {
+            self.ts
+        }
+    }
+
+    fn events(values: &[u64]) -> Vec<Event> {
+        values.iter().enumerate().map(|(seq, &v)| Event { ts: v as u16, seq }).collect()
+    }
+
+    #[test]
+    fn psrs_records_matches_a_stable_sort() {
+        for p in [1, 4, 7] {
+            for (name, values) in edge_cases(p) {
+                let mut data = events(&values);
+                let mut expected = data.clone();
+                expected.sort_by_key(|e| e.ts);
+                psrs_records(&mut data, p);
+                assert_eq!(data, expected, "{name}, p = {p}");
+            }
+        }
+    }
+
+    #[test]
+    fn indirect_sorts_match_std_sort() {
+        for p in [1, 4, 7] {
+            for (name, values) in edge_cases(p) {
+                let mut data: Vec<(u8, [u64; 4])> = values.iter().map(|&v| (v as u8, [v; 4])).collect();
+                let expected = crate::testing::std_sorted(&data);
+                psrs_indirect(&mut data, p);
+                assert_eq!(data, expected, "{name}, p = {p}");
+
+                let mut data = events(&values);
+                let mut expected = data.clone();
+                expected.sort_by_key(|e| e.ts);
+                psrs_indirect_by_key(&mut data, p, |e| e.ts);
+                assert_eq!(data, expected, "{name} (by key), p = {p}");
+            }
+        }
+    }
+
+    #[test]
+    fn psrs_rows_moves_whole_rows() {
+        const ROW_LEN: usize = 3;
+        for p in [1, 4, 7] {
+            for (name, values) in edge_cases(p) {
+                // Rows of (row index, key, row index), keyed by the middle column.
+                let mut matrix: Vec<u64> =
+                    values.iter().enumerate().flat_map(|(i, &v)| [i as u64, v % 50, i as u64]).collect();
+                let mut expected: Vec<&[u64]> = matrix.chunks(ROW_LEN).collect();
+                expected.sort_by_key(|row| row[1]);
+                let expected = expected.concat();
+                psrs_rows(&mut matrix, ROW_LEN, 1, p);
+                assert_eq!(matrix, expected, "{name}, p = {p}");
+            }
+        }
+    }
+
+    /// Packs one 16-byte record per value: a 4-byte sequence number, then
+    /// the value as the key at offset 4, written by `encode`.
+    fn pack(values: &[u64], encode: impl Fn(u64) -> [u8; 8]) -> Vec<u8> {
+        values
+            .iter()
+            .enumerate()
+            .flat_map(|(seq, &v)| {
+                let mut record = [0u8; 16];
+                record[..4].copy_from_slice(&(seq as u32).to_le_bytes());
+                record[4..12].copy_from_slice(&encode(v));
+                record
+            })
+            .collect()
+    }
+
+    #[test]
+    fn binary_records_match_a_stable_sort() {
+        let layout = |key, big_endian| RecordLayout { record_size: 16, key_offset: 4, key, big_endian };
+        let f64_key = |r: &[u8]| f64::from_le_bytes(r[4..12].try_into().unwrap());
+        for p in [1, 4, 7] {
+            for (name, values) in edge_cases(p) {
+                let check = |data: &mut Vec<u8>, layout: &RecordLayout, cmp: &dyn Fn(&[u8], &[u8]) -> Ordering| {
+                    let mut expected: Vec<&[u8]> = data.chunks(16).collect();
+                    expected.sort_by(|a, b| cmp(a, b));
+                    let expected = expected.concat();
+                    psrs_binary_records(data, layout, p);
+                    assert_eq!(*data, expected, "{name}, {layout:?}, p = {p}");
+                };
+
+                let mut data = pack(&values, |v| (v as i64).to_le_bytes());
+                let key = |r: &[u8]| i64::from_le_bytes(r[4..12].try_into().unwrap());
+                check(&mut data, &layout(RecordKey::I64, false), &|a, b| key(a).cmp(&key(b)));
+
+                let mut data = pack(&values, |v| (v % 1000).to_be_bytes());
+                check(&mut data, &layout(RecordKey::U64, true), &|a, b| a[4..12].cmp(&b[4..12]));
+
+                // Both signs, and a few of each special value.
+                let mut data = pack(&values, |v| match v % 16 {
+                    0 => f64::NAN,
+                    1 => f64::NEG_INFINITY,
+                    2 => -0.0,
+                    _ => (v % 2000) as f64 - 1000.5,
+                }
+                .to_le_bytes());
+                check(&mut data, &layout(RecordKey::F64, false), &|a, b| f64_key(a).total_cmp(&f64_key(b)));
+
+                let mut data = pack(&values, |v| ((v % 300) << 40).to_be_bytes());
+                check(&mut data, &layout(RecordKey::Bytes(3), false), &|a, b| a[4..7].cmp(&b[4..7]));
+            }
+        }
+    }
+}
@@ -0,0 +1,148 @@
+//! Reproduction bundles for incorrect sorts, and the `repro` subcommand
+//! that replays them.
+//!
+//! When a run of the sweep comes out unsorted or differs from `slice::sort`,
+//! the harness writes a bundle directory holding `repro.json` (the sorter,
+//! its config, the workload and the seed) and, if asked for, the input
+//! itself. `repro` rebuilds the input, reruns that sorter with that config
+//! and checks the output against `slice::sort`, so failures at odd n/p
+//! combinations can be debugged without rerunning the whole sweep.
+
+use std::fs;
+use std::panic::{self, AssertUnwindSafe};
+use std::path::Path;
+
+use psrs::PsrsConfig;
+use serde::{Deserialize, Serialize};
+
+use crate::datagen::generate_data;
+use crate::scenarios::Dataset;
+use crate::sorters::{self, SortContext};
+use crate::{input_values, load_data, save_data, BenchConfig, DataFiles, RunResult, Workload};
+
+/// The file describing a bundle.
+const BUNDLE_FILE: &str = "repro.json";
+/// The stored input of a bundle, as raw little-endian `u32`s.
+const DATA_FILE: &str = "data.u32";
+
+/// What `repro` needs to rerun one failing configuration.
+#[derive(Debug, Serialize, Deserialize)]
+struct Bundle {
+    algorithm: String,
+    config: PsrsConfig,
+    #[serde(flatten)]
+    workload: Workload,
+    seed: u64,
+    min_val: u32,
+    max_val: u32,
+    /// First index the failing run differed from `slice::sort` at, when run
+    /// with `--check-against-std`.
+    mismatch: Option<usize>,
+    /// Whether the input is stored as [`DATA_FILE`]; otherwise it is
+    /// generated again from the seed.
+    stored_data: bool,
+}
+
+/// Writes a bundle for `runs`, all of one sorter and config, if any of them
+/// was incorrect, into a directory of `bench.repro_dir` named after them.
+pub fn write_bundle(bench: &BenchConfig, files: &DataFiles, runs: &[RunResult]) {
+    let Some(root) = &bench.repro_dir else { return };
+    let Some(failed) = runs.iter().find(|run| !run.sorted) else { return };
+    let workload = failed.workload;
+    let dir = Path::new(root).join(format!(
+        "{}_{}_{}_{:?}_t{}",
+        failed.algorithm,
+        workload.data_len,
+        workload.distribution.name(),
+        workload.element,
+        failed.config.threads
+    ));
+    let bundle = Bundle {
+        algorithm: failed.algorithm.clone(),
+        config: failed.config.clone(),
+        workload,
+        seed: bench.seed.expect("seed is set before the sweep starts"),
+        min_val: bench.min_val,
+        max_val: bench.max_val,
+        mismatch: failed.mismatch,
+        stored_data: bench.repro_data || files.load.is_some(),
+    };
+    fs::create_dir_all(&dir).expect("failed to create reproduction bundle directory");
+    let json = serde_json::to_string_pretty(&bundle).expect("failed to serialize reproduction bundle");
+    fs::write(dir.join(BUNDLE_FILE), json).expect("failed to write reproduction bundle");
+    if bundle.stored_data {
+        save_data(&dir.join(DATA_FILE), &input_values(bench, workload, files));
+    }
+    println!("reproduction bundle written to {}; replay it with `repro {}`", dir.display(), dir.display());
+}
+
+fn usage() -> ! {
+    eprintln!(
+        "usage: repro bundle_dir [--runs N]\n\
+         Reruns the sort a reproduction bundle describes N times (default 1) and checks \
+         every output against slice::sort. Exits with 1 if any run is incorrect."
+    );
+    std::process::exit(2)
+}
+
+/// Runs `repro` with the arguments that follow the subcommand name.
+pub fn main(mut args: impl Iterator<Item = String>) {
+    let (mut dir, mut runs) = (None, 1);
+    while let Some(arg) = args.next() {
+        match arg.as_str() {
+            "--runs" => runs = args.next().and_then(|n| n.parse().ok()).unwrap_or_else(|| usage()),
+            _ if dir.is_none() && !arg.starts_with("--") => dir = Some(arg),
+            _ => usage(),
+        }
+    }
+    let Some(dir) = dir else { usage() };
+    let dir = Path::new(&dir);
+    let text = fs::read_to_string(dir.join(BUNDLE_FILE)).unwrap_or_else(|e| {
+        eprintln!("repro: {}: {e}", dir.join(BUNDLE_FILE).display());
+        std::process::exit(1);
+    });
+    let bundle: Bundle = serde_json::from_str(&text).expect("invalid reproduction bundle");
+    let Some(sorter) = sorters::find(&bundle.algorithm) else {
+        eprintln!("repro: unknown algorithm {:?}; was it built with another feature?", bundle.algorithm);
+        std::process::exit(1);
+    };
+    let workload = bundle.workload;
+    let values = match bundle.stored_data {
+        true => load_data(&dir.join(DATA_FILE)),
+        false => generate_data(workload.data_len, bundle.min_val, bundle.max_val, workload.distribution, bundle.seed),
+    };
+    assert_eq!(values.len(), workload.data_len, "bundle input has the wrong length");
+    let input = Dataset::from_u32(&values, workload.element);
+    let expected = input.sorted_by_std();
+
+    println!(
+        "{} on {} {:?} elements, {:?}, seed {}, {} threads",
+        bundle.algorithm, workload.data_len, workload.element, workload.distribution, bundle.seed, bundle.config.threads
+    );
+    let pool = rayon::ThreadPoolBuilder::new()
+        .num_threads(bundle.config.threads)
+        .build()
+        .expect("failed to build thread pool");
+    let ctx = SortContext { config: &bundle.config };
+    let mut failures = 0;
+    for run in 1..=runs {
+        let mut data = input.clone();
+        let outcome = panic::catch_unwind(AssertUnwindSafe(|| pool.install(|| sorter.sort(&mut data, &ctx))));
+        let problem = match outcome {
+            Err(_) => Some("panicked".to_string()),
+            Ok(_) => data.first_mismatch(&expected).map(|i| format!("differs from slice::sort at index {i}")),
+        };
+        match problem {
+            Some(problem) => {
+                failures += 1;
+                println!("run {run}: FAIL, {problem}");
+            }
+            None => println!("run {run}: ok"),
+        }
+    }
+    if failures > 0 {
+        println!("{failures} of {runs} runs failed");
+        std::process::exit(1);
+    }
+    println!("all {runs} runs correct");
+}
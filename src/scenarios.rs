@@ -0,0 +1,115 @@
+//! Element types the harness benchmarks, all derived from the same
+//! generated `u32` values so every type sees the same distribution.
+
+use serde::{Deserialize, Serialize};
+
+/// Element type of a benchmark scenario.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ElementType {
+    U32,
+    U64,
+    F64,
+    /// 16-byte [`Record`]s.
+    Record,
+    /// Ten-digit decimal strings.
+    String,
+}
+
+impl ElementType {
+    /// Bytes one element occupies, counting a string's heap buffer.
+    pub fn bytes(self) -> usize {
+        match self {
+            ElementType::U32 => 4,
+            ElementType::U64 | ElementType::F64 => 8,
+            ElementType::Record => size_of::<Record>(),
+            ElementType::String => size_of::<String>() + 10,
+        }
+    }
+}
+
+/// A 16-byte element with a `u64` sort key; the payload breaks ties so the
+/// sorted order is unique.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+pub struct Record {
+    pub key: u64,
+    pub payload: u64,
+}
+
+/// The input of one scenario.
+#[derive(Debug, Clone)]
+pub enum Dataset {
+    U32(Vec<u32>),
+    U64(Vec<u64>),
+    F64(Vec<f64>),
+    Record(Vec<Record>),
+    String(Vec<String>),
+}
+
+/// Expands `$body` once per [`Dataset`] variant with `$v` bound to its
+/// vector.
+macro_rules! each_variant {
+    ($data:expr, $v:ident => $body:expr) => {
+        match $data {
+            Dataset::U32($v) => $body,
+            Dataset::U64($v) => $body,
+            Dataset::F64($v) => $body,
+            Dataset::Record($v) => $body,
+            Dataset::String($v) => $body,
+        }
+    };
+}
+
+impl Dataset {
+    /// Converts `values` to `element`. Every conversion is strictly
+    /// monotonic, so the order of `values` carries over.
+    pub fn from_u32(values: &[u32], element: ElementType) -> Self {
+        match element {
+            ElementType::U32 => Dataset::U32(values.to_vec()),
+            ElementType::U64 => Dataset::U64(values.iter().map(|&v| (v as u64) << 32 | v as u64).collect()),
+            ElementType::F64 => Dataset::F64(values.iter().map(|&v| v as f64 + 0.5).collect()),
+            ElementType::Record => Dataset::Record(
+                values.iter().enumerate().map(|(i, &v)| Record { key: v as u64, payload: i as u64 }).collect(),
+            ),
+            ElementType::String => Dataset::String(values.iter().map(|v| format!("{v:010}")).collect()),
+        }
+    }
+
+    pub fn is_sorted(&self) -> bool {
+        each_variant!(self, v => v.is_sorted())
+    }
+
+    /// A copy sorted with `slice::sort`, as the oracle for
+    /// `--check-against-std`.
+    pub fn sorted_by_std(&self) -> Dataset {
+        let mut sorted = self.clone();
+        match &mut sorted {
+            Dataset::U32(v) => v.sort(),
+            Dataset::U64(v) => v.sort(),
+            Dataset::F64(v) => v.sort_by(f64::total_cmp),
+            Dataset::Record(v) => v.sort(),
+            Dataset::String(v) => v.sort(),
+        }
+        sorted
+    }
+
+    /// First index where `self` and `expected` differ, counting a length
+    /// difference as a divergence at the end of the shorter one.
+    pub fn first_mismatch(&self, expected: &Dataset) -> Option<usize> {
+        fn position<T: PartialEq>(output: &[T], expected: &[T]) -> Option<usize> {
+            output
+                .iter()
+                .zip(expected)
+                .position(|(a, b)| a != b)
+                .or((output.len() != expected.len()).then(|| output.len().min(expected.len())))
+        }
+        match (self, expected) {
+            (Dataset::U32(a), Dataset::U32(b)) => position(a, b),
+            (Dataset::U64(a), Dataset::U64(b)) => position(a, b),
+            (Dataset::F64(a), Dataset::F64(b)) => position(a, b),
+            (Dataset::Record(a), Dataset::Record(b)) => position(a, b),
+            (Dataset::String(a), Dataset::String(b)) => position(a, b),
+            _ => Some(0),
+        }
+    }
+}
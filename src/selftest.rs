@@ -0,0 +1,101 @@
+//! `selftest` subcommand: runs every sorter on a battery of edge cases and
+//! checks the output against `slice::sort`.
+
+use std::panic::{self, AssertUnwindSafe};
+
+use psrs::PsrsConfig;
+use rand::rngs::StdRng;
+use rand::{Rng, SeedableRng};
+
+use crate::scenarios::{Dataset, ElementType};
+use crate::sorters::{SortContext, SORTERS};
+
+/// Thread counts every parallel sorter is checked with; 64 exceeds the
+/// length of the small cases.
+const THREADS: [usize; 4] = [1, 3, 8, 64];
+
+const ELEMENT_TYPES: [ElementType; 5] =
+    [ElementType::U32, ElementType::U64, ElementType::F64, ElementType::Record, ElementType::String];
+
+/// The inputs, by name. Random ones use fixed seeds so failures reproduce.
+/// The sorted cases are kept small: the `quicksort` crate pivots on the last
+/// element, so it takes quadratic time and linear stack depth on them.
+fn cases() -> Vec<(String, Vec<u32>)> {
+    let random = |seed: u64, n: usize, end: u32| {
+        let mut rng = StdRng::seed_from_u64(seed);
+        (0..n).map(|_| rng.random_range(0..end)).collect::<Vec<u32>>()
+    };
+    let mut cases = vec![
+        ("empty".to_string(), Vec::new()),
+        ("single".to_string(), vec![42]),
+        ("n < p".to_string(), random(1, 5, u32::MAX)),
+        ("n % p != 0".to_string(), random(2, 10_007, u32::MAX)),
+        ("all equal".to_string(), vec![7; 10_000]),
+        ("sorted".to_string(), (0..2_000).collect()),
+        ("reverse".to_string(), (0..2_000).rev().collect()),
+        ("huge duplicates".to_string(), random(3, 100_000, 4)),
+    ];
+    for seed in [10, 11, 12] {
+        cases.push((format!("random seed {seed}"), random(seed, 100_000, u32::MAX)));
+    }
+    cases
+}
+
+/// Runs `selftest` with the arguments that follow the subcommand name.
+pub fn main(args: impl Iterator<Item = String>) {
+    if let Some(arg) = args.into_iter().next() {
+        eprintln!("usage: selftest\nunexpected argument {arg:?}");
+        std::process::exit(2);
+    }
+    let pools: Vec<_> = THREADS
+        .iter()
+        .map(|&threads| {
+            let pool = rayon::ThreadPoolBuilder::new()
+                .num_threads(threads)
+                .build()
+                .expect("failed to build thread pool");
+            (threads, pool)
+        })
+        .collect();
+
+    let mut failed_cases = 0;
+    for (name, values) in cases() {
+        let mut failures = Vec::new();
+        let mut checks = 0;
+        for element in ELEMENT_TYPES {
+            let input = Dataset::from_u32(&values, element);
+            let expected = input.sorted_by_std();
+            for sorter in SORTERS.iter().filter(|s| s.supports(element)) {
+                let pools = if sorter.parallel() { &pools[..] } else { &pools[..1] };
+                for (threads, pool) in pools {
+                    let config = PsrsConfig::with_threads(*threads);
+                    let ctx = SortContext { config: &config };
+                    let mut data = input.clone();
+                    checks += 1;
+                    let outcome = panic::catch_unwind(AssertUnwindSafe(|| pool.install(|| sorter.sort(&mut data, &ctx))));
+                    let problem = match outcome {
+                        Err(_) => Some("panicked".to_string()),
+                        Ok(_) => data.first_mismatch(&expected).map(|i| format!("differs from slice::sort at index {i}")),
+                    };
+                    if let Some(problem) = problem {
+                        failures.push(format!("{} {element:?} {threads} threads: {problem}", sorter.name()));
+                    }
+                }
+            }
+        }
+        if failures.is_empty() {
+            println!("PASS\t{name}\t({checks} checks)");
+        } else {
+            failed_cases += 1;
+            println!("FAIL\t{name}\t({} of {checks} checks)", failures.len());
+            for failure in failures {
+                println!("\t{failure}");
+            }
+        }
+    }
+    if failed_cases > 0 {
+        println!("{failed_cases} cases failed");
+        std::process::exit(1);
+    }
+    println!("all cases passed");
+}
@@ -0,0 +1,643 @@
+//! `serve` subcommand: a long-running sorting service over HTTP, so several
+//! batch pipelines can share one machine.
+//!
+//! Jobs are queued and run one at a time, each on every worker of a single
+//! Rayon pool that lives as long as the service. The worker keeps its
+//! decoding buffers from job to job, so a steady stream of similar jobs
+//! stops allocating them. Input is little-endian values of one type,
+//! uploaded as the request body or read from a file on the server:
+//!
+//! - `POST /sort?type=T` sorts the body and responds with the sorted values.
+//! - `POST /jobs?type=T` queues the body and responds with the job's status,
+//!   including its id.
+//! - `POST /jobs?type=T&input=PATH&output=PATH` queues sorting one file on
+//!   the server into another. Only with `--root`, and both paths must lie
+//!   inside it. On Linux the worker checks the files it opens against the
+//!   root again; elsewhere clients must not be able to write inside the
+//!   root, or they could swap in a symlink after the paths are checked.
+//! - `GET /jobs/ID` responds with the job's status as JSON.
+//! - `GET /jobs/ID/result` responds with the sorted values of a finished
+//!   upload.
+//! - `DELETE /jobs/ID` forgets a job and its result.
+//!
+//! Every connection carries one request; there is no gRPC endpoint. The
+//! service listens on the loopback interface unless told otherwise, handles
+//! at most `--connections` requests at once and refuses bodies over
+//! `--max-body` bytes, as well as request lines or headers over 8 KiB and
+//! requests with over 100 headers. New jobs are refused while
+//! `--max-jobs` are queued or running, and finished jobs whose results are
+//! not collected are forgotten after `--job-ttl` seconds.
+
+use std::collections::HashMap;
+use std::fs;
+use std::io::{self, BufRead, BufReader, Read, Write};
+use std::net::{TcpListener, TcpStream};
+use std::path::{Path, PathBuf};
+use std::sync::{mpsc, Arc, Condvar, Mutex};
+use std::thread;
+use std::time::{Duration, Instant};
+
+use psrs::{psrs_f64, psrs_radix};
+use rayon::prelude::*;
+use serde::Serialize;
+
+/// Element type of a job's values.
+#[derive(Clone, Copy)]
+enum ValueType {
+    U32,
+    U64,
+    I32,
+    I64,
+    F64,
+}
+
+impl ValueType {
+    fn parse(name: &str) -> Option<ValueType> {
+        match name {
+            "u32" => Some(ValueType::U32),
+            "u64" => Some(ValueType::U64),
+            "i32" => Some(ValueType::I32),
+            "i64" => Some(ValueType::I64),
+            "f64" => Some(ValueType::F64),
+            _ => None,
+        }
+    }
+}
+
+/// How long a connection may stall while sending its request before it is
+/// dropped, so idle clients cannot hold on to a connection slot.
+const READ_TIMEOUT: Duration = Duration::from_secs(60);
+
+/// Longest request line or header accepted, in bytes.
+const MAX_LINE: usize = 8 << 10;
+
+/// Most headers accepted in one request.
+const MAX_HEADERS: usize = 100;
+
+/// Options of `serve`, see [`usage`].
+struct ServeArgs {
+    listen: String,
+    threads: usize,
+    /// Directory server-side file jobs are confined to; without it they are
+    /// refused.
+    root: Option<PathBuf>,
+    /// Largest request body accepted, in bytes.
+    max_body: usize,
+    /// Requests handled at once; further connections wait to be accepted.
+    connections: usize,
+    /// Jobs queued or running at once; further jobs are refused.
+    max_jobs: usize,
+    /// How long finished jobs are kept for their status and result.
+    job_ttl: Duration,
+}
+
+fn usage() -> ! {
+    eprintln!(
+        "usage: serve [--listen [HOST]:PORT] [--threads N] [--root DIR] [--max-body BYTES] \
+         [--connections N] [--max-jobs N] [--job-ttl SECS]\n\
+         Serves sort jobs over HTTP on HOST:PORT (by default 127.0.0.1:8080; give \
+         0.0.0.0:PORT to serve every interface), sorting on a pool of N threads. Values are \
+         little-endian u32, u64, i32, i64 or f64; see the `serve` module for the endpoints. \
+         Jobs reading and writing files on the server are only accepted with --root, for \
+         paths inside DIR. Bodies over --max-body (default 1 GiB) are refused, and at most \
+         --connections (default 64) requests are handled at once. New jobs are refused \
+         with 503 while --max-jobs (default 64) are queued or running, and finished jobs \
+         are forgotten --job-ttl (default 600) seconds after they finish."
+    );
+    std::process::exit(2)
+}
+
+fn parse_args(mut args: impl Iterator<Item = String>) -> ServeArgs {
+    let mut parsed = ServeArgs {
+        listen: ":8080".to_string(),
+        threads: std::thread::available_parallelism().map_or(1, |n| n.get()),
+        root: None,
+        max_body: 1 << 30,
+        connections: 64,
+        max_jobs: 64,
+        job_ttl: Duration::from_secs(600),
+    };
+    while let Some(arg) = args.next() {
+        let mut value = || args.next().unwrap_or_else(|| usage());
+        match arg.as_str() {
+            "--listen" => parsed.listen = value(),
+            "--threads" => parsed.threads = value().parse().unwrap_or_else(|_| usage()),
+            "--root" => parsed.root = Some(value().into()),
+            "--max-body" => parsed.max_body = value().parse().unwrap_or_else(|_| usage()),
+            "--connections" => parsed.connections = value().parse().unwrap_or_else(|_| usage()),
+            "--max-jobs" => parsed.max_jobs = value().parse().unwrap_or_else(|_| usage()),
+            "--job-ttl" => parsed.job_ttl = Duration::from_secs(value().parse().unwrap_or_else(|_| usage())),
+            _ => usage(),
+        }
+    }
+    if parsed.listen.starts_with(':') {
+        parsed.listen.insert_str(0, "127.0.0.1");
+    }
+    if parsed.connections == 0 || parsed.max_jobs == 0 {
+        usage();
+    }
+    parsed.root = parsed.root.map(|root| {
+        fs::canonicalize(&root).unwrap_or_else(|e| {
+            eprintln!("serve: --root {}: {e}", root.display());
+            std::process::exit(2);
+        })
+    });
+    parsed
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Serialize)]
+#[serde(rename_all = "snake_case")]
+enum JobState {
+    Queued,
+    Running,
+    Done,
+    Failed,
+}
+
+/// What `GET /jobs/ID` reports.
+#[derive(Debug, Clone, Serialize)]
+struct JobStatus {
+    id: u64,
+    state: JobState,
+    /// Values sorted, once done.
+    elements: Option<usize>,
+    /// Time spent reading, sorting and writing, once done.
+    millis: Option<f64>,
+    error: Option<String>,
+}
+
+/// Where a job's values come from and go to.
+enum Source {
+    /// The request body, replaced by the sorted values when done.
+    Upload(Vec<u8>),
+    File { input: PathBuf, output: PathBuf },
+}
+
+struct Job {
+    value_type: ValueType,
+    source: Source,
+    status: JobStatus,
+    /// When the job was done or failed.
+    finished: Option<Instant>,
+}
+
+/// State shared by the connection threads and the worker.
+struct Service {
+    jobs: Mutex<HashMap<u64, Job>>,
+    next_id: Mutex<u64>,
+    /// Signalled whenever a job finishes.
+    finished: Condvar,
+    queue: mpsc::Sender<u64>,
+    threads: usize,
+    /// Canonical `--root`, if file jobs are allowed.
+    root: Option<PathBuf>,
+    max_body: usize,
+    max_jobs: usize,
+    job_ttl: Duration,
+}
+
+impl Service {
+    /// Queues a job, or responds with 503 if `max_jobs` are already queued
+    /// or running. Forgets the finished jobs older than `job_ttl` first.
+    fn submit(&self, value_type: ValueType, source: Source) -> Result<JobStatus, Response> {
+        let mut jobs = self.jobs.lock().unwrap();
+        jobs.retain(|_, job| job.finished.is_none_or(|at| at.elapsed() < self.job_ttl));
+        let pending = jobs.values().filter(|job| job.finished.is_none()).count();
+        if pending >= self.max_jobs {
+            let message = format!("the queue is full with {pending} jobs, try again later");
+            return Err(Response::error("503 Service Unavailable", message));
+        }
+        let id = {
+            let mut next_id = self.next_id.lock().unwrap();
+            *next_id += 1;
+            *next_id
+        };
+        let status = JobStatus { id, state: JobState::Queued, elements: None, millis: None, error: None };
+        jobs.insert(id, Job { value_type, source, status: status.clone(), finished: None });
+        self.queue.send(id).expect("the worker outlives the listener");
+        Ok(status)
+    }
+
+    /// Resolves `path`, relative to the root, to a canonical path inside
+    /// it. An `output` need not exist yet, but its directory must. The
+    /// worker checks the files it opens again, see [`check_opened`].
+    fn confine(&self, path: &str, output: bool) -> Result<PathBuf, Response> {
+        let Some(root) = &self.root else {
+            return Err(Response::error("403 Forbidden", "file jobs need the server to run with --root"));
+        };
+        let path = root.join(path);
+        let outside = || Response::error("403 Forbidden", format!("{} is outside the root", path.display()));
+        let resolved = match (output, path.file_name()) {
+            (true, Some(name)) => {
+                let parent = path.parent().unwrap_or(Path::new("/"));
+                fs::canonicalize(parent).map(|dir| dir.join(name))
+            }
+            (true, None) => return Err(outside()),
+            (false, _) => fs::canonicalize(&path),
+        };
+        let resolved = resolved.map_err(|e| Response::error("400 Bad Request", format!("{}: {e}", path.display())))?;
+        // An existing output may itself be a symlink out of the root.
+        let resolved = match output && resolved.is_symlink() {
+            true => fs::canonicalize(&resolved).map_err(|_| outside())?,
+            false => resolved,
+        };
+        match resolved.starts_with(root) {
+            true => Ok(resolved),
+            false => Err(outside()),
+        }
+    }
+
+    fn status(&self, id: u64) -> Option<JobStatus> {
+        self.jobs.lock().unwrap().get(&id).map(|job| job.status.clone())
+    }
+
+    /// Blocks until job `id` is done or failed and removes it. `None` if
+    /// it was deleted in the meantime.
+    fn wait_and_remove(&self, id: u64) -> Option<Job> {
+        let mut jobs = self.jobs.lock().unwrap();
+        loop {
+            match jobs.get(&id)?.status.state {
+                JobState::Done | JobState::Failed => return jobs.remove(&id),
+                JobState::Queued | JobState::Running => jobs = self.finished.wait(jobs).unwrap(),
+            }
+        }
+    }
+}
+
+/// Runs `serve` with the arguments that follow the subcommand name.
+pub fn main(args: impl Iterator<Item = String>) {
+    let args = parse_args(args);
+    let pool = rayon::ThreadPoolBuilder::new().num_threads(args.threads).build().expect("failed to build thread pool");
+    let listener =
+        TcpListener::bind(&args.listen).unwrap_or_else(|e| panic!("failed to listen on {}: {e}", args.listen));
+    let (queue, jobs) = mpsc::channel();
+    let service = Arc::new(Service {
+        jobs: Mutex::new(HashMap::new()),
+        next_id: Mutex::new(0),
+        finished: Condvar::new(),
+        queue,
+        threads: args.threads,
+        root: args.root,
+        max_body: args.max_body,
+        max_jobs: args.max_jobs,
+        job_ttl: args.job_ttl,
+    });
+    {
+        let service = Arc::clone(&service);
+        thread::spawn(move || run_worker(&service, jobs, &pool));
+    }
+    println!("serving on {} with {} threads", args.listen, args.threads);
+    // A fixed set of handlers take turns accepting, so no more than
+    // `connections` requests are read or waited on at once.
+    thread::scope(|s| {
+        for _ in 0..args.connections {
+            s.spawn(|| {
+                for stream in listener.incoming() {
+                    let Ok(stream) = stream else { continue };
+                    if let Err(e) = handle_connection(&service, stream) {
+                        eprintln!("serve: {e}");
+                    }
+                }
+            });
+        }
+    });
+}
+
+/// Buffers the worker decodes values into, kept from job to job.
+#[derive(Default)]
+struct Scratch {
+    u32: Vec<u32>,
+    u64: Vec<u64>,
+    i32: Vec<i32>,
+    i64: Vec<i64>,
+    f64: Vec<f64>,
+}
+
+/// Runs the queued jobs one after another on `pool`.
+fn run_worker(service: &Service, queue: mpsc::Receiver<u64>, pool: &rayon::ThreadPool) {
+    let mut scratch = Scratch::default();
+    for id in queue {
+        let (value_type, source) = {
+            let mut jobs = service.jobs.lock().unwrap();
+            // Deleted while it was queued.
+            let Some(job) = jobs.get_mut(&id) else { continue };
+            job.status.state = JobState::Running;
+            let source = match &mut job.source {
+                Source::Upload(data) => Source::Upload(std::mem::take(data)),
+                Source::File { input, output } => Source::File { input: input.clone(), output: output.clone() },
+            };
+            (job.value_type, source)
+        };
+
+        let start = Instant::now();
+        let result = pool.install(|| match source {
+            Source::Upload(mut data) => {
+                sort_bytes(&mut data, value_type, service.threads, &mut scratch).map(|len| (len, Some(data)))
+            }
+            Source::File { input, output } => {
+                let root = service.root.as_deref().expect("file jobs need a root");
+                let mut data = read_file(&input, root, service.max_body)?;
+                let len = sort_bytes(&mut data, value_type, service.threads, &mut scratch)?;
+                write_file(&output, root, &data)?;
+                Ok((len, None))
+            }
+        });
+
+        let mut jobs = service.jobs.lock().unwrap();
+        if let Some(job) = jobs.get_mut(&id) {
+            job.finished = Some(Instant::now());
+            match result {
+                Ok((len, data)) => {
+                    job.status.state = JobState::Done;
+                    job.status.elements = Some(len);
+                    job.status.millis = Some(start.elapsed().as_secs_f64() * 1000.0);
+                    if let Some(data) = data {
+                        job.source = Source::Upload(data);
+                    }
+                }
+                Err(e) => {
+                    job.status.state = JobState::Failed;
+                    job.status.error = Some(e);
+                }
+            }
+        }
+        service.finished.notify_all();
+    }
+}
+
+/// Reads the file at `path` inside `root`, failing if it is over `max` bytes
+/// like an upload over `--max-body` would.
+fn read_file(path: &Path, root: &Path, max: usize) -> Result<Vec<u8>, String> {
+    let failed = |e: io::Error| format!("failed to read {}: {e}", path.display());
+    let file = fs::File::open(path).map_err(failed)?;
+    check_opened(&file, path, root)?;
+    let mut data = Vec::new();
+    file.take(max as u64 + 1).read_to_end(&mut data).map_err(failed)?;
+    if data.len() > max {
+        return Err(format!("{} is over the limit of {max} bytes", path.display()));
+    }
+    Ok(data)
+}
+
+/// Replaces the contents of the file at `path` inside `root` with `data`.
+/// The file is only truncated once it is known to be inside the root.
+fn write_file(path: &Path, root: &Path, data: &[u8]) -> Result<(), String> {
+    let failed = |e: io::Error| format!("failed to write {}: {e}", path.display());
+    let mut file = fs::OpenOptions::new().write(true).create(true).truncate(false).open(path).map_err(failed)?;
+    check_opened(&file, path, root)?;
+    file.set_len(0).and_then(|()| file.write_all(data)).map_err(failed)
+}
+
+/// Fails unless `file`, opened from `path`, lies inside `root`.
+///
+/// [`Service::confine`] checks the path when the job is submitted, but a
+/// symlink swapped in before the worker opens it would lead elsewhere. On
+/// Linux this checks where the open actually led; elsewhere it relies on
+/// clients not being able to write inside the root.
+fn check_opened(file: &fs::File, path: &Path, root: &Path) -> Result<(), String> {
+    #[cfg(target_os = "linux")]
+    {
+        use std::os::fd::AsRawFd;
+        let opened = fs::read_link(format!("/proc/self/fd/{}", file.as_raw_fd()))
+            .map_err(|e| format!("failed to check {}: {e}", path.display()))?;
+        if !opened.starts_with(root) {
+            return Err(format!("{} is outside the root", path.display()));
+        }
+    }
+    #[cfg(not(target_os = "linux"))]
+    let _ = (file, path, root);
+    Ok(())
+}
+
+/// Sorts `data` in place as little-endian values of `value_type` and
+/// returns how many there are.
+fn sort_bytes(data: &mut [u8], value_type: ValueType, p: usize, scratch: &mut Scratch) -> Result<usize, String> {
+    match value_type {
+        ValueType::U32 => sort_values(data, &mut scratch.u32, p, u32::from_le_bytes, u32::to_le_bytes, psrs_radix),
+        ValueType::U64 => sort_values(data, &mut scratch.u64, p, u64::from_le_bytes, u64::to_le_bytes, psrs_radix),
+        ValueType::I32 => sort_values(data, &mut scratch.i32, p, i32::from_le_bytes, i32::to_le_bytes, psrs_radix),
+        ValueType::I64 => sort_values(data, &mut scratch.i64, p, i64::from_le_bytes, i64::to_le_bytes, psrs_radix),
+        ValueType::F64 => sort_values(data, &mut scratch.f64, p, f64::from_le_bytes, f64::to_le_bytes, psrs_f64),
+    }
+}
+
+/// Decodes `data` as `N`-byte values into `values`, sorts them with `sort`
+/// and encodes them back over `data`.
+fn sort_values<T: Copy + Send + Sync, const N: usize>(
+    data: &mut [u8],
+    values: &mut Vec<T>,
+    p: usize,
+    decode: fn([u8; N]) -> T,
+    encode: fn(T) -> [u8; N],
+    sort: fn(&mut [T], usize),
+) -> Result<usize, String> {
+    if !data.len().is_multiple_of(N) {
+        return Err(format!("input is {} bytes, not a whole number of {N}-byte values", data.len()));
+    }
+    values.clear();
+    data.par_chunks_exact(N).map(|bytes| decode(bytes.try_into().expect("chunk of N bytes"))).collect_into_vec(values);
+    sort(values, p);
+    data.par_chunks_exact_mut(N).zip(values.par_iter()).for_each(|(bytes, &value)| bytes.copy_from_slice(&encode(value)));
+    Ok(values.len())
+}
+
+/// One parsed HTTP request.
+struct Request {
+    method: String,
+    /// Path segments, e.g. `["jobs", "7"]`.
+    path: Vec<String>,
+    query: HashMap<String, String>,
+    body: Vec<u8>,
+}
+
+/// One HTTP response.
+struct Response {
+    status: &'static str,
+    content_type: &'static str,
+    body: Vec<u8>,
+}
+
+impl Response {
+    fn json(status: &'static str, value: &impl Serialize) -> Response {
+        let body = serde_json::to_vec(value).expect("statuses serialize");
+        Response { status, content_type: "application/json", body }
+    }
+
+    fn error(status: &'static str, message: impl Into<String>) -> Response {
+        Response::json(status, &HashMap::from([("error", message.into())]))
+    }
+}
+
+fn handle_connection(service: &Service, stream: TcpStream) -> io::Result<()> {
+    stream.set_read_timeout(Some(READ_TIMEOUT))?;
+    let mut reader = BufReader::new(stream.try_clone()?);
+    let response = match read_request(&mut reader, &stream, service.max_body)? {
+        Ok(request) => route(service, request),
+        Err(response) => response,
+    };
+    let mut stream = stream;
+    write!(
+        stream,
+        "HTTP/1.1 {}\r\nContent-Type: {}\r\nContent-Length: {}\r\nConnection: close\r\n\r\n",
+        response.status,
+        response.content_type,
+        response.body.len()
+    )?;
+    stream.write_all(&response.body)?;
+    stream.flush()
+}
+
+/// Reads the request line, headers and body, or the error response if they
+/// do not parse, a line is over [`MAX_LINE`] bytes, there are over
+/// [`MAX_HEADERS`] headers or the body is over `max_body` bytes. Answers
+/// `Expect: 100-continue` before reading the body.
+fn read_request(
+    reader: &mut BufReader<TcpStream>,
+    mut stream: &TcpStream,
+    max_body: usize,
+) -> io::Result<Result<Request, Response>> {
+    let malformed = || Ok(Err(Response::error("400 Bad Request", "malformed request")));
+    let mut line = Vec::new();
+    if !read_line(reader, &mut line)? {
+        return Ok(Err(Response::error("414 URI Too Long", format!("the request line is over {MAX_LINE} bytes"))));
+    }
+    let Ok(line) = std::str::from_utf8(&line) else { return malformed() };
+    let mut parts = line.split_whitespace();
+    let (Some(method), Some(target)) = (parts.next(), parts.next()) else { return malformed() };
+    let method = method.to_string();
+    let (path, query) = target.split_once('?').unwrap_or((target, ""));
+    let path = path.split('/').filter(|s| !s.is_empty()).map(percent_decode).collect();
+    let query = query
+        .split('&')
+        .filter_map(|pair| pair.split_once('='))
+        .map(|(key, value)| (percent_decode(key), percent_decode(value)))
+        .collect();
+
+    let too_large = |message: String| Ok(Err(Response::error("431 Request Header Fields Too Large", message)));
+    let (mut content_length, mut expect_continue) = (0, false);
+    let mut line = Vec::new();
+    for headers in 0.. {
+        if !read_line(reader, &mut line)? {
+            return too_large(format!("a header is over {MAX_LINE} bytes"));
+        }
+        let Ok(header) = std::str::from_utf8(&line) else { return malformed() };
+        let header = header.trim_end();
+        if header.is_empty() {
+            break;
+        }
+        if headers == MAX_HEADERS {
+            return too_large(format!("the request has over {MAX_HEADERS} headers"));
+        }
+        let Some((name, value)) = header.split_once(':') else { return malformed() };
+        let value = value.trim();
+        if name.eq_ignore_ascii_case("content-length") {
+            let Ok(length) = value.parse() else { return malformed() };
+            content_length = length;
+        } else if name.eq_ignore_ascii_case("expect") && value.eq_ignore_ascii_case("100-continue") {
+            expect_continue = true;
+        }
+    }
+    if content_length > max_body {
+        let message = format!("the body is {content_length} bytes, over the limit of {max_body}");
+        return Ok(Err(Response::error("413 Content Too Large", message)));
+    }
+    if expect_continue {
+        stream.write_all(b"HTTP/1.1 100 Continue\r\n\r\n")?;
+    }
+    let mut body = vec![0; content_length];
+    reader.read_exact(&mut body)?;
+    Ok(Ok(Request { method, path, query, body }))
+}
+
+/// Reads one line, newline included, into `line`, buffering at most
+/// [`MAX_LINE`] bytes of it. `false` if the line is longer than that.
+fn read_line(reader: &mut impl BufRead, line: &mut Vec<u8>) -> io::Result<bool> {
+    line.clear();
+    reader.take(MAX_LINE as u64 + 1).read_until(b'\n', line)?;
+    Ok(line.len() <= MAX_LINE)
+}
+
+/// Decodes `%XX` escapes and `+` in a URL component.
+fn percent_decode(s: &str) -> String {
+    let mut bytes = Vec::with_capacity(s.len());
+    let mut input = s.bytes();
+    while let Some(b) = input.next() {
+        match b {
+            b'%' => {
+                let hex = [input.next().unwrap_or(b'0'), input.next().unwrap_or(b'0')];
+                let value = std::str::from_utf8(&hex).ok().and_then(|h| u8::from_str_radix(h, 16).ok());
+                bytes.push(value.unwrap_or(b'?'));
+            }
+            b'+' => bytes.push(b' '),
+            _ => bytes.push(b),
+        }
+    }
+    String::from_utf8_lossy(&bytes).into_owned()
+}
+
+fn route(service: &Service, request: Request) -> Response {
+    let path: Vec<&str> = request.path.iter().map(String::as_str).collect();
+    let no_such_job = || Response::error("404 Not Found", "no such job");
+    match (request.method.as_str(), path.as_slice()) {
+        ("POST", ["sort"]) => {
+            let Some(value_type) = request.query.get("type").and_then(|t| ValueType::parse(t)) else {
+                return Response::error("400 Bad Request", "missing or unknown type");
+            };
+            let id = match service.submit(value_type, Source::Upload(request.body)) {
+                Ok(status) => status.id,
+                Err(response) => return response,
+            };
+            let Some(job) = service.wait_and_remove(id) else {
+                return Response::error("410 Gone", "the job was deleted");
+            };
+            match (job.status.state, job.source) {
+                (JobState::Done, Source::Upload(data)) => {
+                    Response { status: "200 OK", content_type: "application/octet-stream", body: data }
+                }
+                _ => Response::error("422 Unprocessable Entity", job.status.error.unwrap_or_default()),
+            }
+        }
+        ("POST", ["jobs"]) => {
+            let Some(value_type) = request.query.get("type").and_then(|t| ValueType::parse(t)) else {
+                return Response::error("400 Bad Request", "missing or unknown type");
+            };
+            let source = match (request.query.get("input"), request.query.get("output")) {
+                (None, None) => Source::Upload(request.body),
+                (Some(input), Some(output)) => {
+                    let paths = service.confine(input, false).and_then(|input| Ok((input, service.confine(output, true)?)));
+                    match paths {
+                        Ok((input, output)) => Source::File { input, output },
+                        Err(response) => return response,
+                    }
+                }
+                _ => return Response::error("400 Bad Request", "input and output go together"),
+            };
+            match service.submit(value_type, source) {
+                Ok(status) => Response::json("202 Accepted", &status),
+                Err(response) => response,
+            }
+        }
+        ("GET", ["jobs", id]) => match id.parse().ok().and_then(|id| service.status(id)) {
+            Some(status) => Response::json("200 OK", &status),
+            None => no_such_job(),
+        },
+        ("GET", ["jobs", id, "result"]) => {
+            let jobs = service.jobs.lock().unwrap();
+            let Some(job) = id.parse().ok().and_then(|id: u64| jobs.get(&id)) else { return no_such_job() };
+            match job {
+                Job { status: JobStatus { state: JobState::Done, .. }, source: Source::Upload(data), .. } => {
+                    Response { status: "200 OK", content_type: "application/octet-stream", body: data.clone() }
+                }
+                Job { source: Source::File { output, .. }, .. } => {
+                    Response::error("409 Conflict", format!("the result is written to {}", output.display()))
+                }
+                job => Response::error("409 Conflict", format!("the job is {:?}", job.status.state)),
+            }
+        }
+        ("DELETE", ["jobs", id]) => match id.parse().ok().and_then(|id: u64| service.jobs.lock().unwrap().remove(&id)) {
+            Some(_) => Response { status: "204 No Content", content_type: "text/plain", body: Vec::new() },
+            None => no_such_job(),
+        },
+        _ => Response::error("404 Not Found", "no such endpoint"),
+    }
+}
@@ -0,0 +1,173 @@
+use std::cmp::Ordering;
+use std::ops::Range;
+use std::sync::Mutex;
+
+use crate::executor::{self, default_executor};
+
+/// Runs at most this long are counted by insertion sort.
+const INSERTION_RUN: usize = 32;
+
+/// Number of inversions in `data`: pairs `i < j` with `data[i] > data[j]`.
+///
+/// Like [`psrs`](crate::psrs), the input is split into `p` chunks whose
+/// inversions are counted in parallel by merge sort; the sorted chunks are
+/// then merged pairwise, level by level, counting the inversions between
+/// them. `data` itself is left untouched; the work happens on two clones of it.
+pub fn inversions<T: Clone + Ord + Send + Sync>(data: &[T], p: usize) -> u64 {
+    inversions_by(data, p, T::cmp)
+}
+
+/// Like [`inversions`], but orders elements with `compare` instead of `Ord`.
+/// Elements comparing equal are not inversions.
+pub fn inversions_by<T, F>(data: &[T], p: usize, compare: F) -> u64
+where
+    T: Clone + Send + Sync,
+    F: Fn(&T, &T) -> Ordering + Sync,
+{
+    let exec = default_executor();
+    let n = data.len();
+    if n < 2 {
+        return 0;
+    }
+    let chunk_size = n.div_ceil(p.max(1));
+    let mut runs = data.to_vec();
+    let mut scratch = data.to_vec();
+    let chunks: Vec<Mutex<(&mut [T], &mut [T])>> =
+        runs.chunks_mut(chunk_size).zip(scratch.chunks_mut(chunk_size)).map(Mutex::new).collect();
+    let counts = executor::par_map(exec, chunks.len(), |i| {
+        let (chunk, chunk_scratch) = &mut *chunks[i].lock().unwrap();
+        sort_counting(chunk, chunk_scratch, &compare)
+    });
+    let mut total: u64 = counts.into_iter().sum();
+
+    let mut ranges: Vec<Range<usize>> = (0..n).step_by(chunk_size).map(|s| s..(s + chunk_size).min(n)).collect();
+    while ranges.len() > 1 {
+        // Each task merges two neighbouring runs of `runs` into the matching
+        // stretch of `scratch`; an odd last run is copied over as is.
+        let mut tasks = Vec::with_capacity(ranges.len().div_ceil(2));
+        let mut rest = &mut scratch[..];
+        for pair in ranges.chunks(2) {
+            let (out, tail) = rest.split_at_mut(pair.iter().map(|r| r.len()).sum());
+            rest = tail;
+            let right = pair.get(1).map_or(&[][..], |r| &runs[r.clone()]);
+            tasks.push(Mutex::new((&runs[pair[0].clone()], right, out)));
+        }
+        let counts = executor::par_map(exec, tasks.len(), |i| {
+            let (left, right, out) = &mut *tasks[i].lock().unwrap();
+            merge_counting(left, right, out, &compare)
+        });
+        total += counts.into_iter().sum::<u64>();
+        ranges = ranges.chunks(2).map(|pair| pair[0].start..pair[pair.len() - 1].end).collect();
+        std::mem::swap(&mut runs, &mut scratch);
+    }
+    total
+}
+
+/// How close `data` is to sorted: 1.0 for non-decreasing input, 0.0 for
+/// strictly decreasing input, and about 0.5 for random input. It is one
+/// minus the share of the `n * (n - 1) / 2` pairs that are [`inversions`].
+pub fn sortedness<T: Clone + Ord + Send + Sync>(data: &[T], p: usize) -> f64 {
+    let n = data.len() as f64;
+    if data.len() < 2 {
+        return 1.0;
+    }
+    1.0 - inversions(data, p) as f64 / (n * (n - 1.0) / 2.0)
+}
+
+/// Sorts `data` by merge sort and returns its inversion count. `scratch` must
+/// be as long as `data`.
+fn sort_counting<T, F>(data: &mut [T], scratch: &mut [T], compare: &F) -> u64
+where
+    T: Clone,
+    F: Fn(&T, &T) -> Ordering,
+{
+    let n = data.len();
+    if n <= INSERTION_RUN {
+        return insertion_counting(data, compare);
+    }
+    let mid = n / 2;
+    let (left, right) = data.split_at_mut(mid);
+    let (left_scratch, right_scratch) = scratch.split_at_mut(mid);
+    let inner = sort_counting(left, left_scratch, compare) + sort_counting(right, right_scratch, compare);
+    let across = merge_counting(left, right, scratch, compare);
+    data.clone_from_slice(scratch);
+    inner + across
+}
+
+/// Insertion sort, which moves every element past exactly the elements it
+/// forms inversions with.
+fn insertion_counting<T, F: Fn(&T, &T) -> Ordering>(data: &mut [T], compare: &F) -> u64 {
+    let mut count = 0;
+    for i in 1..data.len() {
+        let mut j = i;
+        while j > 0 && compare(&data[j - 1], &data[j]) == Ordering::Greater {
+            data.swap(j - 1, j);
+            j -= 1;
+        }
+        count += (i - j) as u64;
+    }
+    count
+}
+
+/// Merges sorted `left` and `right` into `out` and returns the number of
+/// pairs with the `left` element greater. Ties take from `left`, so equal
+/// elements never count.
+fn merge_counting<T, F>(left: &[T], right: &[T], out: &mut [T], compare: &F) -> u64
+where
+    T: Clone,
+    F: Fn(&T, &T) -> Ordering,
+{
+    let (mut i, mut j, mut count) = (0, 0, 0);
+    for slot in out.iter_mut() {
+        if j == right.len() || i < left.len() && compare(&left[i], &right[j]) != Ordering::Greater {
+            slot.clone_from(&left[i]);
+            i += 1;
+        } else {
+            // Every element still left in `left` is greater than this one.
+            count += (left.len() - i) as u64;
+            slot.clone_from(&right[j]);
+            j += 1;
+        }
+    }
+    count
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::testing::edge_cases;
+
+    /// Inversions counted pair by pair.
+    fn naive_inversions(data: &[u64]) -> u64 {
+        let mut count = 0;
+        for i in 0..data.len() {
+            count += data[i + 1..].iter().filter(|&&y| y < data[i]).count() as u64;
+        }
+        count
+    }
+
+    #[test]
+    fn inversions_match_a_pairwise_count() {
+        for p in [1, 4, 7] {
+            for (name, values) in edge_cases(p) {
+                // Short enough for the quadratic count, long enough for
+                // every chunk to go through the merge sort.
+                let values: Vec<u64> = values.iter().take(2_500).map(|v| v % 500).collect();
+                assert_eq!(inversions(&values, p), naive_inversions(&values), "{name}, p = {p}");
+            }
+        }
+    }
+
+    #[test]
+    fn sortedness_spans_zero_to_one() {
+        let ascending: Vec<u32> = (0..1_000).collect();
+        let descending: Vec<u32> = (0..1_000).rev().collect();
+        assert_eq!(sortedness(&ascending, 4), 1.0);
+        assert_eq!(sortedness(&descending, 4), 0.0);
+        assert_eq!(sortedness(&[5u8; 100], 4), 1.0);
+        assert_eq!(sortedness::<u8>(&[], 4), 1.0);
+        let random = sortedness(&crate::testing::random_values(10_000, u64::MAX, 20), 4);
+        assert!((random - 0.5).abs() < 0.02, "{random}");
+        assert_eq!(inversions_by(&ascending, 4, |a, b| b.cmp(a)), 1_000 * 999 / 2);
+    }
+}
@@ -0,0 +1,408 @@
+//! The algorithms the benchmark harness knows how to run.
+
+use psrs::{
+    psrs_by_with_local_sort, psrs_by_with_stats, psrs_integers, psrs_with_local_sort, psrs_with_stats, MergeStructure,
+    PartitionStats, PsrsConfig,
+};
+use quicksort::{quicksort, quicksort_by};
+use rayon::prelude::*;
+
+use crate::scenarios::{Dataset, ElementType};
+
+/// What a sorter may use besides the data. Built once per configuration so
+/// its setup is not part of the timed sort. Sorters run inside a Rayon pool
+/// of exactly `config.threads` workers.
+pub struct SortContext<'a> {
+    pub config: &'a PsrsConfig,
+}
+
+/// A sorting algorithm the harness can benchmark.
+pub trait Sorter: Sync {
+    /// Name used in configs, tables and reports.
+    fn name(&self) -> &'static str;
+    /// Parallel sorters run once per thread count, serial ones once as
+    /// baselines.
+    fn parallel(&self) -> bool;
+    /// Whether the sorter handles `element`; unsupported scenarios are
+    /// skipped.
+    fn supports(&self, _element: ElementType) -> bool {
+        true
+    }
+    /// Sorts `data`, returning the partition balance when the sorter
+    /// exposes it.
+    fn sort(&self, data: &mut Dataset, ctx: &SortContext) -> Option<PartitionStats>;
+}
+
+/// Sorts any [`Dataset`], running `$sort` on the `Ord` element types and
+/// `$sort_f64` on floats.
+macro_rules! sort_dataset {
+    ($data:expr, |$v:ident| $sort:expr, |$f:ident| $sort_f64:expr) => {
+        match $data {
+            Dataset::U32($v) => $sort,
+            Dataset::U64($v) => $sort,
+            Dataset::Record($v) => $sort,
+            Dataset::String($v) => $sort,
+            Dataset::F64($f) => $sort_f64,
+        }
+    };
+}
+
+/// Every available sorter. Serial baselines come first.
+pub const SORTERS: &[&dyn Sorter] = &[
+    &Serial,
+    &SortUnstable,
+    #[cfg(feature = "voracious")]
+    &Voracious,
+    #[cfg(feature = "rdxsort")]
+    &Rdxsort,
+    #[cfg(feature = "glidesort")]
+    &Glidesort,
+    &Psrs,
+    &PsrsStd,
+    &PsrsTuned,
+    &PsrsMerge(MergeStructure::QuaternaryHeap),
+    &PsrsMerge(MergeStructure::LoserTree),
+    &PsrsIntegers,
+    #[cfg(all(feature = "hugepages", target_os = "linux"))]
+    &PsrsHugePages,
+    &ParSortUnstable,
+    #[cfg(feature = "voracious")]
+    &VoraciousMt,
+];
+
+/// Looks up a sorter in [`SORTERS`] by name.
+pub fn find(name: &str) -> Option<&'static dyn Sorter> {
+    SORTERS.iter().copied().find(|s| s.name() == name)
+}
+
+/// The `quicksort` crate, the original serial baseline.
+struct Serial;
+
+impl Sorter for Serial {
+    fn name(&self) -> &'static str {
+        "serial"
+    }
+
+    fn parallel(&self) -> bool {
+        false
+    }
+
+    fn sort(&self, data: &mut Dataset, _ctx: &SortContext) -> Option<PartitionStats> {
+        sort_dataset!(data, |v| quicksort(v), |v| quicksort_by(v, f64::total_cmp));
+        None
+    }
+}
+
+struct SortUnstable;
+
+impl Sorter for SortUnstable {
+    fn name(&self) -> &'static str {
+        "sort_unstable"
+    }
+
+    fn parallel(&self) -> bool {
+        false
+    }
+
+    fn sort(&self, data: &mut Dataset, _ctx: &SortContext) -> Option<PartitionStats> {
+        sort_dataset!(data, |v| v.sort_unstable(), |v| v.sort_unstable_by(f64::total_cmp));
+        None
+    }
+}
+
+/// PSRS sorting the chunks with the `quicksort` crate, as the harness's
+/// numbers always have.
+struct Psrs;
+
+impl Sorter for Psrs {
+    fn name(&self) -> &'static str {
+        "psrs"
+    }
+
+    fn parallel(&self) -> bool {
+        true
+    }
+
+    fn sort(&self, data: &mut Dataset, ctx: &SortContext) -> Option<PartitionStats> {
+        let stats = sort_dataset!(
+            data,
+            |v| psrs_with_local_sort(v, ctx.config, quicksort),
+            |v| psrs_by_with_local_sort(v, ctx.config, f64::total_cmp, |c| quicksort_by(c, f64::total_cmp))
+        );
+        Some(stats)
+    }
+}
+
+/// PSRS as library users get it, sorting the chunks with `sort_unstable`
+/// where [`Psrs`] uses the `quicksort` crate.
+struct PsrsStd;
+
+impl Sorter for PsrsStd {
+    fn name(&self) -> &'static str {
+        "psrs_std"
+    }
+
+    fn parallel(&self) -> bool {
+        true
+    }
+
+    fn sort(&self, data: &mut Dataset, ctx: &SortContext) -> Option<PartitionStats> {
+        let stats = sort_dataset!(
+            data,
+            |v| psrs_with_stats(v, ctx.config),
+            |v| psrs_by_with_stats(v, ctx.config, f64::total_cmp)
+        );
+        Some(stats)
+    }
+}
+
+/// PSRS with the chunk count picked from the cache sizes rather than
+/// `config`, for comparing the heuristic against the sweep.
+struct PsrsTuned;
+
+impl Sorter for PsrsTuned {
+    fn name(&self) -> &'static str {
+        "psrs_tuned"
+    }
+
+    fn parallel(&self) -> bool {
+        true
+    }
+
+    fn sort(&self, data: &mut Dataset, ctx: &SortContext) -> Option<PartitionStats> {
+        let threads = ctx.config.threads;
+        let stats = sort_dataset!(
+            data,
+            |v| {
+                let config = PsrsConfig::tuned_for(v, threads);
+                psrs_with_local_sort(v, &config, quicksort)
+            },
+            |v| {
+                let config = PsrsConfig::tuned_for(v, threads);
+                psrs_by_with_local_sort(v, &config, f64::total_cmp, |c| quicksort_by(c, f64::total_cmp))
+            }
+        );
+        Some(stats)
+    }
+}
+
+/// PSRS merging with another [`MergeStructure`] than the default binary
+/// heap. Only for `Ord` elements, as the config only applies to those.
+struct PsrsMerge(MergeStructure);
+
+impl Sorter for PsrsMerge {
+    fn name(&self) -> &'static str {
+        match self.0 {
+            MergeStructure::BinaryHeap => "psrs_binary_heap",
+            MergeStructure::QuaternaryHeap => "psrs_quaternary_heap",
+            MergeStructure::LoserTree => "psrs_loser_tree",
+        }
+    }
+
+    fn parallel(&self) -> bool {
+        true
+    }
+
+    fn supports(&self, element: ElementType) -> bool {
+        element != ElementType::F64
+    }
+
+    fn sort(&self, data: &mut Dataset, ctx: &SortContext) -> Option<PartitionStats> {
+        let config = PsrsConfig { merge: self.0, ..ctx.config.clone() };
+        let stats = sort_dataset!(
+            data,
+            |v| psrs_with_local_sort(v, &config, quicksort),
+            |_v| unreachable!("f64 is not supported")
+        );
+        Some(stats)
+    }
+}
+
+/// PSRS with the counting-sort fast path for narrow value ranges.
+struct PsrsIntegers;
+
+impl Sorter for PsrsIntegers {
+    fn name(&self) -> &'static str {
+        "psrs_integers"
+    }
+
+    fn parallel(&self) -> bool {
+        true
+    }
+
+    fn supports(&self, element: ElementType) -> bool {
+        matches!(element, ElementType::U32 | ElementType::U64)
+    }
+
+    fn sort(&self, data: &mut Dataset, ctx: &SortContext) -> Option<PartitionStats> {
+        match data {
+            Dataset::U32(v) => psrs_integers(v, ctx.config.chunks()),
+            Dataset::U64(v) => psrs_integers(v, ctx.config.chunks()),
+            _ => unreachable!("psrs_integers only sorts integers"),
+        }
+        None
+    }
+}
+
+/// PSRS with its buffers on huge pages, to compare against [`PsrsStd`] for
+/// the share of TLB misses in large sorts.
+#[cfg(all(feature = "hugepages", target_os = "linux"))]
+struct PsrsHugePages;
+
+#[cfg(all(feature = "hugepages", target_os = "linux"))]
+impl Sorter for PsrsHugePages {
+    fn name(&self) -> &'static str {
+        "psrs_huge_pages"
+    }
+
+    fn parallel(&self) -> bool {
+        true
+    }
+
+    fn supports(&self, element: ElementType) -> bool {
+        element != ElementType::F64
+    }
+
+    fn sort(&self, data: &mut Dataset, ctx: &SortContext) -> Option<PartitionStats> {
+        let alloc = psrs::HugePageAlloc::default();
+        sort_dataset!(data, |v| psrs::psrs_in(v, ctx.config.chunks(), alloc), |_v| unreachable!("f64 is not supported"));
+        None
+    }
+}
+
+struct ParSortUnstable;
+
+impl Sorter for ParSortUnstable {
+    fn name(&self) -> &'static str {
+        "par_sort_unstable"
+    }
+
+    fn parallel(&self) -> bool {
+        true
+    }
+
+    fn sort(&self, data: &mut Dataset, _ctx: &SortContext) -> Option<PartitionStats> {
+        sort_dataset!(data, |v| v.par_sort_unstable(), |v| v.par_sort_unstable_by(f64::total_cmp));
+        None
+    }
+}
+
+/// Whether one of the radix sorts below handles `element`. They sort plain
+/// numbers only; a [`Record`](crate::scenarios::Record) would be ordered by
+/// its key alone, which is not the order the harness checks.
+#[cfg(any(feature = "voracious", feature = "rdxsort"))]
+fn radix_supports(element: ElementType) -> bool {
+    matches!(element, ElementType::U32 | ElementType::U64 | ElementType::F64)
+}
+
+/// `voracious_radix_sort`'s serial sort.
+#[cfg(feature = "voracious")]
+struct Voracious;
+
+#[cfg(feature = "voracious")]
+impl Sorter for Voracious {
+    fn name(&self) -> &'static str {
+        "voracious"
+    }
+
+    fn parallel(&self) -> bool {
+        false
+    }
+
+    fn supports(&self, element: ElementType) -> bool {
+        radix_supports(element)
+    }
+
+    fn sort(&self, data: &mut Dataset, _ctx: &SortContext) -> Option<PartitionStats> {
+        use voracious_radix_sort::RadixSort;
+        match data {
+            Dataset::U32(v) => v.voracious_sort(),
+            Dataset::U64(v) => v.voracious_sort(),
+            Dataset::F64(v) => v.voracious_sort(),
+            _ => unreachable!("voracious only sorts numbers"),
+        }
+        None
+    }
+}
+
+/// `voracious_radix_sort`'s multithreaded sort, on `config.threads`
+/// threads of the harness pool.
+#[cfg(feature = "voracious")]
+struct VoraciousMt;
+
+#[cfg(feature = "voracious")]
+impl Sorter for VoraciousMt {
+    fn name(&self) -> &'static str {
+        "voracious_mt"
+    }
+
+    fn parallel(&self) -> bool {
+        true
+    }
+
+    fn supports(&self, element: ElementType) -> bool {
+        radix_supports(element)
+    }
+
+    fn sort(&self, data: &mut Dataset, ctx: &SortContext) -> Option<PartitionStats> {
+        use voracious_radix_sort::RadixSort;
+        let threads = ctx.config.threads;
+        match data {
+            Dataset::U32(v) => v.voracious_mt_sort(threads),
+            Dataset::U64(v) => v.voracious_mt_sort(threads),
+            Dataset::F64(v) => v.voracious_mt_sort(threads),
+            _ => unreachable!("voracious only sorts numbers"),
+        }
+        None
+    }
+}
+
+/// The `rdxsort` crate's serial radix sort.
+#[cfg(feature = "rdxsort")]
+struct Rdxsort;
+
+#[cfg(feature = "rdxsort")]
+impl Sorter for Rdxsort {
+    fn name(&self) -> &'static str {
+        "rdxsort"
+    }
+
+    fn parallel(&self) -> bool {
+        false
+    }
+
+    fn supports(&self, element: ElementType) -> bool {
+        radix_supports(element)
+    }
+
+    fn sort(&self, data: &mut Dataset, _ctx: &SortContext) -> Option<PartitionStats> {
+        use rdxsort::RdxSort;
+        match data {
+            Dataset::U32(v) => v.rdxsort(),
+            Dataset::U64(v) => v.rdxsort(),
+            Dataset::F64(v) => v.rdxsort(),
+            _ => unreachable!("rdxsort only sorts numbers"),
+        }
+        None
+    }
+}
+
+/// Glidesort, a serial stable merge sort.
+#[cfg(feature = "glidesort")]
+struct Glidesort;
+
+#[cfg(feature = "glidesort")]
+impl Sorter for Glidesort {
+    fn name(&self) -> &'static str {
+        "glidesort"
+    }
+
+    fn parallel(&self) -> bool {
+        false
+    }
+
+    fn sort(&self, data: &mut Dataset, _ctx: &SortContext) -> Option<PartitionStats> {
+        sort_dataset!(data, |v| glidesort::sort(v), |v| glidesort::sort_by(v, f64::total_cmp));
+        None
+    }
+}
@@ -0,0 +1,178 @@
+use std::cmp::Ordering;
+
+/// Below this length a partition is finished with a comparison sort on the
+/// remaining suffixes.
+const MKQS_CUTOFF: usize = 16;
+
+/// Sorts byte strings with multikey quicksort (Bentley & Sedgewick).
+///
+/// Each partitioning step looks at a single byte, and a common prefix is
+/// never compared twice, which makes it much cheaper than a comparison sort
+/// on inputs like log lines that share long prefixes. The order is plain
+/// byte-wise lexicographic order, which matches `Ord` for `str` and `[u8]`.
+pub fn multikey_quicksort<T: AsRef<[u8]>>(data: &mut [T]) {
+    mkqs(data, 0);
+}
+
+/// Byte `depth` of `s`, with `None` (end of string) ordered before any byte.
+fn byte_at<T: AsRef<[u8]>>(s: &T, depth: usize) -> Option<u8> {
+    s.as_ref().get(depth).copied()
+}
+
+fn mkqs<T: AsRef<[u8]>>(mut data: &mut [T], mut depth: usize) {
+    loop {
+        let n = data.len();
+        if n < MKQS_CUTOFF {
+            data.sort_unstable_by(|a, b| a.as_ref()[depth..].cmp(&b.as_ref()[depth..]));
+            return;
+        }
+
+        // Median of three bytes as the pivot.
+        let mut candidates = [
+            byte_at(&data[0], depth),
+            byte_at(&data[n / 2], depth),
+            byte_at(&data[n - 1], depth),
+        ];
+        candidates.sort_unstable();
+        let pivot = candidates[1];
+
+        // Three-way partition: [< pivot | == pivot | > pivot].
+        let (mut lt, mut i, mut gt) = (0, 0, n);
+        while i < gt {
+            match byte_at(&data[i], depth).cmp(&pivot) {
+                Ordering::Less => {
+                    data.swap(lt, i);
+                    lt += 1;
+                    i += 1;
+                }
+                Ordering::Greater => {
+                    gt -= 1;
+                    data.swap(i, gt);
+                }
+                Ordering::Equal => i += 1,
+            }
+        }
+
+        let (less, rest) = data.split_at_mut(lt);
+        let (equal, greater) = rest.split_at_mut(gt - lt);
+        mkqs(less, depth);
+        mkqs(greater, depth);
+
+        // Strings that ended at this depth are all equal; the rest share one
+        // more byte of prefix, so continue with them one level deeper.
+        if pivot.is_none() {
+            return;
+        }
+        data = equal;
+        depth += 1;
+    }
+}
+
+/// Compares byte strings in natural order: runs of ASCII digits compare by
+/// their numeric value, so `file2 < file10` and `v1.9 < v1.10`, and all
+/// other bytes compare as bytes.
+///
+/// Digit runs of any length are compared in place, without parsing or
+/// allocating: leading zeros are skipped, then the longer run is larger,
+/// then the digits decide. Strings that only differ in leading zeros, like
+/// `a1` and `a01`, order the one with fewer zeros first at the first such
+/// run, so the order stays total.
+pub fn natural_cmp(a: &[u8], b: &[u8]) -> Ordering {
+    let (mut i, mut j) = (0, 0);
+    let mut zeros_tiebreak = Ordering::Equal;
+    while i < a.len() && j < b.len() {
+        if a[i].is_ascii_digit() && b[j].is_ascii_digit() {
+            let run_a = digit_run(&a[i..]);
+            let run_b = digit_run(&b[j..]);
+            let zeros_a = run_a.iter().take_while(|&&d| d == b'0').count();
+            let zeros_b = run_b.iter().take_while(|&&d| d == b'0').count();
+            let (value_a, value_b) = (&run_a[zeros_a..], &run_b[zeros_b..]);
+            let order = value_a.len().cmp(&value_b.len()).then_with(|| value_a.cmp(value_b));
+            if order.is_ne() {
+                return order;
+            }
+            if zeros_tiebreak.is_eq() {
+                zeros_tiebreak = zeros_a.cmp(&zeros_b);
+            }
+            i += run_a.len();
+            j += run_b.len();
+        } else {
+            let order = a[i].cmp(&b[j]);
+            if order.is_ne() {
+                return order;
+            }
+            i += 1;
+            j += 1;
+        }
+    }
+    (a.len() - i).cmp(&(b.len() - j)).then(zeros_tiebreak)
+}
+
+/// The ASCII digits `s` starts with.
+fn digit_run(s: &[u8]) -> &[u8] {
+    let len = s.iter().take_while(|b| b.is_ascii_digit()).count();
+    &s[..len]
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::testing::{edge_cases, std_sorted};
+
+    /// Strings sharing long prefixes, with some being prefixes of others.
+    fn log_lines(values: &[u64]) -> Vec<String> {
+        values
+            .iter()
+            .map(|&v| format!("2024-01-01 worker-{} {}", v % 7, v % 1_000).repeat(1 + (v % 2) as usize))
+            .collect()
+    }
+
+    #[test]
+    fn multikey_quicksort_matches_std_sort() {
+        for (name, values) in edge_cases(4) {
+            let mut lines = log_lines(&values);
+            let expected = std_sorted(&lines);
+            multikey_quicksort(&mut lines);
+            assert_eq!(lines, expected, "{name}");
+        }
+    }
+
+    #[test]
+    fn psrs_strings_matches_std_sort() {
+        for p in [1, 4, 7] {
+            for (name, values) in edge_cases(p) {
+                let mut lines = log_lines(&values);
+                let expected = std_sorted(&lines);
+                crate::psrs_strings(&mut lines, p);
+                assert_eq!(lines, expected, "{name}, p = {p}");
+
+                let mut bytes: Vec<Vec<u8>> =
+                    values.iter().map(|v| v.to_le_bytes()[..(v % 9) as usize].to_vec()).collect();
+                let expected = std_sorted(&bytes);
+                crate::psrs_strings(&mut bytes, p);
+                assert_eq!(bytes, expected, "{name} (bytes), p = {p}");
+            }
+        }
+    }
+
+    #[test]
+    fn natural_cmp_orders_digit_runs_by_value() {
+        let mut names = vec!["file10", "file2", "file02", "v1.10", "v1.9", "file1", "a", "file", "file2a", "file002"];
+        names.sort_by(|a, b| natural_cmp(a.as_bytes(), b.as_bytes()));
+        assert_eq!(names, ["a", "file", "file1", "file2", "file02", "file002", "file2a", "file10", "v1.9", "v1.10"]);
+    }
+
+    #[test]
+    fn psrs_natural_matches_a_serial_natural_sort() {
+        for p in [1, 4, 7] {
+            for (name, values) in edge_cases(p) {
+                let mut names: Vec<String> =
+                    values.iter().map(|&v| format!("run{}-{:0w$}", v % 13, v % 500, w = (v % 4) as usize)).collect();
+                let mut expected = names.clone();
+                expected.sort_by(|a, b| natural_cmp(a.as_bytes(), b.as_bytes()));
+                crate::psrs_natural(&mut names, p);
+                assert_eq!(names, expected, "{name}, p = {p}");
+            }
+        }
+    }
+}
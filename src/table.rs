@@ -0,0 +1,99 @@
+use std::cmp::Ordering;
+
+use crate::executor::{self, default_executor};
+use crate::psrs_by;
+
+/// One sort key of [`psrs_table_order`]: a column and its direction.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct SortColumn {
+    /// Index into the `columns` slice.
+    pub column: usize,
+    pub descending: bool,
+}
+
+impl SortColumn {
+    pub fn asc(column: usize) -> Self {
+        SortColumn { column, descending: false }
+    }
+
+    pub fn desc(column: usize) -> Self {
+        SortColumn { column, descending: true }
+    }
+}
+
+/// Returns the row indices of a table stored as equally long `columns`,
+/// ordered lexicographically by the columns in `by`, highest priority
+/// first. Rows equal on every key keep their original order.
+///
+/// The values of the first key column travel through the PSRS phases next
+/// to the row index, so most comparisons never touch the other columns;
+/// ties on it are broken by looking the row up in the remaining ones.
+pub fn psrs_table_order<T>(columns: &[&[T]], by: &[SortColumn], p: usize) -> Vec<usize>
+where
+    T: Clone + Ord + Send + Sync,
+{
+    let n = columns.first().map_or(0, |c| c.len());
+    assert!(columns.iter().all(|c| c.len() == n), "psrs_table_order needs columns of equal length");
+    assert!(by.iter().all(|k| k.column < columns.len()), "sort column out of range");
+    let Some((first, rest)) = by.split_first() else {
+        return (0..n).collect();
+    };
+
+    let exec = default_executor();
+    let chunk_size = executor::even_chunk_size(exec, n);
+    let first_column = columns[first.column];
+    let keyed_chunks = executor::par_chunks_map(exec, first_column, chunk_size, |chunk_idx, chunk| {
+        let offset = chunk_idx * chunk_size;
+        chunk.iter().enumerate().map(|(i, v)| (v.clone(), offset + i)).collect::<Vec<_>>()
+    });
+    let mut keyed: Vec<(T, usize)> = keyed_chunks.into_iter().flatten().collect();
+    psrs_by(&mut keyed, p, |(a, i), (b, j)| {
+        directed(a.cmp(b), first.descending)
+            .then_with(|| {
+                rest.iter()
+                    .map(|k| directed(columns[k.column][*i].cmp(&columns[k.column][*j]), k.descending))
+                    .find(|o| o.is_ne())
+                    .unwrap_or(Ordering::Equal)
+            })
+            .then(i.cmp(j))
+    });
+    keyed.into_iter().map(|(_, i)| i).collect()
+}
+
+fn directed(order: Ordering, descending: bool) -> Ordering {
+    if descending {
+        order.reverse()
+    } else {
+        order
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::testing::edge_cases;
+
+    #[test]
+    fn table_order_matches_a_stable_sort_of_the_rows() {
+        for p in [1, 4, 7] {
+            for (name, values) in edge_cases(p) {
+                // Few distinct values per column, so later keys break ties.
+                let a: Vec<u64> = values.iter().map(|v| v % 3).collect();
+                let b: Vec<u64> = values.iter().map(|v| v / 3 % 4).collect();
+                let c: Vec<u64> = values.iter().map(|v| v / 12 % 5).collect();
+                let columns: [&[u64]; 3] = [&a, &b, &c];
+                let by = [SortColumn::desc(1), SortColumn::asc(2), SortColumn::desc(0)];
+
+                let mut expected: Vec<usize> = (0..values.len()).collect();
+                expected.sort_by(|&i, &j| b[j].cmp(&b[i]).then(c[i].cmp(&c[j])).then(a[j].cmp(&a[i])));
+                assert_eq!(psrs_table_order(&columns, &by, p), expected, "{name}, p = {p}");
+            }
+        }
+    }
+
+    #[test]
+    fn no_sort_columns_keeps_the_rows() {
+        let column = [3, 1, 2];
+        assert_eq!(psrs_table_order(&[&column[..]], &[], 2), [0, 1, 2]);
+    }
+}
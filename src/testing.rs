@@ -0,0 +1,47 @@
+//! Inputs shared by the unit tests.
+
+/// `n` pseudo-random values below `bound` (xorshift64*, so tests need no
+/// `rand` and reproduce exactly).
+pub(crate) fn random_values(n: usize, bound: u64, seed: u64) -> Vec<u64> {
+    let mut state = seed.wrapping_mul(0x9E37_79B9_7F4A_7C15) | 1;
+    (0..n)
+        .map(|_| {
+            state ^= state >> 12;
+            state ^= state << 25;
+            state ^= state >> 27;
+            state.wrapping_mul(0x2545_F491_4F6C_DD1D) % bound.max(1)
+        })
+        .collect()
+}
+
+/// The edge-case inputs every sort is checked on, by name: empty, shorter
+/// than the chunk count `p`, all equal, few distinct values, and random.
+pub(crate) fn edge_cases(p: usize) -> Vec<(&'static str, Vec<u64>)> {
+    vec![
+        ("empty", Vec::new()),
+        ("single", vec![7]),
+        ("n < p", random_values(p.saturating_sub(1), u64::MAX, 1)),
+        ("n % p != 0", random_values(10 * p + 3, u64::MAX, 2)),
+        ("all equal", vec![42; 5_000]),
+        ("few distinct", random_values(20_000, 3, 3)),
+        ("sorted", (0..3_000).collect()),
+        ("reverse", (0..3_000).rev().collect()),
+        ("random", random_values(50_000, u64::MAX, 4)),
+    ]
+}
+
+/// `data` sorted with `slice::sort`, the oracle of the tests.
+pub(crate) fn std_sorted<T: Ord + Clone>(data: &[T]) -> Vec<T> {
+    let mut sorted = data.to_vec();
+    sorted.sort();
+    sorted
+}
+
+/// An empty directory of its own for test `name`, under the system
+/// temporary directory.
+pub(crate) fn scratch_dir(name: &str) -> std::path::PathBuf {
+    let dir = std::env::temp_dir().join(format!("psrs-test-{}-{name}", std::process::id()));
+    let _ = std::fs::remove_dir_all(&dir);
+    std::fs::create_dir_all(&dir).expect("cannot create a scratch directory");
+    dir
+}
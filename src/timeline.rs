@@ -0,0 +1,151 @@
+use std::collections::HashMap;
+use std::io::{self, Write};
+use std::sync::Mutex;
+use std::thread::{self, ThreadId};
+use std::time::{Duration, Instant};
+
+use crate::Phase;
+
+/// One timed step of a PSRS run: a whole phase, or a single task of one.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct TimelineEvent {
+    pub phase: Phase,
+    /// The chunk sorted in [`Phase::LocalSort`] or the merge task in
+    /// [`Phase::Merge`]; `None` for the phase as a whole.
+    pub task: Option<usize>,
+    /// The thread that ran the task, numbered in the order threads first
+    /// ran one; `None` for the phase as a whole.
+    pub worker: Option<usize>,
+    /// Elements the task sorted or merged; 0 for the phase as a whole.
+    pub len: usize,
+    /// Since the [`Timeline`] was created.
+    pub start: Duration,
+    pub end: Duration,
+}
+
+/// Records when every phase and, per worker thread, every chunk sort and
+/// merge task of a run started and finished, for
+/// [`psrs_with_timeline`](crate::psrs_with_timeline).
+///
+/// Aggregate phase times don't show which worker holds a phase up; the
+/// per-task events do, and [`Timeline::write_chrome_trace`] lays them out
+/// one track per worker in `chrome://tracing` or Perfetto.
+#[derive(Debug)]
+pub struct Timeline {
+    origin: Instant,
+    state: Mutex<TimelineState>,
+}
+
+#[derive(Debug, Default)]
+struct TimelineState {
+    events: Vec<TimelineEvent>,
+    workers: HashMap<ThreadId, usize>,
+}
+
+impl Default for Timeline {
+    fn default() -> Self {
+        Timeline::new()
+    }
+}
+
+impl Timeline {
+    /// An empty timeline; event times count from now.
+    pub fn new() -> Self {
+        Timeline { origin: Instant::now(), state: Mutex::default() }
+    }
+
+    /// Runs `f`, recording it as `task` of `phase` (or the whole phase if
+    /// `None`) on the calling thread.
+    pub(crate) fn record<R>(&self, phase: Phase, task: Option<usize>, len: usize, f: impl FnOnce() -> R) -> R {
+        let start = self.origin.elapsed();
+        let result = f();
+        let end = self.origin.elapsed();
+        let mut state = self.state.lock().unwrap();
+        let worker = task.map(|_| {
+            let next = state.workers.len();
+            *state.workers.entry(thread::current().id()).or_insert(next)
+        });
+        state.events.push(TimelineEvent { phase, task, worker, len, start, end });
+        result
+    }
+
+    /// Every recorded event, by start time.
+    pub fn events(&self) -> Vec<TimelineEvent> {
+        let mut events = self.state.lock().unwrap().events.clone();
+        events.sort_by_key(|event| event.start);
+        events
+    }
+
+    /// Writes the events in Chrome's `trace_event` JSON format: the phases
+    /// on one track and the tasks of every worker on a track of its own.
+    pub fn write_chrome_trace(&self, mut out: impl Write) -> io::Result<()> {
+        let events = self.events();
+        let workers = events.iter().filter_map(|event| event.worker).max().map_or(0, |max| max + 1);
+        write!(out, "{{\"displayTimeUnit\":\"ms\",\"traceEvents\":[")?;
+        write!(out, "\n{{\"name\":\"thread_name\",\"ph\":\"M\",\"pid\":1,\"tid\":0,\"args\":{{\"name\":\"phases\"}}}}")?;
+        for worker in 0..workers {
+            write!(
+                out,
+                ",\n{{\"name\":\"thread_name\",\"ph\":\"M\",\"pid\":1,\"tid\":{},\"args\":{{\"name\":\"worker {worker}\"}}}}",
+                worker + 1
+            )?;
+        }
+        for event in &events {
+            let name = match (event.phase, event.task) {
+                (Phase::LocalSort, Some(task)) => format!("sort chunk {task}"),
+                (Phase::Merge, Some(task)) => format!("merge {task}"),
+                (phase, _) => format!("{phase:?}"),
+            };
+            write!(
+                out,
+                ",\n{{\"name\":\"{name}\",\"cat\":\"{:?}\",\"ph\":\"X\",\"ts\":{:.3},\"dur\":{:.3},\"pid\":1,\"tid\":{},\"args\":{{\"len\":{}}}}}",
+                event.phase,
+                event.start.as_secs_f64() * 1e6,
+                (event.end - event.start).as_secs_f64() * 1e6,
+                event.worker.map_or(0, |worker| worker + 1),
+                event.len
+            )?;
+        }
+        writeln!(out, "\n]}}")
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::testing::edge_cases;
+    use crate::{psrs_with_timeline, PsrsConfig};
+
+    #[test]
+    fn timeline_records_every_task() {
+        let config = PsrsConfig { min_partition_size: 0, ..PsrsConfig::with_threads(4) };
+        for (name, mut data) in edge_cases(4) {
+            let timeline = Timeline::new();
+            psrs_with_timeline(&mut data, &config, &timeline);
+
+            let events = timeline.events();
+            assert!(events.windows(2).all(|w| w[0].start <= w[1].start), "{name}");
+            assert!(events.iter().all(|e| e.start <= e.end && e.task.is_some() == e.worker.is_some()), "{name}");
+            let task_lens = |phase| events.iter().filter(|e| e.phase == phase && e.task.is_some()).map(|e| e.len).sum();
+            let (sorted, merged): (usize, usize) = (task_lens(Phase::LocalSort), task_lens(Phase::Merge));
+            // Serial runs record no tasks.
+            if events.iter().any(|e| e.task.is_some()) {
+                assert_eq!((sorted, merged), (data.len(), data.len()), "{name}");
+            }
+        }
+    }
+
+    #[test]
+    fn chrome_trace_has_a_track_per_worker() {
+        let timeline = Timeline::new();
+        let mut data = crate::testing::random_values(50_000, u64::MAX, 22);
+        psrs_with_timeline(&mut data, &PsrsConfig::with_threads(4), &timeline);
+        let mut trace = Vec::new();
+        timeline.write_chrome_trace(&mut trace).unwrap();
+        let trace = String::from_utf8(trace).unwrap();
+        let workers = timeline.events().iter().filter_map(|e| e.worker).max().unwrap() + 1;
+        assert_eq!(trace.matches("\"thread_name\"").count(), workers + 1);
+        assert_eq!(trace.matches("\"ph\":\"X\"").count(), timeline.events().len());
+        assert!(trace.starts_with('{') && trace.trim_end().ends_with("]}"));
+    }
+}
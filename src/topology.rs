@@ -0,0 +1,101 @@
+use std::fs;
+use std::sync::OnceLock;
+
+/// Cache sizes of the machine, in bytes, as used by
+/// [`PsrsConfig::tuned_for`](crate::PsrsConfig::tuned_for).
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct CacheSizes {
+    /// Per-core unified L2.
+    pub l2: usize,
+    /// Shared last-level cache.
+    pub l3: usize,
+}
+
+impl Default for CacheSizes {
+    /// Conservative sizes for when detection fails.
+    fn default() -> Self {
+        CacheSizes { l2: 1 << 20, l3: 8 << 20 }
+    }
+}
+
+impl CacheSizes {
+    /// Reads the cache sizes of CPU 0 from sysfs on Linux, falling back to
+    /// [`CacheSizes::default`] for any level it cannot find. Detected once
+    /// per process.
+    pub fn detect() -> CacheSizes {
+        static DETECTED: OnceLock<CacheSizes> = OnceLock::new();
+        *DETECTED.get_or_init(|| {
+            let mut sizes = CacheSizes::default();
+            for index in 0.. {
+                let dir = format!("/sys/devices/system/cpu/cpu0/cache/index{index}");
+                let read = |file: &str| fs::read_to_string(format!("{dir}/{file}")).ok();
+                let Some(level) = read("level") else { break };
+                if read("type").is_some_and(|t| t.trim() == "Instruction") {
+                    continue;
+                }
+                match (level.trim(), read("size").as_deref().and_then(parse_size)) {
+                    ("2", Some(size)) => sizes.l2 = size,
+                    ("3", Some(size)) => sizes.l3 = size,
+                    _ => {}
+                }
+            }
+            sizes
+        })
+    }
+}
+
+/// Parses sysfs sizes like `2048K` or `32M`.
+fn parse_size(text: &str) -> Option<usize> {
+    let text = text.trim();
+    let (digits, unit) = match text.char_indices().find(|(_, c)| !c.is_ascii_digit()) {
+        Some((i, _)) => text.split_at(i),
+        None => (text, ""),
+    };
+    let multiplier = match unit {
+        "" => 1,
+        "K" => 1 << 10,
+        "M" => 1 << 20,
+        "G" => 1 << 30,
+        _ => return None,
+    };
+    digits.parse::<usize>().ok().map(|n| n * multiplier)
+}
+
+/// Relative speed of every CPU, indexed by CPU number, for
+/// [`PsrsConfig::core_weights`](crate::PsrsConfig::core_weights). Read from
+/// sysfs on Linux: `cpu_capacity` where the kernel reports it (ARM
+/// big.LITTLE), else the maximum frequency in MHz, which tells hybrid x86
+/// performance and efficiency cores apart. Empty when neither is available.
+/// Detected once per process.
+pub fn core_weights() -> &'static [u32] {
+    static DETECTED: OnceLock<Vec<u32>> = OnceLock::new();
+    DETECTED.get_or_init(|| {
+        let read = |cpu: usize, file: &str| {
+            let text = fs::read_to_string(format!("/sys/devices/system/cpu/cpu{cpu}/{file}")).ok()?;
+            text.trim().parse::<u32>().ok()
+        };
+        let mut weights = Vec::new();
+        for cpu in 0.. {
+            match read(cpu, "cpu_capacity").or_else(|| read(cpu, "cpufreq/cpuinfo_max_freq").map(|khz| khz / 1000)) {
+                Some(weight) => weights.push(weight.max(1)),
+                None => break,
+            }
+        }
+        weights
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn sysfs_sizes_parse() {
+        assert_eq!(parse_size("2048K\n"), Some(2 << 20));
+        assert_eq!(parse_size("32M"), Some(32 << 20));
+        assert_eq!(parse_size("1G"), Some(1 << 30));
+        assert_eq!(parse_size("512"), Some(512));
+        assert_eq!(parse_size("12Q"), None);
+        assert_eq!(parse_size("K"), None);
+    }
+}
@@ -0,0 +1,96 @@
+//! `trace` subcommand: sorts a generated dataset once while recording a
+//! [`Timeline`], and writes it as a Chrome trace to open in
+//! `chrome://tracing` or Perfetto, with one track per worker.
+
+use std::fs::File;
+use std::io::BufWriter;
+
+use psrs::{psrs_with_timeline, PsrsConfig, Timeline, TimelineEvent};
+
+use crate::datagen::{generate_data, Distribution};
+
+/// Options of `trace`, see [`usage`].
+struct TraceArgs {
+    len: usize,
+    out: String,
+    threads: usize,
+    oversubscription: usize,
+    distribution: Distribution,
+    seed: u64,
+}
+
+fn usage() -> ! {
+    eprintln!(
+        "usage: trace --len N --out trace.json [--threads N] [--oversubscription N] \
+         [--distribution uniform|zipf|nearly_sorted] [--seed N]\n\
+         Sorts N generated u32 values with PSRS and writes when each phase, chunk sort and \
+         merge task ran, per worker, in Chrome's trace_event format."
+    );
+    std::process::exit(2)
+}
+
+fn parse_args(mut args: impl Iterator<Item = String>) -> TraceArgs {
+    let (mut len, mut out) = (None, None);
+    let mut parsed = TraceArgs {
+        len: 0,
+        out: String::new(),
+        threads: std::thread::available_parallelism().map_or(1, |n| n.get()),
+        oversubscription: 1,
+        distribution: Distribution::Uniform,
+        seed: rand::random(),
+    };
+    while let Some(arg) = args.next() {
+        let mut value = || args.next().unwrap_or_else(|| usage());
+        match arg.as_str() {
+            "--len" => len = Some(value().parse().unwrap_or_else(|_| usage())),
+            "--out" => out = Some(value()),
+            "--threads" => parsed.threads = value().parse().unwrap_or_else(|_| usage()),
+            "--oversubscription" => parsed.oversubscription = value().parse().unwrap_or_else(|_| usage()),
+            "--distribution" => {
+                parsed.distribution = match value().as_str() {
+                    "uniform" => Distribution::Uniform,
+                    "zipf" => Distribution::Zipf,
+                    "nearly_sorted" => Distribution::NearlySorted,
+                    _ => usage(),
+                }
+            }
+            "--seed" => parsed.seed = value().parse().unwrap_or_else(|_| usage()),
+            _ => usage(),
+        }
+    }
+    let (Some(len), Some(out)) = (len, out) else { usage() };
+    parsed.len = len;
+    parsed.out = out;
+    parsed
+}
+
+/// Runs `trace` with the arguments that follow the subcommand name.
+pub fn main(args: impl Iterator<Item = String>) {
+    let args = parse_args(args);
+    let mut data = generate_data(args.len, 0, u32::MAX, args.distribution, args.seed);
+    let config = PsrsConfig { oversubscription: args.oversubscription, ..PsrsConfig::with_threads(args.threads) };
+    let pool = rayon::ThreadPoolBuilder::new().num_threads(args.threads).build().expect("failed to build thread pool");
+    let timeline = Timeline::new();
+    pool.install(|| psrs_with_timeline(&mut data, &config, &timeline));
+
+    let file = File::create(&args.out).unwrap_or_else(|e| {
+        eprintln!("trace: {}: {e}", args.out);
+        std::process::exit(1);
+    });
+    timeline.write_chrome_trace(BufWriter::new(file)).expect("failed to write the trace");
+
+    // The phases, and for the task phases the task that finished last.
+    let events = timeline.events();
+    let ms = |event: &TimelineEvent| (event.end - event.start).as_secs_f64() * 1e3;
+    for phase in events.iter().filter(|event| event.task.is_none()) {
+        print!("{:?}\t{:.3} ms", phase.phase, ms(phase));
+        let tasks = events.iter().filter(|event| event.phase == phase.phase && event.task.is_some());
+        if let Some(last) = tasks.max_by_key(|event| event.end) {
+            let task = last.task.unwrap_or_default();
+            let worker = last.worker.unwrap_or_default();
+            print!("\tlast task {task} on worker {worker}: {:.3} ms, {} elements", ms(last), last.len);
+        }
+        println!();
+    }
+    println!("seed {}; trace written to {}", args.seed, args.out);
+}
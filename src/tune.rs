@@ -0,0 +1,155 @@
+//! `tune` subcommand: measures which PSRS settings are fastest on this
+//! machine and writes them to a [`TuningProfile`], which
+//! [`PsrsConfig::tuned_for`] then picks up.
+//!
+//! For every size it times each combination of oversubscription,
+//! oversampling and merge structure on uniform `u32`s. The serial cutoff
+//! is the smallest of a ladder of sizes where PSRS beats the serial sort.
+
+use std::path::PathBuf;
+use std::time::Instant;
+
+use psrs::{psrs_with_config, MergeStructure, PsrsConfig, TunedEntry, TuningProfile};
+
+use crate::datagen::{generate_data, Distribution};
+
+const OVERSUBSCRIPTIONS: [usize; 4] = [1, 2, 4, 8];
+const OVERSAMPLINGS: [usize; 3] = [1, 2, 4];
+const MERGES: [MergeStructure; 3] =
+    [MergeStructure::BinaryHeap, MergeStructure::QuaternaryHeap, MergeStructure::LoserTree];
+/// Sizes the serial cutoff is searched among.
+const CUTOFF_LADDER: [usize; 6] = [1 << 10, 1 << 12, 1 << 14, 1 << 16, 1 << 18, 1 << 20];
+
+/// Options of `tune`, see [`usage`].
+struct TuneArgs {
+    threads: usize,
+    sizes: Vec<usize>,
+    runs: usize,
+    out: PathBuf,
+}
+
+fn usage() -> ! {
+    eprintln!(
+        "usage: tune [--threads N] [--sizes N,N,...] [--runs N] [--out profile.json]\n\
+         Times PSRS settings on N uniform u32s for each size and writes the fastest to \
+         the profile, by default {} (also read from ${}).",
+        TuningProfile::default_path().map_or("<none>".to_string(), |p| p.display().to_string()),
+        psrs::PROFILE_ENV
+    );
+    std::process::exit(2)
+}
+
+fn parse_args(mut args: impl Iterator<Item = String>) -> TuneArgs {
+    let mut out = None;
+    let mut parsed = TuneArgs {
+        threads: std::thread::available_parallelism().map_or(1, |n| n.get()),
+        sizes: vec![1 << 16, 1 << 20, 1 << 23],
+        runs: 3,
+        out: PathBuf::new(),
+    };
+    while let Some(arg) = args.next() {
+        let mut value = || args.next().unwrap_or_else(|| usage());
+        match arg.as_str() {
+            "--threads" => parsed.threads = value().parse().unwrap_or_else(|_| usage()),
+            "--sizes" => {
+                parsed.sizes = value().split(',').map(|s| s.trim().parse().unwrap_or_else(|_| usage())).collect()
+            }
+            "--runs" => parsed.runs = value().parse().unwrap_or_else(|_| usage()),
+            "--out" => out = Some(PathBuf::from(value())),
+            _ => usage(),
+        }
+    }
+    parsed.out = out.or_else(TuningProfile::default_path).unwrap_or_else(|| usage());
+    parsed
+}
+
+/// Runs `tune` with the arguments that follow the subcommand name.
+pub fn main(args: impl Iterator<Item = String>) {
+    let args = parse_args(args);
+    let pool = rayon::ThreadPoolBuilder::new().num_threads(args.threads).build().expect("failed to build thread pool");
+    pool.install(|| {
+        let serial_cutoff = find_serial_cutoff(&args);
+        println!("serial cutoff: {serial_cutoff} elements");
+        println!("n\toversubscription\toversampling\tmerge\tmedian ms\tdefault ms");
+        let mut profile = TuningProfile::default();
+        for &n in &args.sizes {
+            let data = generate_data(n, 0, u32::MAX, Distribution::Uniform, 0);
+            let default_ms = median_ms(&data, &PsrsConfig::with_threads(args.threads), args.runs);
+            let mut best: Option<(f64, PsrsConfig)> = None;
+            for config in grid(args.threads, n) {
+                let ms = median_ms(&data, &config, args.runs);
+                if best.as_ref().is_none_or(|(best_ms, _)| ms < *best_ms) {
+                    best = Some((ms, config));
+                }
+            }
+            let (ms, config) = best.expect("the grid always holds the default config");
+            println!(
+                "{n}\t{}\t{}\t{:?}\t{ms:.3}\t{default_ms:.3}",
+                config.oversubscription, config.oversampling, config.merge
+            );
+            profile.entries.push(TunedEntry {
+                threads: args.threads,
+                bytes: n * size_of::<u32>(),
+                oversubscription: config.oversubscription,
+                oversampling: config.oversampling,
+                merge: config.merge,
+                serial_cutoff,
+            });
+        }
+        profile.save(&args.out).expect("failed to write profile");
+        println!("wrote {}", args.out.display());
+    });
+}
+
+/// Every config to time on `n` elements, leaving out those that would
+/// sample more elements than the input holds, except for one chunk per
+/// thread sampled once, the default.
+fn grid(threads: usize, n: usize) -> impl Iterator<Item = PsrsConfig> {
+    OVERSUBSCRIPTIONS.into_iter().flat_map(move |oversubscription| {
+        OVERSAMPLINGS.into_iter().flat_map(move |oversampling| {
+            MERGES.into_iter().map(move |merge| PsrsConfig {
+                oversubscription,
+                oversampling,
+                merge,
+                ..PsrsConfig::with_threads(threads)
+            })
+        })
+    })
+    .filter(move |config| {
+        let is_default = config.oversubscription == 1 && config.oversampling == 1;
+        is_default || config.chunks().pow(2) * config.oversampling <= n
+    })
+}
+
+/// The smallest size on [`CUTOFF_LADDER`] where the default PSRS config
+/// beats a serial sort, or one step past the ladder if it never does.
+fn find_serial_cutoff(args: &TuneArgs) -> usize {
+    if args.threads <= 1 {
+        return 0;
+    }
+    CUTOFF_LADDER
+        .into_iter()
+        .find(|&n| {
+            let data = generate_data(n, 0, u32::MAX, Distribution::Uniform, 0);
+            let serial = median_ms(&data, &PsrsConfig::with_threads(1), args.runs);
+            let parallel = median_ms(&data, &PsrsConfig::with_threads(args.threads), args.runs);
+            parallel < serial
+        })
+        .unwrap_or(CUTOFF_LADDER[CUTOFF_LADDER.len() - 1] * 4)
+}
+
+/// Median milliseconds of `runs` sorts of copies of `data` with `config`,
+/// after one untimed warm-up.
+fn median_ms(data: &[u32], config: &PsrsConfig, runs: usize) -> f64 {
+    let mut times: Vec<f64> = (0..=runs.max(1))
+        .map(|_| {
+            let mut copy = data.to_vec();
+            let start = Instant::now();
+            psrs_with_config(&mut copy, config);
+            start.elapsed().as_secs_f64() * 1000.0
+        })
+        .skip(1)
+        .collect();
+    times.sort_by(f64::total_cmp);
+    times[times.len() / 2]
+}
@@ -0,0 +1,35 @@
+//! WebAssembly bindings for sorting TypedArrays in the browser.
+//!
+//! Rayon needs real threads, which on the web means a pool of Web Workers
+//! sharing the module's memory. JavaScript must `await initThreadPool(n)`
+//! once before calling any sort function. Build with atomics enabled, e.g.
+//!
+//! ```text
+//! RUSTFLAGS='-C target-feature=+atomics,+bulk-memory' \
+//!     rustup run nightly wasm-pack build --target web -- \
+//!     --features wasm -Z build-std=panic_abort,std
+//! ```
+
+use wasm_bindgen::prelude::*;
+
+pub use wasm_bindgen_rayon::init_thread_pool;
+
+use crate::{psrs_f64, psrs_radix};
+
+/// Sorts a `Uint32Array` with PSRS using `p` chunks.
+#[wasm_bindgen(js_name = sortU32)]
+pub fn sort_u32(data: &mut [u32], p: usize) {
+    psrs_radix(data, p);
+}
+
+/// Sorts a `BigUint64Array` with PSRS using `p` chunks.
+#[wasm_bindgen(js_name = sortU64)]
+pub fn sort_u64(data: &mut [u64], p: usize) {
+    psrs_radix(data, p);
+}
+
+/// Sorts a `Float64Array` with PSRS using `p` chunks, in `f64::total_cmp` order.
+#[wasm_bindgen(js_name = sortF64)]
+pub fn sort_f64(data: &mut [f64], p: usize) {
+    psrs_f64(data, p);
+}